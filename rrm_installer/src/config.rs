@@ -0,0 +1,825 @@
+//! Layered configuration. Each layer overrides the ones before it: the defaults, the system file,
+//! the user file, a `.rrm.toml` next to a modpack, `RRM_*` environment variables and finally
+//! `--config KEY=VALUE` flags. Only the user file is ever written.
+
+use crate::{config_dir, get_or_create_config_dir, DEFAULT_PAGING_SOFTWARE};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::{Table, Value};
+
+/// Version of the layout of `config.toml`, bumped whenever a migration is needed
+pub const SCHEMA_VERSION: i64 = 2;
+
+pub const DEFAULT_CACHE_TTL: &str = "1h";
+
+pub const DEFAULT_HTTP_TIMEOUT: &str = "30s";
+
+/// The file that holds the configuration of a single modpack
+pub const PROJECT_FILE: &str = ".rrm.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Path,
+    Bool,
+    String,
+    /// A string that is not shown by `rrm config list`
+    Secret,
+    /// Like `90s`, `30m`, `6h` or `7d`
+    Duration,
+    /// A whole number, 0 or more
+    Integer,
+    /// Like `http://host:port`
+    Url,
+}
+
+/// The only settings read from a `.rrm.toml`. It comes with whatever modpack was cloned, so it
+/// may not choose folders, programs, accounts, the proxy or the options the game runs with
+const IN_PROJECT: &[&str] = &[
+    "use-pager",
+    "offline",
+    "cache-ttl",
+    "http-timeout",
+    "http-retries",
+    "http-concurrency",
+    "http-rate-limit",
+];
+
+/// A setting that `rrm config` can read and change
+#[derive(Debug)]
+pub struct Key {
+    pub name: &'static str,
+    pub env: &'static str,
+    pub description: &'static str,
+    kind: Kind,
+}
+
+pub const KEYS: &[Key] = &[
+    Key {
+        name: "game-path",
+        env: "RRM_GAME_PATH",
+        description: "Where RimWorld is installed",
+        kind: Kind::Path,
+    },
+    Key {
+        name: "use-pager",
+        env: "RRM_USE_PAGER",
+        description: "Whether long output goes through the pager [values: true, false]",
+        kind: Kind::Bool,
+    },
+    Key {
+        name: "pager",
+        env: "RRM_PAGER",
+        description: "The paging software to use, like bat, more or less",
+        kind: Kind::String,
+    },
+    Key {
+        name: "instance",
+        env: "RRM_INSTANCE",
+        description: "The instance commands run against, see `rrm instance`",
+        kind: Kind::String,
+    },
+    Key {
+        name: "steam-api-key",
+        env: "RRM_STEAM_API_KEY",
+        description: "A Steam Web API key, to search the Workshop through the API",
+        kind: Kind::Secret,
+    },
+    Key {
+        name: "offline",
+        env: "RRM_OFFLINE",
+        description: "Only use cached Workshop answers and local mods [values: true, false]",
+        kind: Kind::Bool,
+    },
+    Key {
+        name: "cache-ttl",
+        env: "RRM_CACHE_TTL",
+        description: "How long Workshop answers are cached, like 30m, 6h or 0 to always ask",
+        kind: Kind::Duration,
+    },
+    Key {
+        name: "http-timeout",
+        env: "RRM_HTTP_TIMEOUT",
+        description: "How long a request to Steam can take, like 30s or 2m",
+        kind: Kind::Duration,
+    },
+    Key {
+        name: "http-retries",
+        env: "RRM_HTTP_RETRIES",
+        description: "How many times a failed or throttled request to Steam is sent again",
+        kind: Kind::Integer,
+    },
+    Key {
+        name: "http-concurrency",
+        env: "RRM_HTTP_CONCURRENCY",
+        description: "How many requests to Steam can be waiting for an answer at once",
+        kind: Kind::Integer,
+    },
+    Key {
+        name: "http-rate-limit",
+        env: "RRM_HTTP_RATE_LIMIT",
+        description: "How many requests to Steam are started per second, 0 for no limit",
+        kind: Kind::Integer,
+    },
+    Key {
+        name: "http-proxy",
+        env: "RRM_HTTP_PROXY",
+        description: "The proxy requests to Steam go through, like http://proxy:3128",
+        kind: Kind::Url,
+    },
+    Key {
+        name: "user-agent",
+        env: "RRM_USER_AGENT",
+        description: "The User-Agent header sent to Steam",
+        kind: Kind::String,
+    },
+    Key {
+        name: "steamcmd-path",
+        env: "RRM_STEAMCMD_PATH",
+        description: "A steamcmd to use instead of the one rrm downloads, like /usr/games/steamcmd",
+        kind: Kind::Path,
+    },
+    Key {
+        name: "steamcmd-mirror",
+        env: "RRM_STEAMCMD_MIRROR",
+        description: "Where the steamcmd archive is downloaded from",
+        kind: Kind::Url,
+    },
+    Key {
+        name: "steamcmd-sha256",
+        env: "RRM_STEAMCMD_SHA256",
+        description: "The SHA-256 the downloaded steamcmd archive must have",
+        kind: Kind::String,
+    },
+    Key {
+        name: "steam-username",
+        env: "RRM_STEAM_USERNAME",
+        description: "The Steam account steamcmd logs in with, instead of anonymously",
+        kind: Kind::String,
+    },
+    Key {
+        name: "steam-password-command",
+        env: "RRM_STEAM_PASSWORD_COMMAND",
+        description: "A command printing the password of steam-username, like a keyring lookup",
+        kind: Kind::String,
+    },
+    Key {
+        name: "download-dir",
+        env: "RRM_DOWNLOAD_DIR",
+        description:
+            "Where steamcmd downloads mods before they are moved, by default in the temp dir",
+        kind: Kind::Path,
+    },
+    Key {
+        name: "user-data-dir",
+        env: "RRM_USER_DATA_DIR",
+        description: "Where RimWorld keeps Config/ and Saves/, when not in the default location",
+        kind: Kind::Path,
+    },
+    Key {
+        name: "launch-args",
+        env: "RRM_LAUNCH_ARGS",
+        description: "The options RimWorld is started with, like -savedatafolder=SaveData",
+        kind: Kind::String,
+    },
+];
+
+/// Splits launch options on spaces, except inside double quotes, which are removed
+pub fn split_args(value: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    result.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        result.push(current);
+    }
+    result
+}
+
+/// Parses durations like `90s`, `30m`, `6h` or `7d`. A plain number is in seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("\"{value}\" is not a duration like 30m, 6h or 7d"))?;
+    let secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("\"{value}\" is not a duration like 30m, 6h or 7d")),
+    };
+
+    Ok(Duration::from_secs(number * secs))
+}
+
+pub fn key(name: &str) -> Option<&'static Key> {
+    KEYS.iter().find(|k| k.name == name)
+}
+
+impl Key {
+    pub fn parse(&self, value: &str) -> Result<Value, String> {
+        match self.kind {
+            Kind::Bool => match value.trim() {
+                "true" | "1" => Ok(Value::Boolean(true)),
+                "false" | "0" => Ok(Value::Boolean(false)),
+                _ => Err(format!(
+                    "{} must be true, false, 1 or 0, not \"{value}\"",
+                    self.name
+                )),
+            },
+            Kind::Path | Kind::String | Kind::Secret | Kind::Url if value.trim().is_empty() => {
+                Err(format!("{} can not be empty", self.name))
+            }
+            Kind::Path | Kind::String | Kind::Secret => Ok(Value::String(value.to_string())),
+            Kind::Duration => parse_duration(value)
+                .map(|_| Value::String(value.trim().to_string()))
+                .map_err(|err| format!("{}: {err}", self.name)),
+            Kind::Integer => value
+                .trim()
+                .parse::<u32>()
+                .map(|n| Value::Integer(n.into()))
+                .map_err(|_| format!("{} must be a whole number, not \"{value}\"", self.name)),
+            Kind::Url => match value.trim().split_once("://") {
+                Some((scheme, host)) if !scheme.is_empty() && !host.is_empty() => {
+                    Ok(Value::String(value.trim().to_string()))
+                }
+                _ => Err(format!(
+                    "{} must be a URL like http://host:port, not \"{value}\"",
+                    self.name
+                )),
+            },
+        }
+    }
+
+    pub fn is_secret(&self) -> bool {
+        self.kind == Kind::Secret
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Default,
+    System,
+    User,
+    Project,
+    Env,
+    Cli,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::System => write!(f, "system"),
+            Source::User => write!(f, "user"),
+            Source::Project => write!(f, "project"),
+            Source::Env => write!(f, "env"),
+            Source::Cli => write!(f, "--config"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub source: Source,
+    /// The file the layer was read from, if any
+    pub path: Option<PathBuf>,
+    pub table: Table,
+}
+
+impl Layer {
+    fn new(source: Source, path: Option<PathBuf>, table: Table) -> Self {
+        Layer {
+            source,
+            path,
+            table,
+        }
+    }
+
+    /// Where the value of `key` in this layer was set, like a file or an environment variable
+    pub fn describe(&self, key: &str) -> String {
+        match (&self.path, self.source) {
+            (Some(path), _) => format!("{} ({})", self.source, path.display()),
+            (None, Source::Env) => crate::config::key(key)
+                .map(|k| format!("env ({})", k.env))
+                .unwrap_or_else(|| "env".to_string()),
+            (None, source) => source.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Layers(pub Vec<Layer>);
+
+impl Layers {
+    /// Reads every layer, migrating the user file to the current schema if needed
+    pub fn load(cli: &[(String, String)]) -> Result<Layers, String> {
+        let mut layers = vec![Layer::new(Source::Default, None, defaults())];
+
+        let system = system_config_path();
+        if let Some(table) = read_file(&system)? {
+            layers.push(Layer::new(Source::System, Some(system), table));
+        }
+
+        layers.push(Layer::new(
+            Source::User,
+            Some(user_config_path()),
+            load_user()?,
+        ));
+
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| find_project_config(&dir));
+        if let Some(project) = project {
+            if let Some(mut table) = read_file(&project)? {
+                for name in remove_untrusted(&mut table) {
+                    eprintln!(
+                        "Warning: Ignoring {name} in \"{}\", set it with `rrm config set` instead",
+                        project.display()
+                    );
+                }
+                layers.push(Layer::new(Source::Project, Some(project), table));
+            }
+        }
+
+        layers.push(Layer::new(Source::Env, None, env_table()));
+
+        let mut table = Table::new();
+        for (name, value) in cli {
+            let key = key(name).ok_or_else(|| unknown_key(name))?;
+            table.insert(name.clone(), key.parse(value)?);
+        }
+        layers.push(Layer::new(Source::Cli, None, table));
+
+        Ok(Layers(layers))
+    }
+
+    pub fn merged(&self) -> Table {
+        let mut merged = Table::new();
+        for layer in &self.0 {
+            merged.extend(layer.table.clone());
+        }
+        merged.remove("schema");
+        merged
+    }
+
+    /// The layer the effective value of `key` comes from
+    pub fn origin(&self, key: &str) -> Option<&Layer> {
+        self.0.iter().rev().find(|l| l.table.contains_key(key))
+    }
+
+    pub fn user(&self) -> &Layer {
+        self.0
+            .iter()
+            .find(|l| l.source == Source::User)
+            .expect("the user layer is always loaded")
+    }
+
+    /// Replaces the user layer with `table` and writes it to disk
+    pub fn write_user(&mut self, table: Table) -> Result<(), String> {
+        write_user(&table)?;
+        if let Some(layer) = self.0.iter_mut().find(|l| l.source == Source::User) {
+            layer.table = table;
+        }
+        Ok(())
+    }
+}
+
+pub fn unknown_key(name: &str) -> String {
+    format!(
+        "Unknown setting \"{name}\", use one of: {}",
+        KEYS.iter().map(|k| k.name).collect::<Vec<_>>().join(", ")
+    )
+}
+
+fn defaults() -> Table {
+    let mut table = Table::new();
+    table.insert("use-pager".to_string(), Value::Boolean(true));
+    table.insert(
+        "pager".to_string(),
+        Value::String(DEFAULT_PAGING_SOFTWARE.to_string()),
+    );
+    table.insert("offline".to_string(), Value::Boolean(false));
+    table.insert(
+        "cache-ttl".to_string(),
+        Value::String(DEFAULT_CACHE_TTL.to_string()),
+    );
+    table.insert(
+        "http-timeout".to_string(),
+        Value::String(DEFAULT_HTTP_TIMEOUT.to_string()),
+    );
+    table.insert("http-retries".to_string(), Value::Integer(3));
+    table.insert("http-concurrency".to_string(), Value::Integer(4));
+    table.insert("http-rate-limit".to_string(), Value::Integer(10));
+    table.insert(
+        "steamcmd-mirror".to_string(),
+        Value::String(crate::steamcmd::DEFAULT_MIRROR.to_string()),
+    );
+    table
+}
+
+pub fn system_config_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    return PathBuf::from(std::env::var("PROGRAMDATA").unwrap_or(r"C:\ProgramData".to_string()))
+        .join("rrm")
+        .join("config.toml");
+
+    #[cfg(not(target_os = "windows"))]
+    PathBuf::from("/etc/rrm/config.toml")
+}
+
+pub fn user_config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// The JSON config written by older versions of rrm
+fn legacy_config_path() -> PathBuf {
+    config_dir().join("config")
+}
+
+/// Removes the settings a `.rrm.toml` can not set from `table`, returning their names
+fn remove_untrusted(table: &mut Table) -> Vec<String> {
+    let untrusted: Vec<String> = table
+        .keys()
+        .filter(|name| !IN_PROJECT.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    for name in &untrusted {
+        table.remove(name);
+    }
+    untrusted
+}
+
+/// Looks for a `.rrm.toml` in `start` and every folder above it
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+fn read_file(path: &Path) -> Result<Option<Table>, String> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read \"{}\": {err}", path.display()))?;
+    let mut table: Table = content
+        .parse()
+        .map_err(|err| format!("Invalid config file \"{}\": {err}", path.display()))?;
+
+    // Relative paths are relative to the file
+    if let Some(dir) = path.parent() {
+        for key in KEYS.iter().filter(|k| k.kind == Kind::Path) {
+            if let Some(Value::String(value)) = table.get_mut(key.name) {
+                if Path::new(value).is_relative() {
+                    *value = dir.join(&value).display().to_string();
+                }
+            }
+        }
+    }
+
+    migrate(table)
+        .map(Some)
+        .map_err(|err| format!("\"{}\": {err}", path.display()))
+}
+
+/// Reads the user file, moving a legacy JSON config to `config.toml` first
+fn load_user() -> Result<Table, String> {
+    if let Some(table) = read_file(&user_config_path())? {
+        return Ok(table);
+    }
+
+    let legacy = legacy_config_path();
+    let Ok(json) = std::fs::read_to_string(&legacy) else {
+        return Ok(Table::new());
+    };
+
+    // Old versions created it empty before writing anything
+    if json.trim().is_empty() {
+        let _ = std::fs::remove_file(&legacy);
+        return Ok(Table::new());
+    }
+
+    let table = migrate_legacy(&json)?;
+    write_user(&table)?;
+    let backup = legacy.with_extension("json.old");
+    let _ = std::fs::rename(&legacy, &backup);
+    eprintln!(
+        "Warning: Moved the configuration to \"{}\", the old one is at \"{}\"",
+        user_config_path().display(),
+        backup.display()
+    );
+
+    Ok(table)
+}
+
+/// Brings a table written by an older rrm up to `SCHEMA_VERSION`
+pub fn migrate(mut table: Table) -> Result<Table, String> {
+    let version = match table.get("schema") {
+        Some(Value::Integer(version)) => *version,
+        Some(_) => return Err("schema must be a number".to_string()),
+        None => SCHEMA_VERSION,
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "written for schema {version} by a newer rrm, this one understands up to {SCHEMA_VERSION}"
+        ));
+    }
+
+    // Schema 2: instances name their user data folder instead of the Config folder inside it
+    if version < 2 {
+        if let Some(Value::Array(instances)) = table.get_mut("instances") {
+            for instance in instances.iter_mut().filter_map(Value::as_table_mut) {
+                let Some(Value::String(config_dir)) = instance.remove("config-dir") else {
+                    continue;
+                };
+                if let Some(user_data) = Path::new(&config_dir).parent() {
+                    instance.insert(
+                        "user-data-dir".to_string(),
+                        Value::String(user_data.display().to_string()),
+                    );
+                }
+            }
+        }
+    }
+
+    // Later schemas go here, each one upgrading the table by a single version
+
+    table.insert("schema".to_string(), Value::Integer(SCHEMA_VERSION));
+    Ok(table)
+}
+
+/// Converts the JSON dump of `Installer` used before `config.toml` (schema 0)
+pub fn migrate_legacy(json: &str) -> Result<Table, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|err| format!("Invalid legacy config: {err}"))?;
+
+    fn rename(key: &str) -> String {
+        match key {
+            "rim_install" => "game-path",
+            "use_more" => "use-pager",
+            "with_paging" => "pager",
+            "current_instance" => "instance",
+            "game_path" => "game-path",
+            "config_dir" => "config-dir",
+            "Hold" => "hold",
+            "Hash" => "hash",
+            "NoDependency" => "no-dependency",
+            key => key,
+        }
+        .to_string()
+    }
+
+    fn convert(value: &serde_json::Value, key: &str) -> Option<Value> {
+        Some(match value {
+            serde_json::Value::Null => return None,
+            serde_json::Value::Bool(b) => Value::Boolean(*b),
+            serde_json::Value::Number(n) => n
+                .as_i64()
+                .map(Value::Integer)
+                .or_else(|| n.as_f64().map(Value::Float))?,
+            serde_json::Value::String(s) if key == "mode" => Value::String(rename(s)),
+            serde_json::Value::String(s) => Value::String(s.clone()),
+            serde_json::Value::Array(items) => {
+                Value::Array(items.iter().filter_map(|v| convert(v, key)).collect())
+            }
+            serde_json::Value::Object(map) => Value::Table(
+                map.iter()
+                    .filter_map(|(k, v)| convert(v, k).map(|v| (rename(k), v)))
+                    .collect(),
+            ),
+        })
+    }
+
+    match convert(&value, "") {
+        Some(Value::Table(mut table)) => {
+            table.insert("schema".to_string(), Value::Integer(1));
+            migrate(table)
+        }
+        _ => Err("Invalid legacy config: not an object".to_string()),
+    }
+}
+
+fn env_table() -> Table {
+    let mut table = Table::new();
+    for key in KEYS {
+        let Ok(value) = std::env::var(key.env) else {
+            continue;
+        };
+        match key.parse(&value) {
+            Ok(value) => {
+                table.insert(key.name.to_string(), value);
+            }
+            Err(err) => eprintln!("Warning: Ignoring {}: {err}", key.env),
+        }
+    }
+    table
+}
+
+fn header() -> String {
+    let mut header = String::from(
+        "# rrm configuration, `rrm config list` shows every setting and where its value comes from.\n\
+         # The same settings can be set in the system file, in a .rrm.toml next to a modpack,\n\
+         # with RRM_* environment variables or with --config KEY=VALUE.\n#\n",
+    );
+    for key in KEYS {
+        header.push_str(&format!(
+            "# {:<10} {} [env: {}]\n",
+            key.name, key.description, key.env
+        ));
+    }
+    header.push('\n');
+    header
+}
+
+pub(crate) fn write_user(table: &Table) -> Result<(), String> {
+    let mut table = table.clone();
+    table.insert("schema".to_string(), Value::Integer(SCHEMA_VERSION));
+
+    let content = toml::to_string(&table).map_err(|err| err.to_string())?;
+    let path = get_or_create_config_dir().join("config.toml");
+    std::fs::write(&path, header() + &content)
+        .map_err(|err| format!("Failed to write config file at {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_legacy() {
+        let table = migrate_legacy(
+            r#"{
+  "rim_install": "/games/RimWorld",
+  "use_more": false,
+  "with_paging": "less",
+  "pins": [
+    { "id": 1, "name": "Harmony", "mode": "Hold" },
+    { "id": 2, "name": "HugsLib", "mode": { "Hash": "ab12" } }
+  ],
+  "instances": [{ "name": "beta", "game_path": "/games/beta", "config_dir": null }],
+  "current_instance": null
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(table["schema"].as_integer(), Some(SCHEMA_VERSION));
+        assert_eq!(table["game-path"].as_str(), Some("/games/RimWorld"));
+        assert_eq!(table["use-pager"].as_bool(), Some(false));
+        assert_eq!(table["pager"].as_str(), Some("less"));
+        assert!(!table.contains_key("instance"));
+
+        let pins = table["pins"].as_array().unwrap();
+        assert_eq!(pins[0]["mode"].as_str(), Some("hold"));
+        assert_eq!(pins[1]["mode"]["hash"].as_str(), Some("ab12"));
+
+        let instance = &table["instances"].as_array().unwrap()[0];
+        assert_eq!(instance["game-path"].as_str(), Some("/games/beta"));
+        assert!(instance.get("config-dir").is_none());
+
+        let future: Table = "schema = 99".parse().unwrap();
+        assert!(migrate(future).is_err());
+    }
+
+    #[test]
+    fn test_migrate_config_dir() {
+        let table: Table = r#"
+schema = 1
+
+[[instances]]
+name = "beta"
+config-dir = "/data/beta/Config"
+"#
+        .parse()
+        .unwrap();
+
+        let table = migrate(table).unwrap();
+        let instance = &table["instances"].as_array().unwrap()[0];
+        assert_eq!(instance["user-data-dir"].as_str(), Some("/data/beta"));
+        assert!(instance.get("config-dir").is_none());
+    }
+
+    #[test]
+    fn test_remove_untrusted() {
+        let mut table: Table = "offline = true\ncache-ttl = \"2h\"".parse().unwrap();
+        assert!(remove_untrusted(&mut table).is_empty());
+        assert_eq!(table.len(), 2);
+
+        let excluded = KEYS.iter().filter(|k| !IN_PROJECT.contains(&k.name));
+        for key in excluded {
+            let mut table = Table::new();
+            table.insert(key.name.to_string(), Value::String("/tmp/evil".to_string()));
+            assert_eq!(remove_untrusted(&mut table), [key.name], "{}", key.name);
+            assert!(table.is_empty());
+        }
+
+        // Every folder, program and account setting stays out
+        for name in [
+            "game-path",
+            "user-data-dir",
+            "download-dir",
+            "instance",
+            "http-proxy",
+            "launch-args",
+            "steam-username",
+            "steam-api-key",
+            "pager",
+            "steamcmd-path",
+            "steam-password-command",
+        ] {
+            assert!(!IN_PROJECT.contains(&name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"-quicktest  -savedatafolder="My Data" -logfile"#),
+            ["-quicktest", "-savedatafolder=My Data", "-logfile"]
+        );
+        assert_eq!(split_args(r#"-a "" -b"#), ["-a", "", "-b"]);
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn test_layers() {
+        let table = |s: &str| s.parse::<Table>().unwrap();
+        let layers = Layers(vec![
+            Layer::new(Source::Default, None, defaults()),
+            Layer::new(
+                Source::User,
+                None,
+                table("pager = \"less\"\nuse-pager = false"),
+            ),
+            Layer::new(Source::Project, None, table("cache-ttl = \"2h\"")),
+            Layer::new(Source::Env, None, table("pager = \"bat\"")),
+        ]);
+
+        let merged = layers.merged();
+        assert_eq!(merged["pager"].as_str(), Some("bat"));
+        assert_eq!(merged["use-pager"].as_bool(), Some(false));
+        assert_eq!(merged["cache-ttl"].as_str(), Some("2h"));
+
+        assert_eq!(layers.origin("pager").unwrap().source, Source::Env);
+        assert_eq!(layers.origin("use-pager").unwrap().source, Source::User);
+        assert!(layers.origin("instance").is_none());
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let use_pager = key("use-pager").unwrap();
+        assert_eq!(use_pager.parse("1"), Ok(Value::Boolean(true)));
+        assert!(use_pager.parse("yes").is_err());
+        assert!(key("pager").unwrap().parse(" ").is_err());
+        assert!(key("paging").is_none());
+
+        let retries = key("http-retries").unwrap();
+        assert_eq!(retries.parse(" 5"), Ok(Value::Integer(5)));
+        assert!(retries.parse("-1").is_err());
+        assert!(retries.parse("many").is_err());
+
+        let proxy = key("http-proxy").unwrap();
+        assert!(proxy.parse("http://proxy:3128").is_ok());
+        assert!(proxy.parse("proxy:3128").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(
+            parse_duration(" 7d"),
+            Ok(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("6 hours").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+}
@@ -1,311 +1,558 @@
 extern crate core;
 
-use rrm_locals::GamePath;
+pub mod config;
+pub mod steamcmd;
+
+use config::{Layers, Source};
+use rrm_locals::{GamePath, UserDataPath};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read, Write};
+use toml::Table;
 
-use include_dir::{include_dir, Dir};
 use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::OnceLock;
 
 use directories::UserDirs;
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-use std::os::unix::fs::PermissionsExt;
 
 #[cfg(target_os = "windows")]
 static DEFAULT_PAGING_SOFTWARE: &str = r"C:\Windows\System32\more.com";
 
-#[cfg(target_os = "macos")]
-static PROJECT_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/steamcmd/macos");
-
-#[cfg(target_os = "windows")]
-static PROJECT_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/steamcmd/windows");
-
-#[cfg(target_os = "linux")]
-static PROJECT_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/steamcmd/linux");
-
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 static DEFAULT_PAGING_SOFTWARE: &str = r"more";
 
-pub fn get_or_create_config_dir() -> PathBuf {
-    if let Some(path) = env_var_config("XDG_CONFIG_HOME")
-        .or_else(|| env_var_config("RRM_CONFIG_HOME"))
-        .or_else(|| env_var_config("CONFIG_HOME"))
-    {
-        return path;
-    }
-
-    let config_dir = UserDirs::new().unwrap().home_dir().join(".config");
-    if config_dir.exists() {
-        let config_dir = config_dir.join("rrm");
-        if !config_dir.exists() {
-            fs::create_dir_all(&config_dir).unwrap();
+/// Where rrm keeps its configuration, without creating it
+pub fn config_dir() -> PathBuf {
+    for var in ["XDG_CONFIG_HOME", "RRM_CONFIG_HOME", "CONFIG_HOME"] {
+        if let Some(dir) = std::env::var_os(var).filter(|dir| !dir.is_empty()) {
+            return PathBuf::from(dir).join("rrm");
         }
-        return config_dir.to_path_buf();
     }
 
-    let config_dir = UserDirs::new().unwrap().home_dir().join(".rrm");
+    let home = UserDirs::new().unwrap().home_dir().to_path_buf();
+    if home.join(".config").exists() {
+        home.join(".config").join("rrm")
+    } else {
+        home.join(".rrm")
+    }
+}
+
+pub fn get_or_create_config_dir() -> PathBuf {
+    let config_dir = config_dir();
     if !config_dir.exists() {
-        fs::create_dir_all(&config_dir).unwrap();
+        fs::create_dir_all(&config_dir).unwrap_or_else(|err| {
+            eprintln!(
+                "Failed to create the config folder at {}",
+                config_dir.display()
+            );
+            eprintln!("Error: {}", err);
+            exit(1);
+        });
     }
+    config_dir
+}
+
+/// Where answers from Steam are cached, shared by every instance
+pub fn cache_dir() -> PathBuf {
+    config_dir().join("cache")
+}
+
+fn default_cache_ttl() -> String {
+    config::DEFAULT_CACHE_TTL.to_string()
+}
 
-    println!("{:?}", &config_dir);
-    config_dir.to_path_buf()
+/// How requests to Steam are sent, see `rrm_scrap::HttpConfig`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HttpSettings {
+    pub http_timeout: String,
+    pub http_retries: u32,
+    pub http_concurrency: usize,
+    pub http_rate_limit: u32,
+    pub http_proxy: Option<String>,
+    pub user_agent: Option<String>,
 }
 
-fn env_var_config(var: &'static str) -> Option<PathBuf> {
-    std::env::var(var).ok().map(|env_config_dir| {
-        let env_config_dir = PathBuf::from(env_config_dir).join("rrm");
-        if !env_config_dir.exists() {
-            fs::create_dir_all(&env_config_dir).unwrap();
+impl Default for HttpSettings {
+    fn default() -> Self {
+        let defaults = rrm_scrap::HttpConfig::default();
+        HttpSettings {
+            http_timeout: config::DEFAULT_HTTP_TIMEOUT.to_string(),
+            http_retries: defaults.retries,
+            http_concurrency: defaults.concurrency,
+            http_rate_limit: defaults.rate_limit,
+            http_proxy: None,
+            user_agent: None,
         }
-        env_config_dir
-    })
+    }
 }
 
+/// Which steamcmd is used, see the `steamcmd` module
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Installer {
-    pub rim_install: Option<GamePath>,
-    pub use_more: bool,
-    pub with_paging: String,
+#[serde(default, rename_all = "kebab-case")]
+pub struct SteamCmdSettings {
+    pub steamcmd_path: Option<PathBuf>,
+    pub steamcmd_mirror: String,
+    pub steamcmd_sha256: Option<String>,
+    /// The account steamcmd logs in with, anonymous when not set
+    pub steam_username: Option<String>,
+    /// Prints the password of `steam_username`, like a keyring lookup
+    pub steam_password_command: Option<String>,
+    /// Where steamcmd downloads mods before they are moved into `Mods/`
+    pub download_dir: Option<PathBuf>,
 }
 
-pub fn run_steam_command(c: &str, config_path: &Path, count: usize) -> String {
-    let steam = get_steamcmd_path(config_path);
+impl Default for SteamCmdSettings {
+    fn default() -> Self {
+        SteamCmdSettings {
+            steamcmd_path: None,
+            steamcmd_mirror: steamcmd::DEFAULT_MIRROR.to_string(),
+            steamcmd_sha256: None,
+            steam_username: None,
+            steam_password_command: None,
+            download_dir: None,
+        }
+    }
+}
 
-    #[cfg(target_os = "windows")]
-    let out = std::process::Command::new(steam.as_path().to_str().unwrap())
-        .args("+login anonymous {} +quit".replace("{}", c).split(" "))
-        .stdin(std::process::Stdio::null())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::null())
-        .output()
-        .unwrap_or_else(|error| {
-            eprintln!("Could not execute steamcmd successfully.\nError: {}", error);
-            exit(1);
-        });
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+static WORKING_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The directory rrm was started from. `Installer::new` moves into the config dir, so relative
+/// paths given by the user have to be joined to this one
+pub fn working_dir() -> PathBuf {
+    WORKING_DIR
+        .get()
+        .cloned()
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default()
+}
 
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    let out = std::process::Command::new("env")
-        .args(
-            r#"HOME=PATH [] +login anonymous {} +quit"#
-                .replace(
-                    "PATH",
-                    config_path.as_os_str().to_str().unwrap(),
-                )
-                .replace("[]", steam.as_path().to_str().unwrap())
-                .replace("{}", c)
-                .split(' '),
-        )
-        .stdin(std::process::Stdio::null())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::null())
-        .output()
-        .unwrap_or_else(|error| {
-            eprintln!("Could not execute steamcmd successfully.\nError: {}", error);
-            exit(1);
-        });
+/// Where the backups, snapshots and lock of the selected instance are kept. Without an
+/// instance it is the config dir itself
+pub fn get_or_create_data_dir() -> PathBuf {
+    DATA_DIR
+        .get()
+        .cloned()
+        .unwrap_or_else(get_or_create_config_dir)
+}
 
-    let out = String::from_utf8(out.clone().stdout).unwrap();
-
-    if c.contains("+workshop_download_item 294100")
-        && out.contains("Connecting anonymously to Steam Public...OK")
-        && out.contains("Waiting for client config...OK")
-        && out.contains("Waiting for user info...OK")
-    {
-        out
-    } else if c.contains("+workshop_download_item 294100") {
-        run_steam_command(c, config_path, count + 1)
-    } else if count == 5 {
-        "Error: Failed to install".to_string()
-    } else {
-        run_steam_command(c, config_path, count + 1)
+/// What a pin keeps rrm from doing with a mod
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PinMode {
+    /// Never update the installed copy
+    Hold,
+    /// Only accept a download whose content has this hash
+    Hash(String),
+    /// Never install the mod just because another one requires it
+    NoDependency,
+}
+
+impl std::fmt::Display for PinMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinMode::Hold => write!(f, "hold"),
+            PinMode::Hash(hash) => write!(f, "hash {}", &hash[..hash.len().min(12)]),
+            PinMode::NoDependency => write!(f, "no-dependency"),
+        }
     }
 }
 
-pub fn get_steamcmd_path(config_path: &Path) -> PathBuf {
-    #[cfg(target_os = "macos")]
-    return config_path.join("steamcmd").join("steamcmd");
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Pin {
+    pub id: usize,
+    pub name: String,
+    pub mode: PinMode,
+}
+
+/// A named RimWorld installation, like a stable install next to a beta branch
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Instance {
+    pub name: String,
+    pub game_path: GamePath,
+    /// Where it keeps `Config/` and `Saves/`, when not in the default location
+    pub user_data_dir: Option<UserDataPath>,
+}
 
-    #[cfg(target_os = "linux")]
-    return config_path.join("steamcmd").join("steamcmd.sh");
+impl Instance {
+    pub fn data_dir(&self) -> PathBuf {
+        get_or_create_config_dir()
+            .join("instances")
+            .join(&self.name)
+    }
+}
 
-    #[cfg(target_os = "windows")]
-    return config_path.join("steamcmd").join("steamcmd.exe");
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Installer {
+    /// The game path of the selected instance, or the default one when there is none
+    #[serde(rename = "game-path")]
+    pub rim_install: Option<GamePath>,
+    #[serde(rename = "use-pager")]
+    pub use_more: bool,
+    #[serde(rename = "pager")]
+    pub with_paging: String,
+    #[serde(default)]
+    pub pins: Vec<Pin>,
+    #[serde(default)]
+    pub instances: Vec<Instance>,
+    #[serde(default, rename = "steam-api-key")]
+    pub steam_api_key: Option<String>,
+    /// Whether the Workshop is only read from the cache
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default = "default_cache_ttl", rename = "cache-ttl")]
+    pub cache_ttl: String,
+    #[serde(flatten)]
+    pub http: HttpSettings,
+    #[serde(flatten)]
+    pub steamcmd: SteamCmdSettings,
+    /// Where the game at `game-path` keeps its data, when not in the default location
+    #[serde(default, rename = "user-data-dir")]
+    pub user_data_dir: Option<UserDataPath>,
+    /// The options RimWorld is started with, like `-savedatafolder=SaveData`
+    #[serde(default, rename = "launch-args")]
+    pub launch_args: Option<String>,
+    /// The instance set with `rrm instance use`
+    #[serde(default, rename = "instance")]
+    pub current_instance: Option<String>,
+    /// The instance every command runs against
+    #[serde(skip)]
+    pub instance: Option<Instance>,
+    /// The default game path while `rim_install` holds the one of an instance
+    #[serde(skip)]
+    default_install: Option<GamePath>,
+    /// Every config layer, to tell where each value comes from
+    #[serde(skip)]
+    pub layers: Layers,
+    /// The values as loaded, so only the ones changed since get written
+    #[serde(skip)]
+    loaded: Table,
 }
 
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-fn set_permissions_for_steamcmd(path: &Path) {
-    let files = path.read_dir().unwrap();
+/// Runs `pager` once to make sure it can be used
+fn check_pager(pager: &str) -> Result<(), String> {
+    let error = |err: String| {
+        format!(
+            "Could not execute pager successfully, make sure the path or name\n\
+                is correct or that it is available within the PATH.\n\
+                Error: {}",
+            err
+        )
+    };
 
-    for file in files {
-        let file = file.unwrap();
+    let status = std::process::Command::new(pager)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .and_then(|mut child| child.wait())
+        .map_err(|err| error(err.to_string()))?;
 
-        if !file.file_type().unwrap().is_dir() {
-            let mut perms = fs::metadata(file.path()).unwrap().permissions();
-            perms.set_mode(0o744);
-            std::fs::set_permissions(file.path(), perms).unwrap();
-        } else {
-            set_permissions_for_steamcmd(&file.path());
-        }
+    if status.success() {
+        Ok(())
+    } else {
+        Err(error(status.to_string()))
     }
 }
 
 impl Installer {
-    fn init(path: Option<PathBuf>) -> Self {
+    fn init() {
         let config_dir = get_or_create_config_dir();
-        let config_file = config_dir.join("config");
-        if !config_file.exists() {
-            File::create(&config_file).unwrap();
-
-            let steamcmd_path = config_dir.join("steamcmd");
-            fs::create_dir(&steamcmd_path).unwrap_or_else(|err| {
-                if !steamcmd_path.is_dir() {
-                    panic!("{}", err);
-                }
-            });
-
-            // TODO: Install from https://steamcdn-a.akamaihd.net/client/installer/steamcmd
-            PROJECT_DIR.extract(steamcmd_path.as_path()).unwrap();
-
-            #[cfg(any(target_os = "macos", target_os = "linux"))]
-            set_permissions_for_steamcmd(steamcmd_path.as_path());
 
-            println!("Installing steamcmd...");
-            run_steam_command("", &config_dir, 1);
-            println!("Done!");
+        if let Ok(dir) = std::env::current_dir() {
+            let _ = WORKING_DIR.set(dir);
         }
-
-        let path = path.map(|path| GamePath::from(&path));
-
         std::env::set_current_dir(&config_dir).unwrap();
-
-        Installer {
-            with_paging: DEFAULT_PAGING_SOFTWARE.to_string(),
-            rim_install: path,
-            use_more: true,
-        }
     }
 
-    fn init_with_path(rim_path: GamePath) -> Self {
-        let rim_install = rim_path.path().to_path_buf();
-        Installer::init(Some(rim_install))
+    /// The values to save in the user file, with the default game path instead of the one of
+    /// the selected instance
+    fn to_table(&self) -> Table {
+        let mut saved = self.clone();
+        if saved.instance.is_some() {
+            saved.rim_install = saved.default_install.clone();
+        }
+        Table::try_from(&saved).unwrap_or_default()
     }
 
+    /// Saves the values changed since the config was loaded to the user file, so values that
+    /// come from other layers are not copied into it
     pub fn write_config(&self) {
-        let json = serde_json::to_string_pretty(self).unwrap();
-
-        let mut config = OpenOptions::new()
-            .append(false)
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .read(false)
-            .open(get_or_create_config_dir().join("config"))
-            .unwrap_or_else(|err| {
-                eprintln!(
-                    "Failed to open config file at {}",
-                    &get_or_create_config_dir().join("config").display()
-                );
-                eprintln!("Error: {}", err);
-                exit(1);
-            });
+        let current = self.to_table();
+        let mut user = self.layers.user().table.clone();
+
+        for key in current.keys().chain(self.loaded.keys()) {
+            match (self.loaded.get(key), current.get(key)) {
+                (before, now) if before == now => {}
+                (_, Some(now)) => {
+                    user.insert(key.clone(), now.clone());
+                }
+                (_, None) => {
+                    user.remove(key);
+                }
+            }
+        }
 
-        config.write_all(json.as_bytes()).unwrap();
+        if let Err(err) = config::write_user(&user) {
+            eprintln!("Error: {}", err);
+            exit(1);
+        }
     }
 
-    pub fn load_config(path: &Path) -> serde_json::Result<Installer> {
-        let file = File::open(path).unwrap_or_else(|err| {
-            eprintln!("Failed to open config file at {}", path.display());
+    /// Loads the layered config. `overrides` are the `--config KEY=VALUE` flags
+    pub fn new(with_path: Option<GamePath>, overrides: &[(String, String)]) -> Option<Self> {
+        // Before `init` changes the working directory, which is where `.rrm.toml` is looked for
+        let layers = Layers::load(overrides).unwrap_or_else(|err| {
             eprintln!("Error: {}", err);
             exit(1);
         });
-        let mut buf = BufReader::new(file);
-        let mut res: Vec<u8> = vec![];
-        buf.read_to_end(&mut res).unwrap();
 
-        serde_json::from_slice(&res)
+        Installer::init();
+
+        let mut installer: Installer = toml::Value::Table(layers.merged())
+            .try_into()
+            .unwrap_or_else(|err| {
+                eprintln!("Error: Invalid configuration: {}", err);
+                exit(1);
+            });
+        installer.layers = layers;
+
+        if let Some(ref rim_path) = installer.rim_install {
+            if !rim_path.path().exists() {
+                eprintln!(
+                    "Warning: Previous saved game location \"{}\" no longer exists.",
+                    rim_path.path().display()
+                );
+                installer.rim_install = None;
+            }
+        }
+        installer.loaded = installer.to_table();
+
+        if let Some(path) = with_path {
+            installer.rim_install = Some(path);
+            installer.write_config();
+        } else if !config::user_config_path().exists() {
+            installer.write_config();
+        }
+
+        Some(installer)
     }
 
-    pub fn new(with_path: Option<GamePath>) -> Option<Self> {
-        let installer = if let Some(path) = with_path {
-            Installer::init_with_path(path)
-        } else {
-            let installer = Installer::init(None);
-            let mut installer = Installer::load_config(&get_or_create_config_dir().join("config"))
-                .unwrap_or(installer);
-
-            if let Some(ref rim_path) = installer.rim_install {
-                if !rim_path.path().exists() {
-                    eprintln!(
-                        "Warning: Previous saved game location \"{}\" no longer exists.",
-                        rim_path.path().display()
-                    );
-                    installer.rim_install = None;
+    /// Validates and saves `value` for `key` in the user file. Returns the layer that still
+    /// overrides it, if any
+    pub fn set_config_value(&mut self, key: &str, value: &str) -> Result<Option<Source>, String> {
+        let k = config::key(key).ok_or_else(|| config::unknown_key(key))?;
+        let mut parsed = k.parse(value)?;
+
+        match key {
+            "game-path" => {
+                let path = Path::new(value);
+                if !path.is_dir() {
+                    return Err(format!(
+                        "\"{}\" is not a valid RimWorld installation path",
+                        path.display()
+                    ));
                 }
+                let path = path.canonicalize().unwrap_or(path.to_path_buf());
+                parsed = toml::Value::String(path.display().to_string());
             }
-            installer
-        };
+            "pager" => check_pager(value)?,
+            "instance" if value == "default" => return self.unset_config_value(key),
+            "instance" if !self.instances.iter().any(|i| i.name == value) => {
+                return Err(format!(
+                    "There is no instance named \"{value}\", see `rrm instance list`"
+                ))
+            }
+            _ => {}
+        }
 
-        installer.write_config();
-        Some(installer)
+        let mut user = self.layers.user().table.clone();
+        user.insert(key.to_string(), parsed);
+        self.layers.write_user(user)?;
+
+        Ok(self.overridden(key))
+    }
+
+    /// Removes `key` from the user file. Returns the layer that still sets it, if any
+    pub fn unset_config_value(&mut self, key: &str) -> Result<Option<Source>, String> {
+        config::key(key).ok_or_else(|| config::unknown_key(key))?;
+
+        let mut user = self.layers.user().table.clone();
+        user.remove(key);
+        self.layers.write_user(user)?;
+
+        Ok(self.overridden(key))
+    }
+
+    fn overridden(&self, key: &str) -> Option<Source> {
+        self.layers
+            .origin(key)
+            .map(|l| l.source)
+            .filter(|s| *s > Source::User)
+    }
+
+    pub fn pin_of(&self, id: usize) -> Option<&Pin> {
+        self.pins.iter().find(|p| p.id == id)
+    }
+
+    /// Adds `pin`, replacing any previous pin of the same mod
+    pub fn add_pin(&mut self, pin: Pin) {
+        self.pins.retain(|p| p.id != pin.id);
+        self.pins.push(pin);
+        self.pins.sort_by_key(|p| p.id);
+        self.write_config();
     }
 
-    pub fn set_more_value(&mut self, value: bool) {
-        self.use_more = value;
+    pub fn remove_pin(&mut self, id: usize) -> Option<Pin> {
+        let index = self.pins.iter().position(|p| p.id == id)?;
+        let pin = self.pins.remove(index);
         self.write_config();
+        Some(pin)
     }
 
     pub fn set_path_value(&mut self, value: PathBuf) {
         self.rim_install = Some(GamePath::from(value.as_path()));
+        if let Some(instance) = &mut self.instance {
+            instance.game_path = GamePath::from(value.as_path());
+            let instance = instance.clone();
+            self.instances
+                .iter_mut()
+                .filter(|i| i.name == instance.name)
+                .for_each(|i| *i = instance.clone());
+        }
         self.write_config();
     }
 
-    pub fn set_paging_software(&mut self, value: &str) {
-        let try_execute_pager = std::process::Command::new(value)
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .spawn()
-            .unwrap_or_else(|error| {
-                eprintln!(
-                    "Could not execute pager successfully, make sure the path or name\n\
-                        is correct or that it is available within the PATH.\n\
-                        Error: {}",
-                    error
-                );
-                exit(1);
-            })
-            .wait()
-            .unwrap_or_else(|error| {
-                eprintln!(
-                    "Could not execute pager successfully, make sure the path or name\n\
-                        is correct or that it is available within the PATH.\n\
-                        Error: {}",
-                    error
-                );
-                exit(1);
-            })
-            .code();
+    /// Makes `name`, or the instance set with `rrm instance use`, the one every command runs
+    /// against
+    pub fn select_instance(&mut self, name: Option<&str>) -> Result<(), String> {
+        let Some(name) = name.or(self.current_instance.as_deref()) else {
+            return Ok(());
+        };
 
-        if let Some(code) = try_execute_pager {
-            if code == 0 {
-                self.with_paging = value.to_string();
-                self.write_config()
-            }
-        } else {
+        if name == "default" {
+            return Ok(());
+        }
+
+        let Some(instance) = self.instances.iter().find(|i| i.name == name).cloned() else {
+            return Err(format!(
+                "There is no instance named \"{name}\", see `rrm instance list`"
+            ));
+        };
+
+        if !instance.game_path.path().exists() {
             eprintln!(
-                "Could not execute pager successfully, make sure the path or name\n\
-                        is correct or that it is available within the PATH."
+                "Warning: The game location \"{}\" of instance \"{}\" no longer exists.",
+                instance.game_path.path().display(),
+                instance.name
             );
         }
+
+        let data_dir = instance.data_dir();
+        fs::create_dir_all(&data_dir).map_err(|err| err.to_string())?;
+        let _ = DATA_DIR.set(data_dir);
+
+        self.default_install = self.rim_install.take();
+        self.rim_install = Some(instance.game_path.clone()).filter(|p| p.path().exists());
+        self.instance = Some(instance);
+        Ok(())
+    }
+
+    /// The options of `launch-args`, which the game is started with
+    pub fn launch_args(&self) -> Vec<String> {
+        self.launch_args
+            .as_deref()
+            .map(config::split_args)
+            .unwrap_or_default()
+    }
+
+    /// Where the game of the selected instance keeps its data: the `user-data-dir` of the
+    /// instance or the config, the `-savedatafolder=` of `launch-args` or the default location
+    pub fn user_data(&self) -> Option<UserDataPath> {
+        let configured = match &self.instance {
+            Some(instance) => instance.user_data_dir.clone(),
+            None => self.user_data_dir.clone(),
+        };
+        if configured.is_some() {
+            return configured;
+        }
+
+        let args = self.launch_args();
+        match &self.rim_install {
+            Some(game) => UserDataPath::detect(game.path(), args.iter().map(String::as_str)),
+            None => UserDataPath::detect(Path::new(""), std::iter::empty()),
+        }
+    }
+
+    /// The game path used when no instance is selected
+    pub fn default_install(&self) -> Option<&GamePath> {
+        if self.instance.is_some() {
+            self.default_install.as_ref()
+        } else {
+            self.rim_install.as_ref()
+        }
     }
 
-    pub fn install_sync(&self, c: Vec<rrm_scrap::ModSteamInfo>) -> (bool, String) {
-        let to_install = Self::gen_install_string(&c);
-        let a: String = run_steam_command(&to_install, &get_or_create_config_dir(), 1);
-        (a.contains("Success. Downloaded item"), a)
+    pub fn add_instance(&mut self, instance: Instance) {
+        self.instances.push(instance);
+        self.write_config();
+    }
+
+    pub fn remove_instance(&mut self, name: &str) -> Option<Instance> {
+        let index = self.instances.iter().position(|i| i.name == name)?;
+        let instance = self.instances.remove(index);
+        if self.current_instance.as_deref() == Some(name) {
+            self.current_instance = None;
+        }
+        self.write_config();
+        Some(instance)
+    }
+
+    pub fn set_current_instance(&mut self, name: Option<String>) {
+        self.current_instance = name;
+        self.write_config();
+    }
+
+    /// The client every request to Steam goes through, with the response cache and the
+    /// http-* settings
+    pub fn http(&self) -> rrm_scrap::Http {
+        let duration = |name: &str, value: &str, default: &str| {
+            config::parse_duration(value).unwrap_or_else(|err| {
+                eprintln!("Warning: {name} {err}, using {default}");
+                config::parse_duration(default).unwrap()
+            })
+        };
+
+        let cache = rrm_scrap::Cache::new(
+            cache_dir().join("http"),
+            duration("cache-ttl", &self.cache_ttl, config::DEFAULT_CACHE_TTL),
+            self.offline,
+        );
+
+        let defaults = rrm_scrap::HttpConfig::default();
+        let settings = rrm_scrap::HttpConfig {
+            timeout: duration(
+                "http-timeout",
+                &self.http.http_timeout,
+                config::DEFAULT_HTTP_TIMEOUT,
+            ),
+            retries: self.http.http_retries,
+            concurrency: self.http.http_concurrency,
+            rate_limit: self.http.http_rate_limit,
+            proxy: self.http.http_proxy.clone(),
+            user_agent: self.http.user_agent.clone().unwrap_or(defaults.user_agent),
+        };
+
+        rrm_scrap::Http::new(&settings, cache).unwrap_or_else(|err| {
+            eprintln!("{err}, check http-proxy with `rrm config get http-proxy`");
+            exit(1);
+        })
+    }
+
+    /// The Steam Workshop client, using the Web API key if one is set
+    pub fn workshop(&self) -> rrm_scrap::Workshop {
+        rrm_scrap::Workshop::new(self.steam_api_key.clone()).with_http(self.http())
     }
 
     pub fn gen_install_string(c: &[rrm_scrap::ModSteamInfo]) -> String {
@@ -316,11 +563,20 @@ impl Installer {
                 .join(" +workshop_download_item 294100 ")
     }
 
+    /// The steamcmd in use and where it comes from, see `steamcmd::locate`
+    pub fn steamcmd(&self) -> (PathBuf, steamcmd::Origin) {
+        steamcmd::locate(self.steamcmd.steamcmd_path.as_deref())
+    }
+
     pub fn get_steamcmd_path(&self) -> PathBuf {
-        get_steamcmd_path(&get_or_create_config_dir())
+        self.steamcmd().0
     }
 
-    pub fn run_steam_command(&self, c: &str, count: usize) -> String {
-        run_steam_command(c, &get_or_create_config_dir(), count)
+    /// `download-dir`, or a folder in the system temp dir
+    pub fn download_dir(&self) -> PathBuf {
+        self.steamcmd
+            .download_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("rrm-downloads"))
     }
 }
@@ -0,0 +1,465 @@
+//! The steamcmd rrm downloads mods with: the one set with `steamcmd-path`, else one on the
+//! `PATH`, else the official archive downloaded from `steamcmd-mirror` into the config dir.
+
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use std::os::unix::fs::PermissionsExt;
+
+pub const DEFAULT_MIRROR: &str = "https://steamcdn-a.akamaihd.net/client/installer";
+
+#[cfg(target_os = "linux")]
+pub const ARCHIVE: &str = "steamcmd_linux.tar.gz";
+
+#[cfg(target_os = "macos")]
+pub const ARCHIVE: &str = "steamcmd_osx.tar.gz";
+
+#[cfg(target_os = "windows")]
+pub const ARCHIVE: &str = "steamcmd.zip";
+
+/// The folder of the steamcmd managed by rrm
+pub fn managed_dir() -> PathBuf {
+    crate::config_dir().join("steamcmd")
+}
+
+/// The program to run inside a steamcmd folder
+pub fn entry_point(dir: &Path) -> PathBuf {
+    #[cfg(target_os = "macos")]
+    return dir.join("steamcmd");
+
+    #[cfg(target_os = "linux")]
+    return dir.join("steamcmd.sh");
+
+    #[cfg(target_os = "windows")]
+    return dir.join("steamcmd.exe");
+}
+
+#[cfg(target_os = "windows")]
+const PROGRAM: &str = "steamcmd.exe";
+
+#[cfg(not(target_os = "windows"))]
+const PROGRAM: &str = "steamcmd";
+
+/// How long `probe` waits, long enough for steamcmd to update itself on its first run
+const PROBE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Where the steamcmd in use comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Config,
+    Path,
+    Managed,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Config => write!(f, "steamcmd-path"),
+            Origin::Path => write!(f, "PATH"),
+            Origin::Managed => write!(f, "downloaded by rrm"),
+        }
+    }
+}
+
+/// The steamcmd to run: `configured` if set, else the first one on the `PATH`, else the one
+/// managed by rrm, which may not be downloaded yet
+pub fn locate(configured: Option<&Path>) -> (PathBuf, Origin) {
+    if let Some(path) = configured {
+        return (path.to_path_buf(), Origin::Config);
+    }
+
+    let on_path = std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(PROGRAM))
+            .find(|path| path.is_file())
+    });
+
+    match on_path {
+        Some(path) => (path, Origin::Path),
+        None => (entry_point(&managed_dir()), Origin::Managed),
+    }
+}
+
+/// Why steamcmd can not be used
+#[derive(Debug, Clone, PartialEq)]
+pub enum SteamCmdError {
+    NotFound(PathBuf),
+    /// The 32-bit libraries steamcmd is built against are not installed
+    Missing32BitLibs(String),
+    CannotRun(String),
+    TimedOut,
+    /// It ran, but did not print the banner with its version
+    Unexpected(String),
+}
+
+impl Display for SteamCmdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SteamCmdError::NotFound(path) => write!(f, "\"{}\" does not exist", path.display()),
+            SteamCmdError::Missing32BitLibs(err) => write!(
+                f,
+                "steamcmd needs the 32-bit C libraries, which are missing ({err}). \
+                On Debian and Ubuntu install them with `sudo dpkg --add-architecture i386 && \
+                sudo apt update && sudo apt install lib32gcc-s1` (lib32gcc1 on older releases), \
+                on Fedora with `sudo dnf install glibc.i686 libstdc++.i686`"
+            ),
+            SteamCmdError::CannotRun(err) => write!(f, "steamcmd could not be run: {err}"),
+            SteamCmdError::TimedOut => write!(
+                f,
+                "steamcmd did not finish in {} seconds",
+                PROBE_TIMEOUT.as_secs()
+            ),
+            SteamCmdError::Unexpected(out) => {
+                write!(f, "steamcmd did not start as expected: {out}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SteamCmdError {}
+
+/// Tells what went wrong from the output of a steamcmd run, if it is a known problem
+pub fn diagnose(output: &str) -> Option<SteamCmdError> {
+    output
+        .lines()
+        .find(|line| {
+            line.contains("error while loading shared libraries")
+                // The 32-bit loader itself is missing, so the existing binary "is not found"
+                || (line.contains("linux32/steamcmd")
+                    && (line.contains("No such file or directory") || line.contains("not found")))
+        })
+        .map(|line| SteamCmdError::Missing32BitLibs(line.trim().to_string()))
+}
+
+/// The version in the banner steamcmd prints on start, like
+/// `Steam Console Client (c) Valve Corporation - version 1705108307`
+pub fn parse_version(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (_, version) = line
+            .split_once("Steam Console Client")?
+            .1
+            .split_once("version ")?;
+        let version = version.split_whitespace().next()?;
+        Some(version.to_string())
+    })
+}
+
+/// Runs `steamcmd +quit` with `home` as its home folder, returning the version it reports
+pub fn probe(steamcmd: &Path, home: &Path) -> Result<String, SteamCmdError> {
+    if !steamcmd.is_file() {
+        return Err(SteamCmdError::NotFound(steamcmd.to_path_buf()));
+    }
+
+    let mut child = Command::new(steamcmd)
+        .arg("+quit")
+        .env("HOME", home)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| SteamCmdError::CannotRun(err.to_string()))?;
+
+    let start = Instant::now();
+    while child
+        .try_wait()
+        .map_err(|err| SteamCmdError::CannotRun(err.to_string()))?
+        .is_none()
+    {
+        if start.elapsed() > PROBE_TIMEOUT {
+            let _ = child.kill();
+            return Err(SteamCmdError::TimedOut);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let out = child
+        .wait_with_output()
+        .map_err(|err| SteamCmdError::CannotRun(err.to_string()))?;
+    let output =
+        String::from_utf8_lossy(&out.stdout).to_string() + &String::from_utf8_lossy(&out.stderr);
+
+    if let Some(err) = diagnose(&output) {
+        return Err(err);
+    }
+    parse_version(&output).ok_or_else(|| {
+        let last = output.lines().rev().find(|l| !l.trim().is_empty());
+        SteamCmdError::Unexpected(last.unwrap_or("no output").trim().to_string())
+    })
+}
+
+/// What `+login` is given: `anonymous`, or the account set with `steam-username`. The password
+/// is never passed as an argument, steamcmd asks for it instead, see `prompt`
+pub fn login_args(username: Option<&str>) -> [String; 2] {
+    let user = username
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .unwrap_or("anonymous");
+    ["+login".to_string(), user.to_string()]
+}
+
+/// Something steamcmd waits for an answer to on its standard input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Password,
+    /// The code Steam Guard sent by email
+    EmailCode,
+    /// The code of the Steam Guard mobile authenticator
+    TwoFactorCode,
+}
+
+/// The prompt steamcmd left on the last, unfinished, line of its output
+pub fn prompt(partial_line: &str) -> Option<Prompt> {
+    let line = partial_line.trim_end().to_lowercase();
+    if line.ends_with("password:") {
+        Some(Prompt::Password)
+    } else if line.ends_with("steam guard code:") {
+        Some(Prompt::EmailCode)
+    } else if line.ends_with("two-factor code:") {
+        Some(Prompt::TwoFactorCode)
+    } else {
+        None
+    }
+}
+
+/// Why logging in failed, like `Invalid Password`, if `line` reports it
+pub fn login_failure(line: &str) -> Option<String> {
+    let reason = if let Some((_, reason)) = line.split_once("Login Failure:") {
+        reason
+    } else if line.contains("Logging in user") {
+        line.split_once("FAILED")?.1
+    } else {
+        return None;
+    };
+
+    let reason = reason.trim().trim_start_matches('(').trim_end_matches(')');
+    Some(reason.trim().to_string())
+}
+
+/// Where the archive for this OS is found on `mirror`
+pub fn archive_url(mirror: &str) -> String {
+    format!("{}/{ARCHIVE}", mirror.trim_end_matches('/'))
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Makes sure `archive` is the one expected: a gzip or zip file, with the hash pinned with
+/// `steamcmd-sha256` if there is one
+pub fn verify(archive: &[u8], expected_sha256: Option<&str>) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let (magic, kind) = (&b"PK\x03\x04"[..], "zip");
+    #[cfg(not(target_os = "windows"))]
+    let (magic, kind) = (&b"\x1f\x8b"[..], "gzip");
+
+    if !archive.starts_with(magic) {
+        return Err(format!(
+            "The downloaded {ARCHIVE} is not a {kind} file, check steamcmd-mirror"
+        ));
+    }
+
+    if let Some(expected) = expected_sha256 {
+        let found = sha256(archive);
+        if !found.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!(
+                "The downloaded {ARCHIVE} has the SHA-256 {found}, but steamcmd-sha256 is {}",
+                expected.trim()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn set_permissions(path: &Path) -> std::io::Result<()> {
+    for file in path.read_dir()? {
+        let file = file?;
+
+        if file.file_type()?.is_dir() {
+            set_permissions(&file.path())?;
+        } else {
+            let mut perms = fs::metadata(file.path())?.permissions();
+            perms.set_mode(0o744);
+            fs::set_permissions(file.path(), perms)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts a verified `archive` into `dir`, replacing what was there only once the new
+/// steamcmd is in place
+pub fn install(archive: &[u8], dir: &Path) -> Result<(), String> {
+    let staging = dir.with_extension("new");
+    let old = dir.with_extension("old");
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_dir_all(&old);
+
+    fs::create_dir_all(&staging)
+        .map_err(|err| format!("Could not create {}: {err}", staging.display()))?;
+
+    let result = extract(archive, &staging).and_then(|_| {
+        if entry_point(&staging).is_file() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{ARCHIVE} has no {}",
+                entry_point(Path::new("")).display()
+            ))
+        }
+    });
+    if let Err(err) = result {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(err);
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    set_permissions(&staging)
+        .map_err(|err| format!("Could not make steamcmd executable: {err}"))?;
+
+    if dir.exists() {
+        fs::rename(dir, &old)
+            .map_err(|err| format!("Could not move away {}: {err}", dir.display()))?;
+    }
+    fs::rename(&staging, dir)
+        .map_err(|err| format!("Could not move steamcmd to {}: {err}", dir.display()))?;
+    let _ = fs::remove_dir_all(&old);
+
+    Ok(())
+}
+
+/// Uses the `tar` every supported OS ships, Windows 10 and later included, which reads zip too
+fn extract(archive: &[u8], dir: &Path) -> Result<(), String> {
+    let file = dir.join(ARCHIVE);
+    fs::write(&file, archive)
+        .map_err(|err| format!("Could not write {}: {err}", file.display()))?;
+
+    let output = std::process::Command::new("tar")
+        .arg("-xf")
+        .arg(ARCHIVE)
+        .current_dir(dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|err| format!("Could not run tar to extract {ARCHIVE}: {err}"))?;
+    let _ = fs::remove_file(&file);
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Could not extract {ARCHIVE}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Removes the steamcmd managed by rrm, with everything it downloaded
+pub fn reset() -> std::io::Result<()> {
+    let dir = managed_dir();
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        #[cfg(target_os = "windows")]
+        let archive = b"PK\x03\x04rest";
+        #[cfg(not(target_os = "windows"))]
+        let archive = b"\x1f\x8brest";
+
+        assert!(verify(archive, None).is_ok());
+        assert!(verify(archive, Some(&sha256(archive).to_uppercase())).is_ok());
+        assert!(verify(archive, Some("00")).is_err());
+        assert!(verify(b"<html>Not Found</html>", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        let banner = "Redirecting stderr to '/home/u/Steam/logs/stderr.txt'\n\
+            [  0%] Checking for available updates...\n\
+            Steam Console Client (c) Valve Corporation - version 1705108307\n\
+            -- type 'quit' to exit --";
+        assert_eq!(parse_version(banner), Some("1705108307".to_string()));
+        assert_eq!(parse_version("Loading Steam API...OK"), None);
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let missing_loader =
+            "/home/u/.rrm/steamcmd/steamcmd.sh: line 39: /home/u/.rrm/steamcmd/linux32/steamcmd: \
+            No such file or directory";
+        assert!(matches!(
+            diagnose(missing_loader),
+            Some(SteamCmdError::Missing32BitLibs(_))
+        ));
+
+        let missing_lib = "linux32/steamcmd: error while loading shared libraries: \
+            libstdc++.so.6: cannot open shared object file";
+        assert!(diagnose(missing_lib).is_some());
+        assert!(diagnose("Steam Console Client (c) Valve Corporation - version 1").is_none());
+    }
+
+    #[test]
+    fn test_login() {
+        assert_eq!(login_args(None), ["+login", "anonymous"]);
+        assert_eq!(login_args(Some(" ")), ["+login", "anonymous"]);
+        assert_eq!(login_args(Some("gamer")), ["+login", "gamer"]);
+
+        assert_eq!(prompt("gamer password: "), Some(Prompt::Password));
+        assert_eq!(prompt("Steam Guard code:"), Some(Prompt::EmailCode));
+        assert_eq!(
+            prompt(
+                "Please confirm the login in the Steam Mobile app on your phone.\nTwo-factor code:"
+            ),
+            Some(Prompt::TwoFactorCode)
+        );
+        assert_eq!(prompt("Logging in user 'gamer' to Steam Public..."), None);
+
+        assert_eq!(
+            login_failure("Logging in user 'gamer' to Steam Public...FAILED (Invalid Password)"),
+            Some("Invalid Password".to_string())
+        );
+        assert_eq!(
+            login_failure("Login Failure: Rate Limit Exceeded"),
+            Some("Rate Limit Exceeded".to_string())
+        );
+        assert_eq!(
+            login_failure("Logging in user 'gamer' to Steam Public...OK"),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_locate() {
+        let configured = Path::new("/usr/games/steamcmd");
+        assert_eq!(
+            locate(Some(configured)),
+            (configured.to_path_buf(), Origin::Config)
+        );
+        let (_, origin) = locate(None);
+        assert_ne!(origin, Origin::Config);
+    }
+
+    #[test]
+    fn test_archive_url() {
+        assert_eq!(
+            archive_url("http://127.0.0.1:8000/"),
+            format!("http://127.0.0.1:8000/{ARCHIVE}")
+        );
+    }
+}
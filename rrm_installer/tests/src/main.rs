@@ -1,7 +1,6 @@
 use rrm_installer::*;
 
-
 fn main() {
-    let installer = Installer::new(None);
+    let installer = Installer::new(None, &[]);
     println!("{:?}", installer)
 }
@@ -1,12 +1,14 @@
 mod game_path;
 mod mod_obj;
 mod mod_paths;
+mod user_data_path;
 
 use fuzzy_matcher::*;
 pub use game_path::*;
 pub use mod_obj::*;
 pub use mod_paths::*;
 use std::ops::Deref;
+pub use user_data_path::*;
 
 pub use flagset::*;
 use std::io::Write;
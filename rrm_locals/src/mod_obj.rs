@@ -90,11 +90,8 @@ impl Mod {
             ))
         }
 
-        if self.dependencies.is_some() {
-            result.push_str(&format!(
-                "dependencies IDs : {}\n",
-                self.dependencies.as_ref().unwrap().join(" ")
-            ))
+        if let Some(dependencies) = &self.dependencies {
+            result.push_str(&format!("dependencies IDs : {}\n", dependencies.join(" ")))
         }
 
         result.push_str(&format!("by {}\n", self.author));
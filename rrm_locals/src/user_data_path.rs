@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SAVE_DATA_FOLDER: &str = "-savedatafolder=";
+
+/// Where RimWorld keeps the data of a player: `Config/` with `ModsConfig.xml` and the settings of
+/// each mod, and `Saves/`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserDataPath(Box<Path>);
+
+fn home() -> Option<PathBuf> {
+    directories::UserDirs::new().map(|dirs| dirs.home_dir().to_path_buf())
+}
+
+impl UserDataPath {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            Err(format!(
+                "\"{}\" does not exist, run RimWorld once so it creates it",
+                path.display()
+            ))
+        } else if !path.is_dir() {
+            Err(format!("\"{}\" is not a folder", path.display()))
+        } else if !path.join("Config").is_dir() && !path.join("Saves").is_dir() {
+            Err(format!(
+                "\"{}\" has no Config or Saves folder, it is not where RimWorld keeps its data",
+                path.display()
+            ))
+        } else {
+            Ok(UserDataPath(Box::from(path)))
+        }
+    }
+
+    /// Where RimWorld keeps its data when it is not started with `-savedatafolder=`
+    pub fn default_location() -> Option<PathBuf> {
+        let home = home()?;
+
+        #[cfg(target_os = "macos")]
+        let path = home.join("Library/Application Support/RimWorld");
+
+        #[cfg(target_os = "linux")]
+        let path = home.join(".config/unity3d/Ludeon Studios/RimWorld by Ludeon Studios");
+
+        #[cfg(target_os = "windows")]
+        let path = home.join(r"AppData\LocalLow\Ludeon Studios\RimWorld by Ludeon Studios");
+
+        Some(path)
+    }
+
+    /// The folder set with `-savedatafolder=` in the launch options of the game at `game_path`.
+    /// A relative one is relative to the game folder
+    pub fn from_launch_args<'a>(
+        args: impl IntoIterator<Item = &'a str>,
+        game_path: &Path,
+    ) -> Option<PathBuf> {
+        let folder = args
+            .into_iter()
+            .filter_map(|arg| arg.strip_prefix(SAVE_DATA_FOLDER))
+            .last()?
+            .trim_matches('"');
+
+        if folder.is_empty() {
+            return None;
+        }
+        Some(game_path.join(folder))
+    }
+
+    /// The data of the game at `game_path` started with `launch_args`, if RimWorld created it
+    pub fn detect<'a>(
+        game_path: &Path,
+        launch_args: impl IntoIterator<Item = &'a str>,
+    ) -> Option<UserDataPath> {
+        let path = UserDataPath::from_launch_args(launch_args, game_path)
+            .or_else(UserDataPath::default_location)?;
+        UserDataPath::create(&path).ok()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Holds `ModsConfig.xml` and the `Mod_*.xml` settings of each mod
+    pub fn config_dir(&self) -> PathBuf {
+        self.0.join("Config")
+    }
+
+    pub fn mods_config(&self) -> PathBuf {
+        self.config_dir().join("ModsConfig.xml")
+    }
+
+    pub fn saves_dir(&self) -> PathBuf {
+        self.0.join("Saves")
+    }
+
+    /// The log of the last run of the game. Unity writes it to the same place whatever
+    /// `-savedatafolder=` says
+    pub fn player_log() -> Option<PathBuf> {
+        #[cfg(target_os = "macos")]
+        return Some(
+            home()?.join("Library/Logs/Ludeon Studios/RimWorld by Ludeon Studios/Player.log"),
+        );
+
+        #[cfg(not(target_os = "macos"))]
+        Some(UserDataPath::default_location()?.join("Player.log"))
+    }
+}
+
+impl TryFrom<&Path> for UserDataPath {
+    type Error = String;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        UserDataPath::create(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserDataPath;
+    use std::path::Path;
+
+    #[test]
+    fn test_from_launch_args() {
+        let game = Path::new("/games/RimWorld");
+
+        assert_eq!(
+            UserDataPath::from_launch_args(["-quicktest", "-savedatafolder=SaveData"], game),
+            Some(game.join("SaveData"))
+        );
+        assert_eq!(
+            UserDataPath::from_launch_args(["-savedatafolder=/data/rimworld"], game),
+            Some(Path::new("/data/rimworld").to_path_buf())
+        );
+        assert_eq!(UserDataPath::from_launch_args(["-quicktest"], game), None);
+        assert_eq!(
+            UserDataPath::from_launch_args(["-savedatafolder="], game),
+            None
+        );
+    }
+
+    #[test]
+    fn test_create() {
+        let dir = std::env::temp_dir().join(format!("rrm_user_data_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(UserDataPath::create(&dir).is_err());
+        assert!(UserDataPath::create(&dir.join("missing")).is_err());
+
+        std::fs::create_dir_all(dir.join("Config")).unwrap();
+        let data = UserDataPath::create(&dir).unwrap();
+        assert_eq!(
+            data.mods_config(),
+            dir.join("Config").join("ModsConfig.xml")
+        );
+        assert_eq!(
+            UserDataPath::detect(
+                &dir,
+                [format!("-savedatafolder={}", dir.display()).as_str()]
+            ),
+            Some(data)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
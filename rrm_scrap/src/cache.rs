@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Responses from Steam saved on disk, so the same query is not sent again before `ttl` passes
+/// and can be answered without a connection
+#[derive(Debug, Clone, Default)]
+pub struct Cache {
+    /// `None` caches nothing
+    dir: Option<PathBuf>,
+    ttl: Duration,
+    offline: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    pub url: String,
+    pub fetched: u64,
+    pub body: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl Cache {
+    /// With `offline` set nothing is sent and every answer comes from `dir`, however old
+    pub fn new(dir: PathBuf, ttl: Duration, offline: bool) -> Self {
+        Cache {
+            dir: Some(dir),
+            ttl,
+            offline,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Whether an answer fetched at `fetched` can be used without asking again
+    pub(crate) fn is_fresh(&self, fetched: u64) -> bool {
+        self.offline || now().saturating_sub(fetched) < self.ttl.as_secs()
+    }
+
+    /// The URL to show, without the API key, and the name of its file in the cache
+    pub(crate) fn key(request: &reqwest::Request) -> (String, String) {
+        let mut url = request.url().clone();
+        let query: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(k, _)| k != "key")
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        if query.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(query);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(request.method().as_str());
+        hasher.update(url.as_str());
+        if let Some(body) = request.body().and_then(|b| b.as_bytes()) {
+            hasher.update(body);
+        }
+        let hash = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        (url.to_string(), hash)
+    }
+
+    pub(crate) fn load(&self, hash: &str) -> Option<CacheEntry> {
+        let path = self.dir.as_ref()?.join(format!("{hash}.json"));
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub(crate) fn store(&self, hash: &str, url: &str, body: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            fetched: now(),
+            body: body.to_string(),
+        };
+        // A cache that can not be written only means asking Steam again next time
+        let _ = std::fs::create_dir_all(dir).and_then(|_| {
+            std::fs::write(
+                dir.join(format!("{hash}.json")),
+                serde_json::to_string(&entry).unwrap_or_default(),
+            )
+        });
+    }
+
+    /// The number of cached responses in `dir` and their size in bytes
+    pub fn usage(dir: &Path) -> (usize, u64) {
+        std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|e| e.metadata().ok())
+                    .filter(|m| m.is_file())
+                    .fold((0, 0), |(n, size), m| (n + 1, size + m.len()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Removes every cached response in `dir`
+    pub fn clear(dir: &Path) -> std::io::Result<()> {
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
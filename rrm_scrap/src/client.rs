@@ -0,0 +1,537 @@
+use crate::details::{parse_details_response, MAX_IDS_PER_REQUEST};
+use crate::Http;
+use crate::{ModSteamDetails, ModSteamInfo};
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+pub const API_URL: &str = "https://api.steampowered.com";
+pub const COMMUNITY_URL: &str = "https://steamcommunity.com";
+
+const APP_ID: &str = "294100";
+
+/// `k_PublishedFileQueryType_RankedByTextSearch`
+const RANKED_BY_TEXT_SEARCH: &str = "12";
+
+const RESULTS_PER_PAGE: &str = "30";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkshopError {
+    /// The request could not be sent, or Steam answered with an error status
+    Network(String),
+    /// Steam answered with something that could not be read, like after a layout change
+    Unexpected(String),
+    /// `IPublishedFileService` can only be used with a Steam Web API key
+    NoApiKey,
+    /// In offline mode, the answer to this URL was never cached
+    NotCached(String),
+}
+
+impl Display for WorkshopError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkshopError::Network(err) => write!(f, "Could not reach Steam: {err}"),
+            WorkshopError::Unexpected(err) => write!(f, "Unexpected response from Steam: {err}"),
+            WorkshopError::NoApiKey => write!(f, "A Steam Web API key is needed"),
+            WorkshopError::NotCached(url) => write!(
+                f,
+                "Offline, and \"{url}\" is not cached. Run the same command once online first"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WorkshopError {}
+
+/// A way to read the Steam Workshop
+#[allow(async_fn_in_trait)]
+pub trait WorkshopClient {
+    /// Mods matching `query`, in the order the Workshop ranks them
+    async fn search(&self, query: &str) -> Result<Vec<ModSteamInfo>, WorkshopError>;
+
+    /// The details of every id Steam knows about. With `full` set the author, rating and
+    /// required items are read too
+    async fn details(
+        &self,
+        ids: &[usize],
+        full: bool,
+    ) -> Result<Vec<ModSteamDetails>, WorkshopError>;
+
+    /// The items of every id that is a collection, ids of single mods are left out
+    async fn collections(&self, ids: &[usize])
+        -> Result<HashMap<usize, Vec<usize>>, WorkshopError>;
+}
+
+/// Form fields listing `ids` the way the `ISteamRemoteStorage` methods expect them
+fn id_form(count_field: &str, ids: &[usize]) -> Vec<(String, String)> {
+    let mut form = vec![(count_field.to_string(), ids.len().to_string())];
+    ids.iter().enumerate().for_each(|(i, id)| {
+        form.push((format!("publishedfileids[{i}]"), id.to_string()));
+    });
+    form
+}
+
+/// Reads the Workshop item pages to fill what the API does not report. The pages are loaded
+/// at the same time, as many as `http` allows
+async fn load_pages(
+    http: &Http,
+    community_url: &str,
+    details: Vec<ModSteamDetails>,
+) -> Result<Vec<ModSteamDetails>, WorkshopError> {
+    let mut tasks = tokio::task::JoinSet::new();
+    for (i, mut details) in details.into_iter().enumerate() {
+        let http = http.clone();
+        let url = item_url(community_url, details.id);
+        tasks.spawn(async move {
+            let contents = http.read(http.get(url)).await?;
+            details.fill_from_page(&Html::parse_document(&contents));
+            Ok::<_, WorkshopError>((i, details))
+        });
+    }
+
+    let mut result = Vec::with_capacity(tasks.len());
+    while let Some(task) = tasks.join_next().await {
+        result.push(task.expect("loading a Workshop page panicked")?);
+    }
+    result.sort_by_key(|(i, _)| *i);
+    Ok(result.into_iter().map(|(_, details)| details).collect())
+}
+
+fn item_url(community_url: &str, id: usize) -> String {
+    format!("{community_url}/sharedfiles/filedetails/?id={id}")
+}
+
+#[derive(Deserialize)]
+struct CollectionResponseRaw {
+    response: CollectionListRaw,
+}
+
+#[derive(Deserialize)]
+struct CollectionListRaw {
+    #[serde(default)]
+    collectiondetails: Vec<CollectionRaw>,
+}
+
+#[derive(Deserialize)]
+struct CollectionRaw {
+    publishedfileid: String,
+    result: u32,
+    #[serde(default)]
+    children: Vec<CollectionChildRaw>,
+}
+
+#[derive(Deserialize)]
+struct CollectionChildRaw {
+    publishedfileid: String,
+}
+
+/// Parses the body of a `GetCollectionDetails` response
+pub(crate) fn parse_collection_response(
+    body: &str,
+) -> Result<HashMap<usize, Vec<usize>>, WorkshopError> {
+    let response: CollectionResponseRaw =
+        serde_json::from_str(body).map_err(|err| WorkshopError::Unexpected(err.to_string()))?;
+
+    Ok(response
+        .response
+        .collectiondetails
+        .into_iter()
+        .filter(|c| c.result == 1 && !c.children.is_empty())
+        .filter_map(|c| {
+            let id = c.publishedfileid.parse().ok()?;
+            let children = c
+                .children
+                .iter()
+                .filter_map(|child| child.publishedfileid.parse().ok())
+                .collect();
+            Some((id, children))
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct QueryResponseRaw {
+    response: QueryListRaw,
+}
+
+#[derive(Deserialize)]
+struct QueryListRaw {
+    #[serde(default)]
+    publishedfiledetails: Vec<QueryItemRaw>,
+}
+
+#[derive(Deserialize)]
+struct QueryItemRaw {
+    publishedfileid: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    short_description: String,
+    #[serde(default)]
+    creator: String,
+}
+
+#[derive(Deserialize)]
+struct PlayersResponseRaw {
+    response: PlayersListRaw,
+}
+
+#[derive(Deserialize)]
+struct PlayersListRaw {
+    #[serde(default)]
+    players: Vec<PlayerRaw>,
+}
+
+#[derive(Deserialize)]
+struct PlayerRaw {
+    steamid: String,
+    personaname: String,
+}
+
+/// The Steam Web API. Details and collections need no key, searching needs one
+#[derive(Debug, Clone)]
+pub struct SteamApi {
+    http: Http,
+    api_url: String,
+    community_url: String,
+    key: Option<String>,
+}
+
+impl SteamApi {
+    pub fn new(key: Option<String>) -> Self {
+        SteamApi::with_urls(API_URL, COMMUNITY_URL, key)
+    }
+
+    /// Talks to other hosts than Steam's, like a local mock server
+    pub fn with_urls(api_url: &str, community_url: &str, key: Option<String>) -> Self {
+        SteamApi {
+            http: Http::default(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            community_url: community_url.trim_end_matches('/').to_string(),
+            key: key.filter(|k| !k.trim().is_empty()),
+        }
+    }
+
+    /// Sends every request through `http`, sharing its cache and limits
+    pub fn with_http(mut self, http: Http) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// The names of the given Steam accounts, by their SteamID64
+    async fn player_names(
+        &self,
+        key: &str,
+        steam_ids: &[&str],
+    ) -> Result<HashMap<String, String>, WorkshopError> {
+        let url = format!("{}/ISteamUser/GetPlayerSummaries/v2/", self.api_url);
+        let body = self
+            .http
+            .read(
+                self.http
+                    .get(url)
+                    .query(&[("key", key), ("steamids", &steam_ids.join(","))]),
+            )
+            .await?;
+
+        let response: PlayersResponseRaw = serde_json::from_str(&body)
+            .map_err(|err| WorkshopError::Unexpected(err.to_string()))?;
+        Ok(response
+            .response
+            .players
+            .into_iter()
+            .map(|p| (p.steamid, p.personaname))
+            .collect())
+    }
+}
+
+impl WorkshopClient for SteamApi {
+    async fn search(&self, query: &str) -> Result<Vec<ModSteamInfo>, WorkshopError> {
+        let Some(key) = &self.key else {
+            return Err(WorkshopError::NoApiKey);
+        };
+
+        let url = format!("{}/IPublishedFileService/QueryFiles/v1/", self.api_url);
+        let body = self
+            .http
+            .read(self.http.get(url).query(&[
+                ("key", key.as_str()),
+                ("appid", APP_ID),
+                ("query_type", RANKED_BY_TEXT_SEARCH),
+                ("search_text", query),
+                ("numperpage", RESULTS_PER_PAGE),
+                ("return_short_description", "true"),
+            ]))
+            .await?;
+
+        let response: QueryResponseRaw = serde_json::from_str(&body)
+            .map_err(|err| WorkshopError::Unexpected(err.to_string()))?;
+        let items = response.response.publishedfiledetails;
+
+        // QueryFiles only has the SteamID64 of the uploader
+        let mut creators: Vec<&str> = items.iter().map(|m| m.creator.as_str()).collect();
+        creators.sort_unstable();
+        creators.dedup();
+        let names = if creators.is_empty() {
+            HashMap::new()
+        } else {
+            self.player_names(key, &creators).await.unwrap_or_default()
+        };
+
+        Ok(items
+            .iter()
+            .filter_map(|m| {
+                Some(ModSteamInfo {
+                    id: m.publishedfileid.parse().ok()?,
+                    title: m.title.clone(),
+                    description: m.short_description.clone(),
+                    author: names.get(&m.creator).cloned().unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    async fn details(
+        &self,
+        ids: &[usize],
+        full: bool,
+    ) -> Result<Vec<ModSteamDetails>, WorkshopError> {
+        let url = format!(
+            "{}/ISteamRemoteStorage/GetPublishedFileDetails/v1/",
+            self.api_url
+        );
+
+        let mut result = vec![];
+        for chunk in ids.chunks(MAX_IDS_PER_REQUEST) {
+            let body = self
+                .http
+                .read(self.http.post(&url).form(&id_form("itemcount", chunk)))
+                .await?;
+            result.extend(parse_details_response(&body)?);
+        }
+
+        if full {
+            result = load_pages(&self.http, &self.community_url, result).await?;
+        }
+
+        Ok(result)
+    }
+
+    async fn collections(
+        &self,
+        ids: &[usize],
+    ) -> Result<HashMap<usize, Vec<usize>>, WorkshopError> {
+        let url = format!(
+            "{}/ISteamRemoteStorage/GetCollectionDetails/v1/",
+            self.api_url
+        );
+
+        let mut result = HashMap::new();
+        for chunk in ids.chunks(MAX_IDS_PER_REQUEST) {
+            let body = self
+                .http
+                .read(
+                    self.http
+                        .post(&url)
+                        .form(&id_form("collectioncount", chunk)),
+                )
+                .await?;
+            result.extend(parse_collection_response(&body)?);
+        }
+
+        Ok(result)
+    }
+}
+
+/// Reads the Workshop web pages, for when the API can not be used
+#[derive(Debug, Clone)]
+pub struct Scraper {
+    http: Http,
+    community_url: String,
+}
+
+impl Scraper {
+    pub fn new() -> Self {
+        Scraper::with_url(COMMUNITY_URL)
+    }
+
+    pub fn with_url(community_url: &str) -> Self {
+        Scraper {
+            http: Http::default(),
+            community_url: community_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Sends every request through `http`, sharing its cache and limits
+    pub fn with_http(mut self, http: Http) -> Self {
+        self.http = http;
+        self
+    }
+}
+
+impl Default for Scraper {
+    fn default() -> Self {
+        Scraper::new()
+    }
+}
+
+/// Reads the results of a Workshop search page
+pub(crate) fn parse_search_page(contents: &str) -> Result<Vec<ModSteamInfo>, WorkshopError> {
+    let contents: Html = Html::parse_document(contents);
+    let script: Selector =
+        Selector::parse("#profileBlock > div > div.workshopBrowseItems > script").unwrap();
+    let author: Selector = Selector::parse("#profileBlock > div > div.workshopBrowseItems > div > div.workshopItemAuthorName.ellipsis > a").unwrap();
+
+    let mut mods_steam_info = contents
+        .select(&script)
+        .map(|elem| crate::single_decode_element(elem.inner_html()))
+        .collect::<Option<Vec<ModSteamInfo>>>()
+        .ok_or_else(|| WorkshopError::Unexpected("the search page changed".to_string()))?;
+
+    for (m, element) in mods_steam_info.iter_mut().zip(contents.select(&author)) {
+        m.author = element.inner_html();
+    }
+
+    Ok(mods_steam_info)
+}
+
+/// Reads the items of a collection page, empty when the page is of a single mod
+pub(crate) fn parse_collection_page(page: &Html) -> Vec<usize> {
+    let item: Selector = Selector::parse(".collectionChildren .collectionItem").unwrap();
+
+    page.select(&item)
+        .filter_map(|e| e.value().id()?.strip_prefix("sharedfile_")?.parse().ok())
+        .collect()
+}
+
+impl WorkshopClient for Scraper {
+    async fn search(&self, query: &str) -> Result<Vec<ModSteamInfo>, WorkshopError> {
+        let url = format!("{}/workshop/browse/", self.community_url);
+        let contents = self
+            .http
+            .read(
+                self.http
+                    .get(url)
+                    .query(&[("appid", APP_ID), ("searchtext", &format!("\"{query}\""))]),
+            )
+            .await?;
+
+        parse_search_page(&contents)
+    }
+
+    async fn details(
+        &self,
+        ids: &[usize],
+        _full: bool,
+    ) -> Result<Vec<ModSteamDetails>, WorkshopError> {
+        let details = ids
+            .iter()
+            .map(|id| ModSteamDetails {
+                id: *id,
+                ..Default::default()
+            })
+            .collect();
+
+        let mut result = load_pages(&self.http, &self.community_url, details).await?;
+        // Pages of removed items have no title
+        result.retain(|details| !details.title.is_empty());
+        Ok(result)
+    }
+
+    async fn collections(
+        &self,
+        ids: &[usize],
+    ) -> Result<HashMap<usize, Vec<usize>>, WorkshopError> {
+        let mut result = HashMap::new();
+        for id in ids {
+            let contents = self
+                .http
+                .read(self.http.get(item_url(&self.community_url, *id)))
+                .await?;
+            let children = parse_collection_page(&Html::parse_document(&contents));
+            if !children.is_empty() {
+                result.insert(*id, children);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The Web API, falling back to reading the pages when it fails
+#[derive(Debug, Clone)]
+pub struct Workshop {
+    pub api: SteamApi,
+    pub scraper: Scraper,
+}
+
+impl Workshop {
+    pub fn new(key: Option<String>) -> Self {
+        Workshop {
+            api: SteamApi::new(key),
+            scraper: Scraper::new(),
+        }
+    }
+
+    pub fn with_http(self, http: Http) -> Self {
+        Workshop {
+            api: self.api.with_http(http.clone()),
+            scraper: self.scraper.with_http(http),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.api.http.cache.is_offline()
+    }
+
+    fn warn(err: &WorkshopError) {
+        if !matches!(err, WorkshopError::NoApiKey | WorkshopError::NotCached(_)) {
+            eprintln!("Warning: {err}, reading the Workshop pages instead");
+        }
+    }
+}
+
+impl Default for Workshop {
+    fn default() -> Self {
+        Workshop::new(None)
+    }
+}
+
+impl WorkshopClient for Workshop {
+    async fn search(&self, query: &str) -> Result<Vec<ModSteamInfo>, WorkshopError> {
+        match self.api.search(query).await {
+            Ok(mods) => Ok(mods),
+            Err(err) => {
+                Workshop::warn(&err);
+                self.scraper.search(query).await
+            }
+        }
+    }
+
+    async fn details(
+        &self,
+        ids: &[usize],
+        full: bool,
+    ) -> Result<Vec<ModSteamDetails>, WorkshopError> {
+        match self.api.details(ids, full).await {
+            Ok(details) => Ok(details),
+            Err(err) => {
+                Workshop::warn(&err);
+                self.scraper.details(ids, full).await
+            }
+        }
+    }
+
+    async fn collections(
+        &self,
+        ids: &[usize],
+    ) -> Result<HashMap<usize, Vec<usize>>, WorkshopError> {
+        match self.api.collections(ids).await {
+            Ok(collections) => Ok(collections),
+            Err(err) => {
+                Workshop::warn(&err);
+                self.scraper.collections(ids).await
+            }
+        }
+    }
+}
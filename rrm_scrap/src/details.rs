@@ -0,0 +1,356 @@
+use crate::{ModSteamInfo, WorkshopError};
+use rrm_locals::{DisplayType, InfoString};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Deserializer};
+use std::io::{Stdout, Write};
+
+/// `GetPublishedFileDetails` and `GetCollectionDetails` accept at most 100 ids per request
+pub(crate) const MAX_IDS_PER_REQUEST: usize = 100;
+
+/// A Workshop item listed under "Required items" in its page
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct RequiredItem {
+    pub id: usize,
+    pub title: String,
+}
+
+/// Full details of a Workshop item, as reported by `GetPublishedFileDetails` and its page
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ModSteamDetails {
+    pub id: usize,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub file_size: u64,
+    pub time_created: u64,
+    pub time_updated: u64,
+    pub tags: Vec<String>,
+    pub subscriptions: u64,
+    /// Stars out of 5, `None` when the item does not have enough ratings yet
+    pub rating: Option<u8>,
+    pub num_ratings: Option<u64>,
+    pub required_items: Vec<RequiredItem>,
+}
+
+#[derive(Deserialize)]
+struct DetailsResponseRaw {
+    response: DetailsListRaw,
+}
+
+#[derive(Deserialize)]
+struct DetailsListRaw {
+    #[serde(default)]
+    publishedfiledetails: Vec<PublishedFileRaw>,
+}
+
+#[derive(Deserialize)]
+struct TagRaw {
+    tag: String,
+}
+
+/// Raw item from the `GetPublishedFileDetails` response
+#[derive(Deserialize)]
+struct PublishedFileRaw {
+    publishedfileid: String,
+    result: u32,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    file_size: u64,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    time_created: u64,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    time_updated: u64,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    subscriptions: u64,
+    #[serde(default)]
+    tags: Vec<TagRaw>,
+}
+
+/// Steam sometimes sends numbers as strings, accept both
+fn lenient_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(n) => Ok(n.as_u64().unwrap_or_default()),
+        serde_json::Value::String(s) => Ok(s.parse().unwrap_or_default()),
+        _ => Ok(0),
+    }
+}
+
+impl From<PublishedFileRaw> for ModSteamDetails {
+    fn from(value: PublishedFileRaw) -> Self {
+        ModSteamDetails {
+            id: value.publishedfileid.parse().unwrap_or_default(),
+            title: value.title,
+            description: value.description,
+            file_size: value.file_size,
+            time_created: value.time_created,
+            time_updated: value.time_updated,
+            subscriptions: value.subscriptions,
+            tags: value.tags.into_iter().map(|t| t.tag).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ModSteamDetails> for ModSteamInfo {
+    fn from(value: ModSteamDetails) -> Self {
+        ModSteamInfo {
+            id: value.id,
+            title: value.title,
+            description: value.description,
+            author: value.author,
+        }
+    }
+}
+
+/// Parses the body of a `GetPublishedFileDetails` response, skipping items Steam could not find
+pub(crate) fn parse_details_response(body: &str) -> Result<Vec<ModSteamDetails>, WorkshopError> {
+    let response: DetailsResponseRaw =
+        serde_json::from_str(body).map_err(|err| WorkshopError::Unexpected(err.to_string()))?;
+
+    Ok(response
+        .response
+        .publishedfiledetails
+        .into_iter()
+        .filter(|item| item.result == 1)
+        .map(ModSteamDetails::from)
+        .collect())
+}
+
+/// Reads the text of the first element matching `selector`
+fn parse_text(page: &Html, selector: &str) -> Option<String> {
+    let selector: Selector = Selector::parse(selector).unwrap();
+    page.select(&selector)
+        .next()
+        .map(|e| e.text().collect::<String>().trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Reads the "Required items" block of a Workshop item page
+pub(crate) fn parse_required_items(page: &Html) -> Vec<RequiredItem> {
+    let link: Selector = Selector::parse("#RequiredItems > a").unwrap();
+    let title: Selector = Selector::parse(".requiredItem").unwrap();
+    let re = regex::Regex::new(r"[?&]id=(?P<id>\d+)").unwrap();
+
+    page.select(&link)
+        .filter_map(|a| {
+            let href = a.value().attr("href")?;
+            let id = re.captures(href)?["id"].parse().ok()?;
+            let title = a
+                .select(&title)
+                .next()
+                .map(|t| t.text().collect::<String>().trim().to_string())
+                .unwrap_or_default();
+
+            Some(RequiredItem { id, title })
+        })
+        .collect()
+}
+
+/// Reads the star rating and number of ratings of a Workshop item page
+pub(crate) fn parse_rating(page: &Html) -> (Option<u8>, Option<u64>) {
+    let stars: Selector = Selector::parse(".fileRatingDetails img").unwrap();
+    let count: Selector = Selector::parse(".numRatings").unwrap();
+
+    let stars = page
+        .select(&stars)
+        .next()
+        .and_then(|img| img.value().attr("src"))
+        .and_then(|src| {
+            let name = src.rsplit('/').next()?;
+            name.split_once("-star")?.0.parse().ok()
+        });
+
+    let count = page.select(&count).next().and_then(|c| {
+        c.text()
+            .collect::<String>()
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+            .parse()
+            .ok()
+    });
+
+    (stars, count)
+}
+
+/// Reads the name of the first creator of a Workshop item page
+pub(crate) fn parse_author(page: &Html) -> Option<String> {
+    let author: Selector = Selector::parse(".creatorsBlock .friendBlockContent").unwrap();
+
+    page.select(&author).next().and_then(|a| {
+        a.text()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    })
+}
+
+impl ModSteamDetails {
+    /// Fills the fields only available in the Workshop item page, and the title and
+    /// description if the API did not give them
+    pub(crate) fn fill_from_page(&mut self, page: &Html) {
+        if self.title.is_empty() {
+            self.title = parse_text(page, ".workshopItemTitle").unwrap_or_default();
+        }
+        if self.description.is_empty() {
+            self.description = parse_text(page, ".workshopItemDescription").unwrap_or_default();
+        }
+        if let Some(author) = parse_author(page) {
+            self.author = author;
+        }
+        (self.rating, self.num_ratings) = parse_rating(page);
+        self.required_items = parse_required_items(page);
+    }
+
+    /// Tags that name a game version, like `1.5`
+    pub fn game_versions(&self) -> Vec<&str> {
+        self.tags
+            .iter()
+            .map(String::as_str)
+            .filter(|tag| is_version_tag(tag))
+            .collect()
+    }
+
+    /// Tags that are not a game version
+    pub fn other_tags(&self) -> Vec<&str> {
+        self.tags
+            .iter()
+            .map(String::as_str)
+            .filter(|tag| !is_version_tag(tag))
+            .collect()
+    }
+
+    /// Generates the headers of the table with the {size} spacing
+    pub fn gen_headers(size: usize) -> String {
+        "".to_string()
+            .add_s(format!("{:>15}", "Steam ID"))
+            .add_s(format!("   {:>10}", "Size"))
+            .add_s(format!("   {:<10}", "Updated"))
+            .add_s(format!("   {:<size$}", "Name", size = size))
+            .add_s(format!("\n{:>15}", "--------"))
+            .add_s(format!("   {:>10}", "--------"))
+            .add_s(format!("   {:<10}", "--------"))
+            .add_s(format!("   {:<size$}", "--------", size = size))
+    }
+
+    pub fn gen_large(&self) -> String {
+        let mut result = ""
+            .to_string()
+            .add_s(format!("Name        : {}\n", self.title))
+            .add_s(format!("Steam ID    : {}\n", self.id))
+            .add_s(format!("Author      : {}\n", self.author))
+            .add_s(format!("File size   : {}\n", human_size(self.file_size)))
+            .add_s(format!(
+                "Created     : {}\n",
+                format_timestamp(self.time_created)
+            ))
+            .add_s(format!(
+                "Updated     : {}\n",
+                format_timestamp(self.time_updated)
+            ))
+            .add_s(format!(
+                "Versions    : {}\n",
+                self.game_versions().join(" ")
+            ))
+            .add_s(format!("Tags        : {}\n", self.other_tags().join(", ")))
+            .add_s(format!("Subscribers : {}\n", self.subscriptions));
+
+        match (self.rating, self.num_ratings) {
+            (Some(stars), Some(n)) => {
+                result.push_str(&format!("Rating      : {stars}/5 ({n} ratings)\n"))
+            }
+            (Some(stars), None) => result.push_str(&format!("Rating      : {stars}/5\n")),
+            _ => result.push_str("Rating      : Not enough ratings\n"),
+        }
+
+        if !self.required_items.is_empty() {
+            result.push_str("Required items:\n");
+            self.required_items.iter().for_each(|item| {
+                result.push_str(&format!("{:>15}   {}\n", item.id, item.title));
+            });
+        }
+
+        result.push_str(&format!("Description: {}\n", self.description));
+        result
+    }
+
+    pub fn gen_short(&self, biggest_name: usize) -> String {
+        "".to_string()
+            .add_s(format!("{:>15}", self.id))
+            .add_s(format!("   {:>10}", human_size(self.file_size)))
+            .add_s(format!(
+                "   {:<10}",
+                format_timestamp(self.time_updated)
+                    .split(' ')
+                    .next()
+                    .unwrap_or_default()
+            ))
+            .add_s(format!("   {:<size$}", self.title, size = biggest_name))
+    }
+
+    pub fn gen_display(&self, form: &DisplayType, biggest_name: usize) -> String {
+        if let DisplayType::Long = form {
+            self.gen_large()
+        } else {
+            self.gen_short(biggest_name)
+        }
+    }
+
+    pub fn display(&self, form: &DisplayType, biggest_name: usize) {
+        let mut f: Stdout = std::io::stdout();
+        writeln!(f, "{}", self.gen_display(form, biggest_name)).unwrap()
+    }
+}
+
+fn is_version_tag(tag: &str) -> bool {
+    tag.split('.').count() == 2
+        && tag
+            .split('.')
+            .all(|n| n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Formats a byte count like `12.3 MB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Formats a unix timestamp like `2024-03-01 17:04 UTC`
+pub fn format_timestamp(secs: u64) -> String {
+    if secs == 0 {
+        return "Unknown".to_string();
+    }
+
+    // Days to civil date, from Howard Hinnant's `civil_from_days`
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let secs_of_day = secs % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60
+    )
+}
@@ -0,0 +1,235 @@
+use crate::{format_timestamp, Cache, WorkshopError};
+use reqwest::StatusCode;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+/// How the Workshop clients talk to Steam
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpConfig {
+    /// For the whole request, from connecting to reading the body
+    pub timeout: Duration,
+    /// How many times a request that failed to connect, timed out or got a 429 or 5xx answer is
+    /// sent again
+    pub retries: u32,
+    /// How many requests can be waiting for an answer at the same time
+    pub concurrency: usize,
+    /// Requests started per second, 0 for no limit
+    pub rate_limit: u32,
+    /// Like `http://proxy:3128`. Without it the `HTTPS_PROXY` and `HTTP_PROXY` variables are
+    /// used
+    pub proxy: Option<String>,
+    pub user_agent: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            concurrency: 4,
+            rate_limit: 10,
+            proxy: None,
+            user_agent: format!("rrm/{}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+/// The limits every clone of an `Http` shares
+#[derive(Debug)]
+struct Limits {
+    in_flight: Semaphore,
+    /// When the next request may start
+    next_start: Mutex<Instant>,
+    interval: Option<Duration>,
+}
+
+/// The HTTP client of the Workshop clients, reading and filling the cache
+#[derive(Debug, Clone)]
+pub struct Http {
+    client: reqwest::Client,
+    pub cache: Cache,
+    retries: u32,
+    limits: Arc<Limits>,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Http::new(&HttpConfig::default(), Cache::default()).unwrap()
+    }
+}
+
+/// A random delay of up to `max`, so clients that failed together do not retry together
+fn jitter(max: Duration) -> Duration {
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    max.mul_f64((random % 1000) as f64 / 1000.0)
+}
+
+/// How long to wait before sending a request again, doubling after every attempt
+fn backoff(attempt: u32) -> Duration {
+    let base = Duration::from_millis(500) * 2u32.pow(attempt.min(6));
+    base + jitter(base)
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+impl Http {
+    pub fn new(config: &HttpConfig, cache: Cache) -> Result<Self, String> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.timeout.min(Duration::from_secs(10)))
+            .user_agent(&config.user_agent);
+
+        if let Some(proxy) = &config.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|err| format!("Invalid proxy \"{proxy}\": {err}"))?;
+            builder = builder.proxy(proxy);
+        }
+
+        let client = builder.build().map_err(|err| err.to_string())?;
+
+        Ok(Http {
+            client,
+            cache,
+            retries: config.retries,
+            limits: Arc::new(Limits {
+                in_flight: Semaphore::new(config.concurrency.max(1)),
+                next_start: Mutex::new(Instant::now()),
+                interval: (config.rate_limit > 0)
+                    .then(|| Duration::from_secs(1) / config.rate_limit),
+            }),
+        })
+    }
+
+    pub fn get(&self, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Waits for the turn of the next request under the rate limit
+    async fn wait_turn(&self) {
+        let Some(interval) = self.limits.interval else {
+            return;
+        };
+
+        let start = {
+            let mut next_start = self.limits.next_start.lock().await;
+            let start = (*next_start).max(Instant::now());
+            *next_start = start + interval;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    /// Sends `request`, again after a while if it fails in a way that may not last
+    async fn send(&self, request: reqwest::Request) -> Result<reqwest::Response, WorkshopError> {
+        let _permit = self.limits.in_flight.acquire().await.unwrap();
+
+        let mut attempt = 0;
+        loop {
+            let Some(this_try) = request.try_clone() else {
+                return Err(WorkshopError::Network(
+                    "the request can not be sent".to_string(),
+                ));
+            };
+
+            self.wait_turn().await;
+            let result = self.client.execute(this_try).await;
+
+            let wait = match &result {
+                Err(err) if err.is_connect() || err.is_timeout() => Some(backoff(attempt)),
+                Ok(resp) if is_retryable(resp.status()) => Some(
+                    resp.headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok()?.parse().ok())
+                        .map(Duration::from_secs)
+                        .unwrap_or_else(|| backoff(attempt)),
+                ),
+                _ => None,
+            };
+
+            let Some(wait) = wait.filter(|_| attempt < self.retries) else {
+                let resp = result.map_err(|err| WorkshopError::Network(err.to_string()))?;
+
+                let status = resp.status();
+                if !status.is_success() {
+                    return Err(WorkshopError::Network(format!("HTTP {status}")));
+                }
+                return Ok(resp);
+            };
+
+            attempt += 1;
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn fetch(&self, request: reqwest::Request) -> Result<String, WorkshopError> {
+        self.send(request)
+            .await?
+            .text()
+            .await
+            .map_err(|err| WorkshopError::Network(err.to_string()))
+    }
+
+    /// Downloads a file, never from the cache
+    pub async fn download(&self, url: &str) -> Result<Vec<u8>, WorkshopError> {
+        let request = self
+            .get(url)
+            .build()
+            .map_err(|err| WorkshopError::Network(err.to_string()))?;
+
+        let bytes = self
+            .send(request)
+            .await?
+            .bytes()
+            .await
+            .map_err(|err| WorkshopError::Network(err.to_string()))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Sends `request` unless a fresh enough answer is cached. When Steam can not be reached an
+    /// older answer is used if there is one
+    pub async fn read(&self, request: reqwest::RequestBuilder) -> Result<String, WorkshopError> {
+        let request = request
+            .build()
+            .map_err(|err| WorkshopError::Network(err.to_string()))?;
+        let (url, hash) = Cache::key(&request);
+
+        let cached = self.cache.load(&hash);
+        if let Some(entry) = &cached {
+            if self.cache.is_fresh(entry.fetched) {
+                return Ok(entry.body.clone());
+            }
+        }
+
+        if self.cache.is_offline() {
+            return Err(WorkshopError::NotCached(url));
+        }
+
+        match self.fetch(request).await {
+            Ok(body) => {
+                self.cache.store(&hash, &url, &body);
+                Ok(body)
+            }
+            Err(err) => match cached {
+                Some(entry) => {
+                    eprintln!(
+                        "Warning: {err}, using the answer cached on {}",
+                        format_timestamp(entry.fetched)
+                    );
+                    Ok(entry.body)
+                }
+                None => Err(err),
+            },
+        }
+    }
+}
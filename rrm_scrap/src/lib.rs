@@ -1,13 +1,22 @@
 pub use flagset::*;
 use fuzzy_matcher::FuzzyMatcher;
 use lazy_regex::*;
-use reqwest::Response;
 use rrm_locals::{DisplayType, InfoString};
 use std::io::Stdout;
 use std::io::Write;
 use std::ops::Deref;
 use std::process::{exit, Stdio};
 
+mod cache;
+mod client;
+mod details;
+mod http;
+
+pub use cache::*;
+pub use client::*;
+pub use details::*;
+pub use http::*;
+
 #[cfg(test)]
 mod test;
 
@@ -17,59 +26,18 @@ fn capitalize(s: &str) -> String {
     ccc.to_uppercase() + bbb
 }
 
-async fn get_contents(url: String) -> String {
-    let resp: Response = reqwest::get(url).await.unwrap_or_else(|err| {
-        eprintln!("{}", capitalize(&err.to_string()));
-        exit(1);
-    });
-
-    let resp: String = resp.text().await.unwrap_or_else(|err| {
-        eprintln!("{}", capitalize(&err.to_string()));
-        exit(1);
-    });
-
-    resp
-}
-
-pub async fn look_for_mod(mod_name: &str) -> (Vec<ModSteamInfo>, usize) {
-    use scraper::{Html, Selector};
-
-    let contents: String = get_contents(
-        r#"https://steamcommunity.com/workshop/browse/?appid=294100&searchtext="URL""#
-            .replace("URL", mod_name),
-    )
-    .await;
-
-    let contents: Html = Html::parse_document(&contents);
-    let script: Selector =
-        Selector::parse("#profileBlock > div > div.workshopBrowseItems > script").unwrap();
-
-    let mut mods_steam_info: Vec<ModSteamInfo> = contents
-        .select(&script)
-        .map(|elem| single_decode_element(elem.inner_html()))
-        .collect();
-
-    let author: Selector = Selector::parse("#profileBlock > div > div.workshopBrowseItems > div > div.workshopItemAuthorName.ellipsis > a").unwrap();
-
-    // What this size for?
-    let mut size: usize = 0;
-
-    for (i, element) in contents.select(&author).enumerate() {
-        mods_steam_info[i].author = element.inner_html();
-        if mods_steam_info[i].title.len() > size {
-            size = mods_steam_info[i].title.len();
-        }
-    }
-
-    (mods_steam_info, size)
-}
-
 /// From a <script> tag from steam's workshop page, gets the relevant info about said mod
-fn single_decode_element(element_contents: String) -> ModSteamInfo {
+fn single_decode_element(element_contents: String) -> Option<ModSteamInfo> {
     let re = regex::Regex::new(r"\{.{1,}\}").unwrap();
-    let mm = re.find(element_contents.trim()).unwrap().as_str();
-
-    serde_json::from_str::<ModSteamInfoRaw>(mm).unwrap().into()
+    let mm = re.find(element_contents.trim())?.as_str();
+
+    let raw = serde_json::from_str::<ModSteamInfoRaw>(mm).ok()?;
+    Some(ModSteamInfo {
+        id: raw.id.parse().ok()?,
+        title: raw.title,
+        description: raw.description,
+        author: raw.author,
+    })
 }
 
 #[derive(Default, Clone, Debug, PartialEq)]
@@ -94,17 +62,6 @@ fn default_author() -> String {
     "".to_string()
 }
 
-impl From<ModSteamInfoRaw> for ModSteamInfo {
-    fn from(value: ModSteamInfoRaw) -> Self {
-        ModSteamInfo {
-            id: value.id.parse().unwrap(),
-            title: value.title,
-            description: value.description,
-            author: value.author,
-        }
-    }
-}
-
 impl ModSteamInfo {
     /// Generates the headers of the table with the {size} spacing
     fn gen_headers(size: usize) -> String {
@@ -169,8 +126,17 @@ impl SteamMods {
         SteamMods::default()
     }
 
-    pub async fn search(m: &str) -> Self {
-        let (mods, biggest_name_size) = look_for_mod(m).await;
+    pub async fn search<C: WorkshopClient>(client: &C, m: &str) -> Self {
+        let mods = client.search(m).await.unwrap_or_else(|err| {
+            eprintln!("{}", capitalize(&err.to_string()));
+            exit(1);
+        });
+
+        SteamMods::from_mods(mods)
+    }
+
+    pub fn from_mods(mods: Vec<ModSteamInfo>) -> Self {
+        let biggest_name_size = mods.iter().map(|m| m.title.len()).max().unwrap_or(0);
 
         SteamMods {
             mods,
@@ -213,7 +179,7 @@ impl SteamMods {
                 result.push_str(&format!(
                     " {:<4} {}",
                     i,
-                    &m.gen_display(&DisplayType::Short, self.biggest_name_size)
+                    m.gen_display(&DisplayType::Short, self.biggest_name_size)
                 ));
             });
         }
@@ -1,9 +1,9 @@
 use rrm_locals::DisplayType;
-use rrm_scrap::SteamMods;
+use rrm_scrap::{SteamMods, Workshop};
 
 #[tokio::main]
 async fn main() {
-    let mods = SteamMods::search("Fluffy")
+    let mods = SteamMods::search(&Workshop::default(), "Fluffy")
         .await
         .with_display(DisplayType::Long);
 
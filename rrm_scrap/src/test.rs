@@ -25,7 +25,7 @@ pub fn single_decode_mod_test() {
     let script_data = "\n\t\t\t\tSharedFileBindMouseHover( \"sharedfile_3403180654\", false, {\"id\":\"3403180654\",\"title\":\"Alpha Books\",\"description\":\"https:\\/\\/i.imgur.com\\/rLaa7So.png Features Alpha Books brings: - 19 new types of books that are all single-use, and give a bonus to the reader. From useful hediffs, to abilities, to unlocking new map locations, there is variety enough to spice up any playthr...\",\"user_subscribed\":false,\"user_favorited\":false,\"played\":false,\"appid\":294100} );\n\t\t\t";
     let element = super::single_decode_element(script_data.to_string());
 
-    assert_eq!(target_mod, element)
+    assert_eq!(Some(target_mod), element)
 }
 
 #[test]
@@ -37,3 +37,323 @@ pub fn gen_headers_test() {
         "       Steam ID   Name         Uploader            \n       --------   --------     --------            ".to_string()
     )
 }
+
+#[test]
+pub fn parse_details_response_test() {
+    let body = r#"{"response":{"result":1,"resultcount":2,"publishedfiledetails":[
+        {"publishedfileid":"2009463077","result":1,"title":"Harmony","description":"Lib",
+         "file_size":"1234567","time_created":1581177600,"time_updated":1709312640,
+         "subscriptions":3000000,"tags":[{"tag":"Mod"},{"tag":"1.4"},{"tag":"1.5"}]},
+        {"publishedfileid":"1","result":9}
+    ]}}"#;
+
+    let details = super::parse_details_response(body).unwrap();
+
+    assert_eq!(details.len(), 1);
+    assert_eq!(details[0].id, 2009463077);
+    assert_eq!(details[0].file_size, 1234567);
+    assert_eq!(details[0].game_versions(), vec!["1.4", "1.5"]);
+    assert_eq!(details[0].other_tags(), vec!["Mod"]);
+}
+
+#[test]
+pub fn parse_item_page_test() {
+    use super::RequiredItem;
+    use scraper::Html;
+
+    let page = Html::parse_document(
+        r#"<div class="creatorsBlock"><div class="friendBlockContent">
+            Brrainz<br><span class="friendSmallText">Offline</span></div></div>
+        <div class="fileRatingDetails"><img src="https://community.akamai.steamstatic.com/public/images/sharedfiles/4-star_large.png?v=2"></div>
+        <div class="numRatings">12,345 ratings</div>
+        <div id="RequiredItems">
+            <a href="https://steamcommunity.com/workshop/filedetails/?id=2009463077" target="_blank">
+                <div class="requiredItem">Harmony</div>
+            </a>
+        </div>"#,
+    );
+
+    assert_eq!(super::parse_author(&page), Some("Brrainz".to_string()));
+    assert_eq!(super::parse_rating(&page), (Some(4), Some(12345)));
+    assert_eq!(
+        super::parse_required_items(&page),
+        vec![RequiredItem {
+            id: 2009463077,
+            title: "Harmony".to_string()
+        }]
+    );
+}
+
+#[test]
+pub fn format_helpers_test() {
+    assert_eq!(super::human_size(512), "512 B");
+    assert_eq!(super::human_size(1234567), "1.2 MB");
+    assert_eq!(super::format_timestamp(1709312640), "2024-03-01 17:04 UTC");
+    assert_eq!(super::format_timestamp(0), "Unknown");
+}
+
+#[test]
+pub fn parse_collection_response_test() {
+    let body = r#"{"response":{"result":1,"resultcount":2,"collectiondetails":[
+        {"publishedfileid":"100","result":1,"children":[
+            {"publishedfileid":"2009463077","sortorder":1,"filetype":0},
+            {"publishedfileid":"818773962","sortorder":2,"filetype":0}]},
+        {"publishedfileid":"2009463077","result":1}
+    ]}}"#;
+
+    let collections = super::parse_collection_response(body).unwrap();
+
+    assert_eq!(collections.len(), 1);
+    assert_eq!(collections[&100], vec![2009463077, 818773962]);
+}
+
+/// Serves `routes` as `(path prefix, status, body)` on a local port, returning its URL
+async fn mock_server(routes: Vec<(&'static str, u16, String)>) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let routes = routes.clone();
+            tokio::spawn(async move {
+                // Read the headers and then as much body as they announce
+                let mut request = vec![];
+                let mut buf = [0; 4096];
+                let head_end = loop {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&request[..head_end]).to_lowercase();
+                let length: usize = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .and_then(|l| l.trim().parse().ok())
+                    .unwrap_or(0);
+                while request.len() < head_end + length {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (status, body) = routes
+                    .iter()
+                    .find(|(prefix, ..)| path.starts_with(&prefix.to_lowercase()))
+                    .map(|(_, status, body)| (*status, body.clone()))
+                    .unwrap_or((404, String::new()));
+
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    url
+}
+
+#[tokio::test]
+pub async fn steam_api_client_test() {
+    use super::{SteamApi, WorkshopClient, WorkshopError};
+
+    let url = mock_server(vec![
+        (
+            "/ISteamRemoteStorage/GetPublishedFileDetails/",
+            200,
+            r#"{"response":{"publishedfiledetails":[
+                {"publishedfileid":"2009463077","result":1,"title":"Harmony","tags":[{"tag":"1.5"}]}
+            ]}}"#
+                .to_string(),
+        ),
+        (
+            "/ISteamRemoteStorage/GetCollectionDetails/",
+            200,
+            r#"{"response":{"collectiondetails":[
+                {"publishedfileid":"100","result":1,"children":[{"publishedfileid":"2009463077"}]}
+            ]}}"#
+                .to_string(),
+        ),
+        (
+            "/IPublishedFileService/QueryFiles/",
+            200,
+            r#"{"response":{"total":1,"publishedfiledetails":[
+                {"publishedfileid":"2009463077","title":"Harmony","short_description":"Lib","creator":"76561198000000000"}
+            ]}}"#
+                .to_string(),
+        ),
+        (
+            "/ISteamUser/GetPlayerSummaries/",
+            200,
+            r#"{"response":{"players":[{"steamid":"76561198000000000","personaname":"Brrainz"}]}}"#
+                .to_string(),
+        ),
+    ])
+    .await;
+
+    let api = SteamApi::with_urls(&url, &url, None);
+    let details = api.details(&[2009463077], false).await.unwrap();
+    assert_eq!(details[0].title, "Harmony");
+    assert_eq!(details[0].game_versions(), vec!["1.5"]);
+
+    let collections = api.collections(&[100]).await.unwrap();
+    assert_eq!(collections[&100], vec![2009463077]);
+
+    assert_eq!(api.search("harmony").await, Err(WorkshopError::NoApiKey));
+
+    let api = SteamApi::with_urls(&url, &url, Some("KEY".to_string()));
+    let found = api.search("harmony").await.unwrap();
+    assert_eq!(found[0].id, 2009463077);
+    assert_eq!(found[0].author, "Brrainz");
+}
+
+#[tokio::test]
+pub async fn workshop_fallback_test() {
+    use super::{Http, HttpConfig, Scraper, SteamApi, Workshop, WorkshopClient};
+
+    let page = r#"<div class="workshopItemTitle">Harmony</div>
+        <div class="creatorsBlock"><div class="friendBlockContent">Brrainz</div></div>"#;
+    let search = r#"<div id="profileBlock"><div><div class="workshopBrowseItems">
+        <script>SharedFileBindMouseHover( "sharedfile_2009463077", false, {"id":"2009463077","title":"Harmony","description":"Lib"} );</script>
+        <div><div class="workshopItemAuthorName ellipsis"><a>Brrainz</a></div></div>
+    </div></div></div>"#;
+
+    let url = mock_server(vec![
+        ("/ISteamRemoteStorage/", 503, String::new()),
+        ("/sharedfiles/filedetails/", 200, page.to_string()),
+        ("/workshop/browse/", 200, search.to_string()),
+    ])
+    .await;
+
+    // Retrying the API would only slow the test down
+    let http = Http::new(
+        &HttpConfig {
+            retries: 0,
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .unwrap();
+    let workshop = Workshop {
+        api: SteamApi::with_urls(&url, &url, None),
+        scraper: Scraper::with_url(&url),
+    }
+    .with_http(http);
+
+    let details = workshop.details(&[2009463077], true).await.unwrap();
+    assert_eq!(details[0].title, "Harmony");
+    assert_eq!(details[0].author, "Brrainz");
+
+    let found = workshop.search("harmony").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].title, "Harmony");
+    assert_eq!(found[0].author, "Brrainz");
+
+    assert!(workshop
+        .collections(&[2009463077])
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+pub async fn cache_offline_test() {
+    use super::{Cache, Http, HttpConfig, Scraper, WorkshopClient, WorkshopError};
+    use std::time::Duration;
+
+    let http = |cache| Http::new(&HttpConfig::default(), cache).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("rrm_cache_{}", std::process::id()));
+    let page = r#"<div class="workshopItemTitle">Harmony</div>"#;
+    let url = mock_server(vec![("/sharedfiles/filedetails/", 200, page.to_string())]).await;
+
+    let online = Scraper::with_url(&url).with_http(http(Cache::new(
+        dir.clone(),
+        Duration::from_secs(60),
+        false,
+    )));
+    let details = online.details(&[2009463077], false).await.unwrap();
+    assert_eq!(details[0].title, "Harmony");
+    assert_eq!(Cache::usage(&dir).0, 1);
+
+    // Offline nothing is sent, every answer has to come from the cache
+    let offline =
+        Scraper::with_url(&url).with_http(http(Cache::new(dir.clone(), Duration::ZERO, true)));
+    let details = offline.details(&[2009463077], false).await.unwrap();
+    assert_eq!(details[0].title, "Harmony");
+    assert!(matches!(
+        offline.details(&[1], false).await,
+        Err(WorkshopError::NotCached(_))
+    ));
+
+    Cache::clear(&dir).unwrap();
+    assert_eq!(Cache::usage(&dir), (0, 0));
+}
+
+#[tokio::test]
+pub async fn http_retry_test() {
+    use super::{Cache, Http, HttpConfig};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Busy every other request
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let heads = Arc::new(Mutex::new(vec![]));
+    let seen = heads.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0; 4096];
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            let count = {
+                let mut heads = seen.lock().unwrap();
+                heads.push(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                heads.len()
+            };
+            let response = if count % 2 == 1 {
+                "HTTP/1.1 503 Busy\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    let config = HttpConfig {
+        user_agent: "rrm-test".to_string(),
+        ..Default::default()
+    };
+    let http = Http::new(&config, Cache::default()).unwrap();
+    assert_eq!(http.read(http.get(&url)).await.unwrap(), "ok");
+
+    {
+        let heads = heads.lock().unwrap();
+        assert_eq!(heads.len(), 2);
+        assert!(heads[1].contains("user-agent: rrm-test"));
+    }
+
+    // Without retries the 503 is the answer
+    let config = HttpConfig {
+        retries: 0,
+        ..Default::default()
+    };
+    let http = Http::new(&config, Cache::default()).unwrap();
+    assert!(http.read(http.get(&url)).await.is_err());
+
+    let config = HttpConfig {
+        proxy: Some("not a proxy".to_string()),
+        ..Default::default()
+    };
+    assert!(Http::new(&config, Cache::default()).is_err());
+}
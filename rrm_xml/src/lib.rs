@@ -12,6 +12,9 @@ pub struct Element {
 
 pub trait XMLFile {
     fn values_of(&self, keys: &[&str]) -> Vec<Element>;
+
+    /// Parses the whole file, returning the first problem found
+    fn validate(&self) -> Result<(), String>;
 }
 
 impl Clone for Element {
@@ -84,25 +87,92 @@ impl XMLFile for File {
 
         r
     }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut file = BufReader::new(self);
+        let mut contents: Vec<u8> = Vec::new();
+
+        file.read_to_end(&mut contents).map_err(|e| e.to_string())?;
+        fix_common_issues(&mut contents);
+
+        let contents = String::from_utf8(contents).map_err(|e| e.to_string())?;
+
+        for e in EventReader::from_str(&contents) {
+            e.map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
 }
 
 #[test]
 fn test() {
     use crate::*;
 
-    let file = File::open("/Applications/RimWorld.app/Mods/Achtung/About/Manifest.xml").unwrap();
-    let r = file.values_of(&["version", "identifier"]);
-    println!("{:?}", r);
-
-    let file = File::open("/Applications/RimWorld.app/Mods/Achtung/About/About.xml").unwrap();
-    let r = file.values_of(&["version", "identifier"]);
-    println!("{:?}", r);
-
-    let file =
-        File::open("/Applications/RimWorld.app/Mods/Area_unlocker/About/Manifest.xml").unwrap();
-    let r = file.values_of(&["version", "identifier"]);
+    let dir = std::env::temp_dir().join(format!("rrm_xml_values_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let values = |name: &str, contents: &str| -> Vec<(String, String)> {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        File::open(&path)
+            .unwrap()
+            .values_of(&["version", "identifier"])
+            .into_iter()
+            .map(|e| (e.name, e.value))
+            .collect()
+    };
+
+    let manifest = values(
+        "Manifest.xml",
+        "<Manifest><identifier>Achtung</identifier><version>3.1.5</version></Manifest>",
+    );
+    assert_eq!(
+        manifest,
+        [
+            ("identifier".to_string(), "Achtung".to_string()),
+            ("version".to_string(), "3.1.5".to_string())
+        ]
+    );
+
+    let about = values(
+        "About.xml",
+        "\u{FEFF}<ModMetaData><name>Achtung!</name><modDependencies><li>\
+         <steamWorkshopUrl>steam://url/CommunityFilePage/2009463077</steamWorkshopUrl>\
+         </li></modDependencies></ModMetaData>",
+    );
+    assert_eq!(
+        about,
+        [("steamWorkshopUrl".to_string(), "2009463077".to_string())]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
 
-    println!("{:?}", r);
+#[test]
+fn validate_test() {
+    use std::io::{Seek, Write};
+
+    let path = std::env::temp_dir().join(format!("rrm_xml_validate_{}.xml", std::process::id()));
+    let mut file = File::options()
+        .create(true)
+        .truncate(true)
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+
+    file.write_all(b"<ModMetaData><name>Broken</name>").unwrap();
+    file.rewind().unwrap();
+    assert!(file.validate().is_err());
+
+    file.set_len(0).unwrap();
+    file.rewind().unwrap();
+    file.write_all(b"\xEF\xBB\xBF<ModMetaData><name>Fine</name></ModMetaData>")
+        .unwrap();
+    file.rewind().unwrap();
+    assert_eq!(file.validate(), Ok(()));
+
+    std::fs::remove_file(path).unwrap();
 }
 
 fn fix_common_issues(contents: &mut Vec<u8>) {
@@ -1,61 +1,90 @@
 use crate::utils::*;
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct App {
+    /// Run against this instance instead of the one set with `rrm instance use`
+    #[clap(long, global = true)]
+    pub(crate) instance: Option<String>,
+
+    /// Override a setting for this run only, like `--config use-pager=false`
+    #[clap(long = "config", global = true, value_name = "KEY=VALUE", value_parser = parse_override)]
+    pub(crate) overrides: Vec<(String, String)>,
+
+    /// Only use cached Workshop answers and local mods, same as `--config offline=true`
+    #[clap(long, global = true)]
+    pub(crate) offline: bool,
+
+    /// Wait for another rrm or RimWorld using the same instance instead of stopping
+    #[clap(long, global = true)]
+    pub(crate) wait: bool,
+
     #[clap(subcommand)]
     pub(crate) command: Commands,
 }
 
 #[derive(Subcommand, Debug)]
-#[clap(override_help = "\
-rrm-set
-Set new configuration values
-
-USAGE:
-    rrm set <OPTION> <VALUE>
-
-OPTIONS:
-    game-path    Set the path where RimWorld is installed [alias: 'path']
-    pager        Set the paging software to use, like bat, more or less [alias: 'paging']
-    use-pager    Set if rrm should use more to display output [values: false, true, 0, 1] [alias: 'use-paging']
-")]
-pub enum Options {
-    #[clap(
-        about = "Set if rrm should use paging software to display output [values: false, true, 0, 1]",
-        visible_alias = "use-paging"
-    )]
-    UsePager {
-        #[clap(required = true, value_parser(["true", "false", "0", "1"]))]
+pub enum Config {
+    #[clap(about = "Show the value of a setting and where it comes from")]
+    Get {
+        #[clap(required = true)]
+        key: String,
+    },
+
+    #[clap(about = "Save a setting in the user config file")]
+    Set {
+        #[clap(required = true)]
+        key: String,
+        #[clap(required = true)]
         value: String,
     },
 
-    #[clap(
-        about = "Set the path where RimWorld is installed",
-        visible_alias = "path"
-    )]
-    GamePath {
-        /// The path where RimWorld is installed
+    #[clap(about = "Remove a setting from the user config file")]
+    Unset {
         #[clap(required = true)]
-        value: PathBuf,
+        key: String,
     },
 
     #[clap(
-        about = "Set the paging software to use, like bat, more or less",
-        visible_alias = "paging"
+        visible_alias = "l",
+        about = "List every setting, its value and where it comes from"
     )]
-    Pager {
-        #[cfg(target_os = "windows")]
-        /// The path where the paging software is, for example: C:\Windows\System32\more.com
-        #[clap(required = true)]
-        value: PathBuf,
+    List,
 
-        #[cfg(any(target_os = "macos", target_os = "linux"))]
-        /// The name of the paging software, for example: bat, more
-        #[clap(required = true)]
-        value: PathBuf,
-    },
+    #[clap(about = "Open the user config file with $VISUAL or $EDITOR")]
+    Edit,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Cache {
+    #[clap(about = "Show how many Workshop answers are cached and their size")]
+    Info,
+
+    #[clap(about = "Remove every cached Workshop answer")]
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Steamcmd {
+    #[clap(about = "Download steamcmd, unless it is already installed")]
+    Install,
+
+    #[clap(about = "Download steamcmd again and let it update itself")]
+    Update,
+
+    #[clap(about = "Print the path of the steamcmd in use")]
+    Path,
+
+    #[clap(about = "Remove the steamcmd downloaded by rrm and everything it downloaded")]
+    Reset,
+}
+
+fn parse_override(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, found \"{value}\""))
 }
 
 #[derive(Subcommand, Debug)]
@@ -66,14 +95,44 @@ pub enum Commands {
         args: InstallCommandGroup,
     },
 
-    #[clap(about = "Install everything again (except ignored by default)")]
+    #[clap(about = "Install everything again (except pinned mods by default)")]
     Pull {
         #[clap(flatten)]
-        args: Pull,
+        args: PullCommandGroup,
+        /// Update mods pinned with `rrm pin` too
         #[clap(short, long, visible_alias = "also-ignored")]
         ignored: bool,
     },
 
+    #[clap(about = "Keep a mod from being updated or installed as a dependency")]
+    Pin {
+        #[clap(flatten)]
+        args: PinCommandGroup,
+    },
+
+    #[clap(about = "Remove the pin of a mod")]
+    Unpin {
+        /// The Steam ID, Workshop URL, packageId or name of the mod
+        #[clap(required = true)]
+        r#mod: String,
+    },
+
+    #[clap(about = "List the pinned mods")]
+    Pins,
+
+    #[clap(about = "Find RimWorld installations in Steam libraries, GOG and $RIMWORLD_PATH")]
+    Detect {
+        /// Set the game path to the installation with this #
+        #[clap(long)]
+        set: Option<usize>,
+    },
+
+    #[clap(about = "Manage named RimWorld installations, like a stable one and a beta branch")]
+    Instance {
+        #[clap(subcommand)]
+        command: Instance,
+    },
+
     #[clap(
         visible_alias = "ss",
         hide = true,
@@ -82,7 +141,7 @@ pub enum Commands {
     )]
     SearchSteam {
         #[clap(flatten)]
-        args: Steam,
+        args: SteamCommandGroup,
     },
 
     #[clap(
@@ -94,13 +153,34 @@ pub enum Commands {
     )]
     SearchLocally {
         #[clap(flatten)]
-        args: Local,
+        args: LocalCommandGroup,
+    },
+
+    #[clap(about = "Read or change the configuration")]
+    Config {
+        #[clap(subcommand)]
+        command: Config,
     },
 
-    #[clap(about = "Set new configuration values")]
+    /// Kept so `rrm set path <GAME_PATH>` keeps working, same as `rrm config set`
+    #[clap(hide = true)]
     Set {
+        #[clap(required = true)]
+        key: String,
+        #[clap(required = true)]
+        value: String,
+    },
+
+    #[clap(about = "Manage the cache of Workshop answers used by --offline")]
+    Cache {
+        #[clap(subcommand)]
+        command: Cache,
+    },
+
+    #[clap(about = "Manage the steamcmd used to download mods")]
+    Steamcmd {
         #[clap(subcommand)]
-        command: Options,
+        command: Steamcmd,
     },
 
     #[clap(visible_alias = "s", about = "Search for mods locally or in Steam")]
@@ -109,6 +189,68 @@ pub enum Commands {
         command: Search,
     },
 
+    #[clap(about = "Restore the previous version of a mod from its backups")]
+    Rollback {
+        #[clap(flatten)]
+        args: RollbackCommandGroup,
+    },
+
+    #[clap(about = "List or prune the backups made when mods are replaced")]
+    Backups {
+        #[clap(subcommand)]
+        command: Backups,
+    },
+
+    #[clap(about = "Save or restore the whole mod setup: Mods/, ModsConfig.xml and mod settings")]
+    Snapshot {
+        #[clap(subcommand)]
+        command: Snapshot,
+    },
+
+    #[clap(about = "Look for broken, duplicated or misnamed mods")]
+    Doctor {
+        #[clap(flatten)]
+        args: DoctorCommandGroup,
+    },
+
+    #[clap(
+        about = "List, export, import, compare or reset the settings of mods (Config/Mod_*.xml)"
+    )]
+    Settings {
+        #[clap(subcommand)]
+        command: Settings,
+    },
+
+    #[clap(about = "Make the instance match a modpack.toml: its mods, pins and load order")]
+    Apply {
+        #[clap(flatten)]
+        args: ApplyCommandGroup,
+    },
+
+    #[clap(about = "Start RimWorld, after applying a modpack.toml, and show the errors it logs")]
+    Run {
+        #[clap(flatten)]
+        args: RunCommandGroup,
+    },
+
+    #[clap(about = "Show the errors of RimWorld's Player.log grouped by the mod that caused them")]
+    Log {
+        #[clap(flatten)]
+        args: LogCommandGroup,
+    },
+
+    #[clap(about = "Find mods installed more than once and choose which copy to keep")]
+    Duplicates {
+        #[clap(flatten)]
+        args: DuplicatesCommandGroup,
+    },
+
+    #[clap(about = "Show the Steam Workshop details of a mod by ID or URL")]
+    Info {
+        #[clap(flatten)]
+        args: InfoCommandGroup,
+    },
+
     #[clap(
         visible_alias = "l",
         about = LIST_DESCRIPTION
@@ -123,6 +265,27 @@ pub enum Commands {
     },
 }
 
+impl Commands {
+    /// Whether it changes `Mods/` or the mod setup, so the instance has to be locked
+    pub fn changes_mods(&self) -> bool {
+        match self {
+            Commands::Install { args } => !args.dry_run,
+            Commands::Pull { args, .. } => !args.dry_run,
+            Commands::Rollback { args } => !args.dry_run,
+            Commands::Snapshot {
+                command: Snapshot::Restore { dry_run, .. },
+            } => !dry_run,
+            Commands::Doctor { args } => args.fix,
+            Commands::Apply { args } => !args.dry_run,
+            Commands::Duplicates { args } => !args.dry_run,
+            Commands::Settings {
+                command: Settings::Import { dry_run, .. } | Settings::Reset { dry_run, .. },
+            } => !dry_run,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Args, Debug)]
 pub struct DisplayOptions {
     /// Display the larger message
@@ -146,7 +309,7 @@ pub enum Search {
     )]
     Steam {
         #[clap(flatten)]
-        args: Steam,
+        args: SteamCommandGroup,
     },
 
     #[clap(
@@ -156,13 +319,13 @@ pub enum Search {
     )]
     Local {
         #[clap(flatten)]
-        args: Local,
+        args: LocalCommandGroup,
     },
 }
 
 #[derive(Args, Debug)]
 #[clap(arg_required_else_help = true)]
-pub struct Steam {
+pub struct SteamCommandGroup {
     #[clap(flatten)]
     pub display: DisplayOptions,
 
@@ -191,13 +354,316 @@ pub struct Steam {
     pub(crate) name: bool,
 
     /// Search by all fields
-    #[clap(long, conflicts_with_all = &["authors", "version", "steam-id", "name"], requires="filter")]
+    #[clap(long, conflicts_with_all = &["author", "version", "steam_id", "name"], requires="filter")]
     pub(crate) all: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Backups {
+    #[clap(
+        visible_alias = "l",
+        about = "List the backups of every mod, newest first"
+    )]
+    List {
+        #[clap(flatten)]
+        display: DisplayOptions,
+    },
+
+    #[clap(about = "Remove old backups")]
+    Prune {
+        /// How many backups to keep for each mod
+        #[clap(long, default_value_t = 1)]
+        keep: usize,
+
+        /// Show what would be removed without changing anything
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Settings {
+    #[clap(
+        visible_alias = "l",
+        about = "List the settings files and the mod each one belongs to"
+    )]
+    List {
+        /// Only the settings of this mod: its Steam ID, packageId or name
+        r#mod: Option<String>,
+    },
+
+    #[clap(about = "Copy the settings files into a folder, like one shipped with a modpack")]
+    Export {
+        /// Only the settings of this mod: its Steam ID, packageId or name
+        r#mod: Option<String>,
+
+        /// The folder to copy them into
+        #[clap(long, short, default_value = "settings")]
+        to: PathBuf,
+    },
+
+    #[clap(about = "Copy settings files from a folder into the game, backing up the ones replaced")]
+    Import {
+        /// Only the settings of this mod: its Steam ID, packageId or name
+        r#mod: Option<String>,
+
+        /// The folder to copy them from
+        #[clap(long, short, default_value = "settings")]
+        from: PathBuf,
+
+        /// Show what would be copied without changing anything
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    #[clap(about = "Show how the settings in a folder differ from the ones of the game")]
+    Diff {
+        /// Only the settings of this mod: its Steam ID, packageId or name
+        r#mod: Option<String>,
+
+        /// The folder to compare with
+        #[clap(long, short, default_value = "settings")]
+        from: PathBuf,
+    },
+
+    #[clap(about = "Remove the settings of a mod so the game uses its defaults, backing them up")]
+    Reset {
+        /// The Steam ID, packageId or name of the mod
+        #[clap(required = true)]
+        r#mod: String,
+
+        /// Show what would be removed without changing anything
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Yes to all questions
+        #[clap(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Snapshot {
+    #[clap(about = "Save the current mods, ModsConfig.xml and mod settings")]
+    Create {
+        /// The name of the snapshot, defaults to the current time
+        name: Option<String>,
+    },
+
+    #[clap(visible_alias = "l", about = "List saved snapshots")]
+    List,
+
+    #[clap(about = "Make the mods, ModsConfig.xml and mod settings match a snapshot")]
+    Restore {
+        /// The name of the snapshot
+        #[clap(required = true)]
+        name: String,
+
+        /// Show what would be removed and written without changing anything
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Yes to all questions
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    #[clap(about = "Delete a snapshot and the files only it uses")]
+    Delete {
+        /// The name of the snapshot
+        #[clap(required = true)]
+        name: String,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct DoctorCommandGroup {
+    /// Fix the problems that can be fixed, reinstalling broken mods from the Workshop
+    #[clap(long)]
+    pub(crate) fix: bool,
+
+    /// Show what --fix would do about each problem
+    #[clap(long, visible_alias = "vvv")]
+    pub(crate) verbose: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Instance {
+    #[clap(about = "Add an instance for a RimWorld installation")]
+    Add {
+        /// The name of the instance, like `stable` or `beta`
+        #[clap(required = true)]
+        name: String,
+
+        /// The path where that RimWorld is installed
+        #[clap(required = true)]
+        game_path: PathBuf,
+
+        /// The folder RimWorld keeps Config/ and Saves/ in, if not the default one
+        #[clap(long, alias = "config-dir")]
+        user_data_dir: Option<PathBuf>,
+
+        /// Also make it the instance every command runs against
+        #[clap(long = "use")]
+        use_it: bool,
+    },
+
+    #[clap(visible_alias = "l", about = "List the instances")]
+    List,
+
+    #[clap(about = "Make commands run against an instance, `default` for the plain game path")]
+    Use {
+        #[clap(required = true)]
+        name: String,
+    },
+
+    #[clap(about = "Forget an instance, its backups and snapshots are kept")]
+    Remove {
+        #[clap(required = true)]
+        name: String,
+    },
+}
+
 #[derive(Args, Debug)]
 #[clap(arg_required_else_help = true)]
-pub struct Local {
+pub struct PinCommandGroup {
+    /// The Steam ID, Workshop URL, packageId or name of the mod
+    #[clap(required = true)]
+    pub(crate) r#mod: String,
+
+    /// `hold` never updates it, `hash` only accepts the content installed now and
+    /// `no-dependency` never installs it because another mod requires it
+    #[clap(long, value_parser(["hold", "hash", "no-dependency"]), default_value = "hold")]
+    pub(crate) mode: String,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum Keep {
+    /// The copy with the most recently modified About folder
+    Newest,
+    /// The copy with a valid PublishedFileId.txt
+    Workshop,
+    /// The copy without a Steam ID
+    Local,
+}
+
+#[derive(Args, Debug)]
+pub struct DuplicatesCommandGroup {
+    /// Keep this copy of every duplicated mod instead of asking
+    #[clap(long, value_enum)]
+    pub(crate) keep: Option<Keep>,
+
+    /// Show what would be done without changing anything
+    #[clap(long)]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ApplyCommandGroup {
+    /// The modpack manifest, defaults to the first modpack.toml in the current folder or above
+    #[clap(long, short)]
+    pub(crate) file: Option<PathBuf>,
+
+    /// Move the mods the modpack does not list to the backup store
+    #[clap(long)]
+    pub(crate) prune: bool,
+
+    /// Also install the dependencies of the Workshop mods
+    #[clap(long, short, visible_alias = "resolve-dependencies")]
+    pub(crate) resolve: bool,
+
+    /// Show what would be installed, removed and written without changing anything
+    #[clap(long)]
+    pub(crate) dry_run: bool,
+
+    /// Yes to all questions
+    #[clap(long, short)]
+    pub(crate) yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct LogCommandGroup {
+    /// Only show the errors of this mod, by Steam ID, packageId or name
+    pub(crate) r#mod: Option<String>,
+
+    /// The log to read instead of the game's Player.log
+    #[clap(long, short)]
+    pub(crate) file: Option<PathBuf>,
+
+    /// Read Player-prev.log, the log of the run before the last one
+    #[clap(long, conflicts_with = "file")]
+    pub(crate) previous: bool,
+
+    /// Also show the stack trace of each error
+    #[clap(long, short)]
+    pub(crate) verbose: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RunCommandGroup {
+    /// A modpack.toml to apply before starting, otherwise the active mods are kept
+    #[clap(long, short)]
+    pub(crate) profile: Option<PathBuf>,
+
+    /// Move the mods the profile does not list to the backup store
+    #[clap(long, requires = "profile")]
+    pub(crate) prune: bool,
+
+    /// Also install the dependencies of the Workshop mods of the profile
+    #[clap(long, short, requires = "profile")]
+    pub(crate) resolve: bool,
+
+    /// Keep the config and saves of this run in this folder, instead of the instance's
+    #[clap(long, visible_alias = "savedatafolder")]
+    pub(crate) save_data_folder: Option<PathBuf>,
+
+    /// Start straight into a small test map
+    #[clap(long)]
+    pub(crate) quicktest: bool,
+
+    /// Start the game and return without following Player.log
+    #[clap(long)]
+    pub(crate) detach: bool,
+
+    /// Yes to all questions
+    #[clap(long, short)]
+    pub(crate) yes: bool,
+
+    /// More options for RimWorld, after `--`
+    #[clap(last = true)]
+    pub(crate) extra: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct RollbackCommandGroup {
+    /// The folder name, Steam ID, packageId or name of the mod
+    #[clap(required = true)]
+    pub(crate) r#mod: String,
+
+    /// Restore the backup made at this time (as shown by `rrm backups list`) instead of the newest
+    #[clap(long)]
+    pub(crate) from: Option<u64>,
+
+    /// Show what would be restored without changing anything
+    #[clap(long)]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct InfoCommandGroup {
+    #[clap(flatten)]
+    pub display: DisplayOptions,
+
+    /// The Steam ID(s) or Workshop URL(s) of the mod(s)
+    #[clap(required = true)]
+    pub(crate) items: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct LocalCommandGroup {
     #[clap(flatten)]
     pub display: DisplayOptions,
 
@@ -222,17 +688,21 @@ pub struct Local {
     pub(crate) name: bool,
 
     /// Search by all fields
-    #[clap(long, conflicts_with_all = &["authors", "version", "steam-id", "name"])]
+    #[clap(long, conflicts_with_all = &["author", "version", "steam_id", "name"])]
     pub(crate) all: bool,
 }
 
 #[derive(Args, Debug, Clone)]
 #[clap(arg_required_else_help = true)]
-pub struct Pull {
+pub struct PullCommandGroup {
     /// Automatic dependencies installation
     #[clap(long, short, visible_alias = "resolve-dependencies")]
     pub(crate) resolve: bool,
 
+    /// Show what would be downloaded, removed and moved without changing anything
+    #[clap(long)]
+    pub(crate) dry_run: bool,
+
     /// Show more information about the process [alias: vvv]
     #[clap(long, visible_alias = "vvv")]
     pub(crate) verbose: bool,
@@ -281,6 +751,14 @@ pub struct InstallCommandGroup {
     #[clap(long, short, visible_alias = "resolve-dependencies")]
     pub(crate) resolve: bool,
 
+    /// Show what would be downloaded, removed and moved without changing anything
+    #[clap(long)]
+    pub(crate) dry_run: bool,
+
+    /// Update mods pinned with `rrm pin` too
+    #[clap(long)]
+    pub(crate) ignore_pins: bool,
+
     /// Show more information about the process [alias: vvv]
     #[clap(long, visible_alias = "vvv")]
     pub(crate) verbose: bool,
@@ -310,7 +788,7 @@ macro_rules! b_if {
     };
 }
 
-impl Local {
+impl LocalCommandGroup {
     pub fn to_filter_obj(&self) -> rrm_locals::FlagSet<rrm_locals::FilterBy> {
         let mut result: rrm_locals::FlagSet<rrm_locals::FilterBy> =
             rrm_locals::FlagSet::from(rrm_locals::FilterBy::None);
@@ -358,7 +836,7 @@ macro_rules! filter {
     }};
 }
 
-impl Steam {
+impl SteamCommandGroup {
     pub fn to_filter_obj(&self) -> rrm_scrap::FlagSet<rrm_scrap::FilterBy> {
         filter!(self)
     }
@@ -370,6 +848,27 @@ impl InstallCommandGroup {
     }
 }
 
+impl InstallCommandGroup {
+    /// Installs the given Steam IDs without asking questions
+    pub fn with_ids(ids: Vec<String>) -> Self {
+        InstallCommandGroup {
+            rimmod: ids,
+            filter: None,
+            author: false,
+            version: false,
+            steam_id: false,
+            name: false,
+            all: false,
+            yes: true,
+            resolve: false,
+            dry_run: false,
+            ignore_pins: false,
+            verbose: false,
+            debug: false,
+        }
+    }
+}
+
 impl App {
     pub fn load() -> App {
         App::parse()
@@ -395,5 +894,16 @@ macro_rules! impl_io {
     };
 }
 
-impl_io!(Pull);
+impl_io!(PullCommandGroup);
 impl_io!(InstallCommandGroup);
+
+#[cfg(test)]
+mod tests {
+    use super::App;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_app() {
+        App::command().debug_assert();
+    }
+}
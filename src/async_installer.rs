@@ -1,27 +1,51 @@
 use crate::args::InstallingOptions;
+use crate::progress::Progress;
 use crate::utils::*;
 use async_recursion::async_recursion;
-use notify::{RecommendedWatcher, Watcher};
-use rrm_installer::{Installer, get_or_create_config_dir};
+use rrm_installer::Installer;
+use rrm_installer::steamcmd::{self, Prompt};
 use std::process::Stdio;
+use std::time::Duration;
 use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    process::Command,
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::{ChildStdin, Command},
 };
 
+/// Types the answer to what steamcmd asks while logging in with `steam-username`
+async fn answer(
+    prompt: Prompt,
+    stdin: &mut ChildStdin,
+    installer: &Installer,
+) -> Result<(), String> {
+    let answer = match prompt {
+        Prompt::Password => crate::steamcmd::password(installer)?,
+        Prompt::EmailCode => crate::steamcmd::read_code("Steam Guard code sent by email"),
+        Prompt::TwoFactorCode => {
+            crate::steamcmd::read_code("Steam Guard mobile authenticator code")
+        }
+    };
+
+    stdin
+        .write_all(format!("{answer}\n").as_bytes())
+        .await
+        .map_err(|err| err.to_string())?;
+    stdin.flush().await.map_err(|err| err.to_string())
+}
+
 #[async_recursion(?Send)]
 pub async fn install<T>(
     args: T,
     mods: Vec<rrm_scrap::ModSteamInfo>,
     installer: Installer,
-    start_file_watcher: &mut RecommendedWatcher,
-    path_downloads: &PathBuf,
+    staging_dir: &Path,
+    progress: &mut Progress,
 ) -> String
 where
     T: InstallingOptions,
 {
     let install_message = Installer::gen_install_string(&mods);
     let steamcmd = installer.get_steamcmd_path();
+    let login = steamcmd::login_args(installer.steamcmd.steam_username.as_deref());
 
     args.is_verbose()
         .then(|| log!( Status: "Spawning SteamCMD"));
@@ -29,31 +53,36 @@ where
     #[cfg(target_os = "windows")]
     let mut cmd = {
         args.is_debug().then(|| {
-            log!(Status: "Spawning with command \"{} {}\"",
+            log!(Status: "Spawning with command \"{} +force_install_dir {} {} {} +quit\"",
                 steamcmd.as_path().to_str().unwrap(),
-                "+login anonymous {} +quit".replace("{}", &install_message)
+                staging_dir.join("Steam").display(),
+                login.join(" "),
+                &install_message
             )
         });
 
         let mut cmd = Command::new(steamcmd.as_path().to_str().unwrap());
-        cmd.args(
-            "+login anonymous {} +quit"
-                .replace("{}", &install_message)
-                .split(" "),
-        );
+        cmd.arg("+force_install_dir").arg(staging_dir.join("Steam"));
+        cmd.args(&login);
+        cmd.args(install_message.split(" "));
+        cmd.arg("+quit");
+        cmd.stdin(Stdio::piped());
         cmd.stdout(Stdio::piped());
         cmd
     };
 
     #[cfg(any(target_os = "linux", target_os = "macos"))]
     let mut cmd = {
-        let dwl_path = PathBuf::from(crate::install::TMP_PATH);
         let mut cmd = Command::new(steamcmd.display().to_string());
-        cmd.args(["+login anonymous", &install_message, "+quit"]);
-        cmd.env("HOME", dwl_path.display().to_string());
+        // Any steamcmd, also one installed by a package manager, then downloads into the staging dir
+        cmd.arg("+force_install_dir").arg(staging_dir.join("Steam"));
+        cmd.args(&login);
+        cmd.args([&install_message, "+quit"]);
+        cmd.env("HOME", staging_dir.display().to_string());
         if args.is_debug() {
             log!(Status: "Spawning with command \"{:?}\"", cmd);
         }
+        cmd.stdin(Stdio::piped());
         cmd.stdout(Stdio::piped());
         cmd
     };
@@ -64,51 +93,88 @@ where
         log!( Status: "Done spawning");
     }
 
-    let stdout = child.stdout.take().unwrap();
-    let mut reader = BufReader::new(stdout).lines();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
 
     let mut overall_result = String::new();
 
     let mut did_update = false;
 
-    while let Some(line) = reader.next_line().await.unwrap() {
-        if line.contains("Waiting for client config...OK") {
-            if !&get_or_create_config_dir()
-                .join(path_downloads.parent().unwrap())
-                .exists()
-            {
-                std::fs::create_dir_all(
-                    get_or_create_config_dir().join(path_downloads.parent().unwrap()),
-                )
-                .unwrap();
+    // Read by hand instead of by lines, as prompts are not followed by a new line
+    let mut pending: Vec<u8> = vec![];
+    let mut buf = [0; 4096];
+    let mut tick = tokio::time::interval(Duration::from_millis(250));
+    loop {
+        let n = tokio::select! {
+            read = stdout.read(&mut buf) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            },
+            _ = tick.tick() => {
+                progress.poll();
+                progress.draw();
+                continue;
             }
+        };
+        pending.extend_from_slice(&buf[..n]);
 
-            start_file_watcher
-                .watch(
-                    &get_or_create_config_dir().join(path_downloads.parent().unwrap()),
-                    notify::RecursiveMode::Recursive,
-                )
-                .unwrap();
-        }
-        if line.contains("Update complete") {
-            log!(Warning: "SteamCMD updated");
-            did_update = true;
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+
+            if let Some(reason) = steamcmd::login_failure(&line) {
+                progress.clear();
+                log!(Error: "Could not log in to Steam: {}", reason);
+                let _ = child.kill().await;
+                return overall_result + &line;
+            }
+
+            progress.line(&line);
+            if line.contains("Update complete") {
+                progress.clear();
+                log!(Warning: "SteamCMD updated");
+                did_update = true;
+            }
+
+            overall_result += &(line.clone() + "\n");
+            if args.is_debug() {
+                progress.clear();
+                log!(Received: "{line}");
+            } else if args.is_debug() && line.contains("Success") {
+                log!(Warning:
+                    "{})",
+                    &line[0..line.find(") ").unwrap_or(line.len())]
+                );
+            }
         }
 
-        overall_result += &(line.clone() + "\n");
-        if args.is_debug() {
-            log!(Received: "{line}");
-        } else if args.is_debug() && line.contains("Success") {
-            log!(Warning:
-                "{})",
-                &line[0..line.find(") ").unwrap_or(line.len())]
-            );
+        let partial = String::from_utf8_lossy(&pending).to_string();
+        if let Some(prompt) = steamcmd::prompt(&partial) {
+            overall_result += &(partial + "\n");
+            pending.clear();
+
+            progress.clear();
+            if let Err(err) = answer(prompt, &mut stdin, &installer).await {
+                log!(Error: "Could not log in to Steam: {}", err);
+                let _ = child.kill().await;
+                return overall_result;
+            }
         }
     }
 
+    overall_result += &String::from_utf8_lossy(&pending);
+
+    // Without its banner steamcmd did not even start, and retrying would not help
+    if steamcmd::parse_version(&overall_result).is_none()
+        && let Err(err) = crate::steamcmd::check(&installer)
+    {
+        log!(Error: "{}", err);
+        return overall_result;
+    }
+
     if did_update {
         log!(Warning: "Retrying installation because SteamCMD update canceled it");
-        install(args, mods, installer, start_file_watcher, path_downloads).await
+        install(args, mods, installer, staging_dir, progress).await
     } else {
         overall_result
     }
@@ -0,0 +1,466 @@
+use crate::args::{DisplayOptions, RollbackCommandGroup};
+use crate::utils::*;
+use fs_extra::dir;
+use fs_extra::dir::CopyOptions;
+use rrm_locals::ModVec;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A previous version of a mod, kept at `backups/<folder>/<created>/<folder>`
+#[derive(Debug, Clone)]
+pub struct Backup {
+    /// Name of the folder the mod had inside `Mods/`
+    pub folder: String,
+    pub created: u64,
+    pub path: PathBuf,
+    pub m: Option<Mod>,
+}
+
+impl Backup {
+    pub fn name(&self) -> &str {
+        self.m
+            .as_ref()
+            .map(|m| m.name.as_str())
+            .unwrap_or(&self.folder)
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        self.folder == query
+            || self.m.as_ref().is_some_and(|m| {
+                m.steam_id == query
+                    || m.name.eq_ignore_ascii_case(query)
+                    || m.package_id
+                        .as_ref()
+                        .is_some_and(|p| p.eq_ignore_ascii_case(query))
+            })
+    }
+}
+
+pub fn backups_dir() -> PathBuf {
+    rrm_installer::get_or_create_data_dir().join("backups")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Renames `from` to `to`, copying across filesystems when a rename is not possible
+pub fn move_folder(from: &Path, to: &Path) -> io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(to)?;
+    let options = CopyOptions {
+        overwrite: true,
+        content_only: true,
+        ..Default::default()
+    };
+    dir::copy(from, to, &options).map_err(io::Error::other)?;
+    dir::remove(from).map_err(io::Error::other)
+}
+
+/// Moves a mod folder out of `Mods/` into the backup store, under the name it had in `Mods/`
+pub fn store(path: &Path, folder: &str) -> io::Result<PathBuf> {
+    store_in(&backups_dir(), path, folder)
+}
+
+pub(crate) fn store_in(backups: &Path, path: &Path, folder: &str) -> io::Result<PathBuf> {
+    let mut created = now();
+    while backups.join(folder).join(created.to_string()).exists() {
+        created += 1;
+    }
+
+    let destination = backups.join(folder).join(created.to_string());
+    std::fs::create_dir_all(&destination)?;
+    move_folder(path, &destination.join(folder))?;
+
+    Ok(destination.join(folder))
+}
+
+/// Swaps `staged` into `destination`, moving every folder in `old` out of the way first.
+/// If the swap fails the old folders are put back, otherwise they end up in the backup store
+pub fn replace(staged: &Path, destination: &Path, old: &[PathBuf]) -> io::Result<()> {
+    replace_in(&backups_dir(), staged, destination, old)
+}
+
+fn replace_in(
+    backups: &Path,
+    staged: &Path,
+    destination: &Path,
+    old: &[PathBuf],
+) -> io::Result<()> {
+    // Between the renames the mod is in neither place, so Ctrl-C waits for the swap
+    crate::interrupt::uninterruptible(|| {
+        let mods_dir = destination.parent().unwrap();
+
+        let mut moved_away = vec![];
+        for path in old {
+            let aside = mods_dir.join(format!(".rrm-old-{}", folder_name(path)));
+            if let Err(err) = std::fs::rename(path, &aside) {
+                restore_aside(&moved_away);
+                return Err(err);
+            }
+            moved_away.push((path.clone(), aside));
+        }
+
+        if let Err(err) = std::fs::rename(staged, destination) {
+            restore_aside(&moved_away);
+            return Err(err);
+        }
+
+        for (path, aside) in moved_away {
+            match store_in(backups, &aside, &folder_name(&path)) {
+                Ok(backup) => log!(Backup: "Previous version saved at \"{}\"", backup.display()),
+                Err(err) => {
+                    log!(Warning: "Could not back up \"{}\", it is at \"{}\": {}", path.display(), aside.display(), err)
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+fn restore_aside(moved_away: &[(PathBuf, PathBuf)]) {
+    for (path, aside) in moved_away {
+        if let Err(err) = std::fs::rename(aside, path) {
+            log!(Error: "Could not restore \"{}\", it is at \"{}\": {}", path.display(), aside.display(), err);
+        }
+    }
+}
+
+pub fn list_backups() -> Vec<Backup> {
+    list_backups_in(&backups_dir())
+}
+
+fn list_backups_in(backups: &Path) -> Vec<Backup> {
+    let mut result = vec![];
+
+    let Ok(folders) = backups.read_dir() else {
+        return result;
+    };
+
+    for folder in folders.flatten().filter(|f| f.path().is_dir()) {
+        let Ok(versions) = folder.path().read_dir() else {
+            continue;
+        };
+
+        for version in versions.flatten().filter(|v| v.path().is_dir()) {
+            let Ok(created) = folder_name(&version.path()).parse() else {
+                continue;
+            };
+
+            let path = version.path().join(folder.file_name());
+            if !path.is_dir() {
+                continue;
+            }
+
+            let (mods, _) = rrm_locals::mods_at(&version.path()).parse();
+            result.push(Backup {
+                folder: folder_name(&folder.path()),
+                created,
+                path,
+                m: mods.into_iter().next(),
+            });
+        }
+    }
+
+    result.sort_by(|a, b| a.folder.cmp(&b.folder).then(b.created.cmp(&a.created)));
+    result
+}
+
+pub fn list(d: DisplayOptions) {
+    let backups = list_backups();
+
+    if backups.is_empty() {
+        println!("No backups found");
+        return;
+    }
+
+    for backup in backups {
+        let version = backup
+            .m
+            .as_ref()
+            .and_then(|m| m.version.clone())
+            .unwrap_or_default();
+
+        if d.large {
+            println!(
+                "{}\n  created : {}\n  version : {}\n  path    : {}\n",
+                backup.name(),
+                rrm_scrap::format_timestamp(backup.created),
+                version,
+                backup.path.display()
+            );
+        } else {
+            println!(
+                "{:>12}   {:<20}   {:<10} {}",
+                backup.created,
+                rrm_scrap::format_timestamp(backup.created),
+                version,
+                backup.name()
+            );
+        }
+    }
+}
+
+pub fn prune(keep: usize, dry_run: bool) {
+    prune_in(&backups_dir(), keep, dry_run);
+}
+
+fn prune_in(backups_dir: &Path, keep: usize, dry_run: bool) {
+    let backups = list_backups_in(backups_dir);
+    let mut kept: Vec<&str> = vec![];
+
+    for backup in &backups {
+        if kept.iter().filter(|f| **f == backup.folder).count() < keep {
+            kept.push(&backup.folder);
+            continue;
+        }
+
+        let version_dir = backup.path.parent().unwrap();
+        if dry_run {
+            log!(Remove: "\"{}\"", version_dir.display());
+        } else if let Err(err) = dir::remove(version_dir) {
+            log!(Error: "Could not remove \"{}\": {}", version_dir.display(), err);
+        } else {
+            log!(Removed: "{} from {}", backup.name(), rrm_scrap::format_timestamp(backup.created));
+        }
+    }
+
+    for backup in &backups {
+        if let Some(folder) = backup.path.parent().and_then(Path::parent)
+            && folder.read_dir().is_ok_and(|mut d| d.next().is_none())
+        {
+            let _ = std::fs::remove_dir(folder);
+        }
+    }
+}
+
+/// Swaps `backup` into `mods_dir`, backing up the `current` folders it replaces
+fn restore(backups: &Path, backup: &Backup, mods_dir: &Path, current: &[PathBuf]) -> bool {
+    let destination = mods_dir.join(&backup.folder);
+
+    // The backup is only in the staging folder until the swap is done
+    crate::interrupt::uninterruptible(|| {
+        let staged = mods_dir.join(format!(".rrm-staging-{}", backup.folder));
+        if let Err(err) = move_folder(&backup.path, &staged) {
+            log!(Error: "Could not stage backup \"{}\": {}", backup.path.display(), err);
+            return false;
+        }
+
+        if let Err(err) = replace_in(backups, &staged, &destination, current) {
+            log!(Error: "Could not restore {}: {}", backup.name(), err);
+            if let Err(err) = move_folder(&staged, &backup.path) {
+                log!(Error: "The backup was left at \"{}\": {}", staged.display(), err);
+            }
+            return false;
+        }
+        true
+    })
+}
+
+pub fn rollback(i: Installer, args: RollbackCommandGroup) {
+    let rim_install = i.rim_install.as_ref().unwrap();
+    let mods_dir = rim_install.path().join("Mods");
+
+    let backup = list_backups()
+        .into_iter()
+        .filter(|b| b.matches(&args.r#mod))
+        .find(|b| args.from.is_none_or(|from| b.created == from));
+
+    let Some(backup) = backup else {
+        log!(Error: "No backup found for \"{}\"", args.r#mod);
+        return;
+    };
+
+    // The version installed now gets backed up too, so the rollback can be undone
+    let installed = GameMods::from(rim_install.clone());
+    let current: Vec<PathBuf> = installed
+        .iter()
+        .filter(|m| {
+            folder_name(Path::new(&m.path)) == backup.folder
+                || backup
+                    .m
+                    .as_ref()
+                    .is_some_and(|b| b.steam_id != "NOT FOUND" && b.steam_id == m.steam_id)
+        })
+        .map(|m| PathBuf::from(&m.path))
+        .collect();
+
+    let destination = mods_dir.join(&backup.folder);
+
+    if args.dry_run {
+        log!(Warning: "Dry run, nothing will be changed");
+        for path in &current {
+            log!(Backup: "\"{}\"", path.display());
+        }
+        log!(Restore: "{} from {} to \"{}\"", backup.name(), rrm_scrap::format_timestamp(backup.created), destination.display());
+        return;
+    }
+
+    if !restore(&backups_dir(), &backup, &mods_dir, &current) {
+        return;
+    }
+
+    let _ = std::fs::remove_dir(backup.path.parent().unwrap());
+    log!(Restored: "{} from {}", backup.name(), rrm_scrap::format_timestamp(backup.created));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_backups_in, prune_in, replace_in, restore, store_in};
+    use std::path::{Path, PathBuf};
+
+    fn temp(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rrm_backup_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Mods")).unwrap();
+        dir
+    }
+
+    fn write_mod(path: &Path, version: &str) {
+        std::fs::create_dir_all(path.join("About")).unwrap();
+        std::fs::write(
+            path.join("About").join("About.xml"),
+            "<ModMetaData><name>Foo</name><packageId>someone.foo</packageId></ModMetaData>",
+        )
+        .unwrap();
+        std::fs::write(path.join("version.txt"), version).unwrap();
+    }
+
+    fn version(path: &Path) -> String {
+        std::fs::read_to_string(path.join("version.txt")).unwrap()
+    }
+
+    fn leftovers(mods: &Path) -> Vec<String> {
+        std::fs::read_dir(mods)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with(".rrm-"))
+            .collect()
+    }
+
+    #[test]
+    fn test_store() {
+        let dir = temp("store");
+        let backups = dir.join("backups");
+        write_mod(&dir.join("Mods").join("Foo"), "1");
+
+        let stored = store_in(&backups, &dir.join("Mods").join("Foo"), "Foo").unwrap();
+        assert!(!dir.join("Mods").join("Foo").exists());
+        assert_eq!(version(&stored), "1");
+
+        let listed = list_backups_in(&backups);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].folder, "Foo");
+        assert_eq!(listed[0].name(), "Foo");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace() {
+        let dir = temp("replace");
+        let (mods, backups) = (dir.join("Mods"), dir.join("backups"));
+        write_mod(&mods.join("Foo"), "1");
+        write_mod(&mods.join(".rrm-staging-Foo"), "2");
+
+        replace_in(
+            &backups,
+            &mods.join(".rrm-staging-Foo"),
+            &mods.join("Foo"),
+            &[mods.join("Foo")],
+        )
+        .unwrap();
+
+        assert_eq!(version(&mods.join("Foo")), "2");
+        assert!(leftovers(&mods).is_empty());
+        let listed = list_backups_in(&backups);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(version(&listed[0].path), "1");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace_failed_swap() {
+        let dir = temp("replace_failed");
+        let (mods, backups) = (dir.join("Mods"), dir.join("backups"));
+        write_mod(&mods.join("Foo"), "1");
+        write_mod(&mods.join(".rrm-staging-Foo"), "2");
+        // A folder with content can not be renamed over, so the second rename fails
+        write_mod(&mods.join("Taken"), "3");
+
+        let result = replace_in(
+            &backups,
+            &mods.join(".rrm-staging-Foo"),
+            &mods.join("Taken"),
+            &[mods.join("Foo")],
+        );
+
+        assert!(result.is_err());
+        assert_eq!(version(&mods.join("Foo")), "1");
+        assert_eq!(version(&mods.join("Taken")), "3");
+        assert_eq!(leftovers(&mods), [".rrm-staging-Foo"]);
+        assert!(list_backups_in(&backups).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore() {
+        let dir = temp("restore");
+        let (mods, backups) = (dir.join("Mods"), dir.join("backups"));
+        write_mod(&mods.join("Foo"), "1");
+        store_in(&backups, &mods.join("Foo"), "Foo").unwrap();
+        write_mod(&mods.join("Foo"), "2");
+
+        let backup = list_backups_in(&backups).remove(0);
+        assert!(restore(&backups, &backup, &mods, &[mods.join("Foo")]));
+
+        assert_eq!(version(&mods.join("Foo")), "1");
+        assert!(leftovers(&mods).is_empty());
+        let listed = list_backups_in(&backups);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(version(&listed[0].path), "2");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = temp("prune");
+        let (mods, backups) = (dir.join("Mods"), dir.join("backups"));
+        for v in ["1", "2", "3"] {
+            write_mod(&mods.join("Foo"), v);
+            store_in(&backups, &mods.join("Foo"), "Foo").unwrap();
+        }
+        assert_eq!(list_backups_in(&backups).len(), 3);
+
+        prune_in(&backups, 1, true);
+        assert_eq!(list_backups_in(&backups).len(), 3);
+
+        prune_in(&backups, 1, false);
+        let listed = list_backups_in(&backups);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(version(&listed[0].path), "3");
+
+        prune_in(&backups, 0, false);
+        assert!(list_backups_in(&backups).is_empty());
+        assert!(!backups.join("Foo").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
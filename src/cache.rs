@@ -0,0 +1,30 @@
+use crate::utils::*;
+use rrm_scrap::Cache;
+use std::process::ExitCode;
+
+pub fn info(i: Installer) {
+    let dir = rrm_installer::cache_dir();
+    let (count, size) = Cache::usage(&dir.join("http"));
+
+    println!("Path     : {}", dir.display());
+    println!("Answers  : {count}");
+    println!("Size     : {}", rrm_scrap::human_size(size));
+    println!("TTL      : {}", i.cache_ttl);
+    println!("Offline  : {}", i.offline);
+}
+
+pub fn clear() -> Result<(), ExitCode> {
+    let dir = rrm_installer::cache_dir().join("http");
+    let (count, size) = Cache::usage(&dir);
+
+    match Cache::clear(&dir) {
+        Ok(()) => {
+            log!(Removed: "{} cached answers, freed {}", count, rrm_scrap::human_size(size));
+            Ok(())
+        }
+        Err(err) => {
+            log!(Error: "Could not remove \"{}\": {}", dir.display(), err);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::utils::*;
+use rrm_installer::config::{self, KEYS, Source};
+use std::process::ExitCode;
+
+fn display(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn warn_overridden(key: &str, source: Option<Source>) {
+    if let Some(source) = source {
+        log!(Warning: "{} is still overridden by the {} config, see `rrm config list`", key, source);
+    }
+}
+
+pub fn get(i: Installer, key: String) -> Result<(), ExitCode> {
+    if config::key(&key).is_none() {
+        log!(Error: "{}", config::unknown_key(&key));
+        return Err(ExitCode::FAILURE);
+    }
+
+    match i.layers.origin(&key) {
+        Some(layer) => {
+            println!("{}", display(&layer.table[&key]));
+            Ok(())
+        }
+        None => Err(ExitCode::FAILURE),
+    }
+}
+
+pub fn set(mut i: Installer, key: String, value: String) -> Result<(), ExitCode> {
+    match i.set_config_value(&key, &value) {
+        Ok(overridden) => {
+            if config::key(&key).is_some_and(|k| k.is_secret()) {
+                log!(Set: "{}", key);
+            } else {
+                log!(Set: "{} = {}", key, value);
+            }
+            warn_overridden(&key, overridden);
+            Ok(())
+        }
+        Err(err) => {
+            log!(Error: "{}", err);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+pub fn unset(mut i: Installer, key: String) -> Result<(), ExitCode> {
+    match i.unset_config_value(&key) {
+        Ok(overridden) => {
+            log!(Unset: "{}", key);
+            warn_overridden(&key, overridden);
+            Ok(())
+        }
+        Err(err) => {
+            log!(Error: "{}", err);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+pub fn list(i: Installer) {
+    for key in KEYS {
+        let (value, origin) = i
+            .layers
+            .origin(key.name)
+            .map(|l| {
+                let value = if key.is_secret() {
+                    "********".to_string()
+                } else {
+                    display(&l.table[key.name])
+                };
+                (value, l.describe(key.name))
+            })
+            .unwrap_or_else(|| ("Not set".to_string(), "".to_string()));
+
+        println!("{:<22} {:<40} {}", key.name, value, origin);
+    }
+
+    println!();
+    for layer in i.layers.0.iter().filter(|l| l.path.is_some()) {
+        println!(
+            "{:<14} {}",
+            layer.source.to_string(),
+            layer.path.as_ref().unwrap().display()
+        );
+    }
+}
+
+pub fn edit(i: Installer) -> Result<(), ExitCode> {
+    let path = config::user_config_path();
+    if !path.exists() {
+        i.write_config();
+    }
+
+    #[cfg(target_os = "windows")]
+    let fallback = "notepad";
+    #[cfg(not(target_os = "windows"))]
+    let fallback = "vi";
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| fallback.to_string());
+
+    // The editor may come with arguments, like `code --wait`
+    let mut words = editor.split_whitespace();
+    let status = std::process::Command::new(words.next().unwrap_or(fallback))
+        .args(words)
+        .arg(&path)
+        .status();
+
+    if let Err(err) = status {
+        log!(Error: "Could not run \"{}\": {}", editor, err);
+        return Err(ExitCode::FAILURE);
+    }
+
+    if let Err(err) = config::Layers::load(&[]) {
+        log!(Error: "{}", err);
+        log!(Error: "Run `rrm config edit` again to fix it");
+        return Err(ExitCode::FAILURE);
+    }
+
+    Ok(())
+}
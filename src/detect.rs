@@ -0,0 +1,454 @@
+use crate::utils::*;
+use std::collections::HashSet;
+
+/// RimWorld's Steam app ID
+const RIMWORLD_APP_ID: &str = "294100";
+
+/// A node of Valve's KeyValues format, used by `libraryfolders.vdf` and `appmanifest_*.acf`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Vdf {
+    Value(String),
+    Map(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    /// Looks up `key` ignoring case, as Steam itself is not consistent about it
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Value(_) => None,
+        }
+    }
+
+    pub fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Vdf::Value(value) => Some(value),
+            Vdf::Map(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Map(entries) => entries,
+            Vdf::Value(_) => &[],
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => tokens.push(c.to_string()),
+            '"' => {
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => token.push('\n'),
+                            Some('t') => token.push('\t'),
+                            Some(escaped) => token.push(escaped),
+                            None => return Err("Unterminated escape".to_string()),
+                        },
+                        Some(c) => token.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                // Keep quoted braces apart from the real ones
+                tokens.push(format!("\"{token}"));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => return Err(format!("Unexpected character '{c}'")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_map(tokens: &[String], pos: &mut usize, nested: bool) -> Result<Vdf, String> {
+    let mut entries = vec![];
+
+    while *pos < tokens.len() {
+        let token = &tokens[*pos];
+        *pos += 1;
+
+        if token == "}" {
+            return if nested {
+                Ok(Vdf::Map(entries))
+            } else {
+                Err("Unexpected '}'".to_string())
+            };
+        }
+
+        let Some(key) = token.strip_prefix('"') else {
+            return Err(format!("Expected a key, found '{token}'"));
+        };
+
+        let Some(value) = tokens.get(*pos) else {
+            return Err(format!("Missing the value of \"{key}\""));
+        };
+        *pos += 1;
+
+        let value = if value == "{" {
+            parse_map(tokens, pos, true)?
+        } else if let Some(value) = value.strip_prefix('"') {
+            Vdf::Value(value.to_string())
+        } else {
+            return Err(format!("Expected the value of \"{key}\", found '{value}'"));
+        };
+
+        entries.push((key.to_string(), value));
+    }
+
+    if nested {
+        Err("Missing '}'".to_string())
+    } else {
+        Ok(Vdf::Map(entries))
+    }
+}
+
+pub fn parse_vdf(input: &str) -> Result<Vdf, String> {
+    let tokens = tokenize(input)?;
+    parse_map(&tokens, &mut 0, false)
+}
+
+/// Every library listed in a `libraryfolders.vdf`, in either the current or the legacy format
+pub fn library_folders(vdf: &Vdf) -> Vec<PathBuf> {
+    let Some(root) = vdf.get("libraryfolders") else {
+        return vec![];
+    };
+
+    root.entries()
+        .iter()
+        .filter(|(key, _)| key.chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|(_, library)| match library {
+            Vdf::Value(path) => Some(PathBuf::from(path)),
+            Vdf::Map(_) => library.str("path").map(PathBuf::from),
+        })
+        .collect()
+}
+
+/// What an `appmanifest_294100.acf` says about the installed game
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppManifest {
+    pub install_dir: String,
+    pub build_id: Option<String>,
+    /// The beta branch, if any
+    pub beta: Option<String>,
+}
+
+pub fn parse_app_manifest(vdf: &Vdf) -> Option<AppManifest> {
+    let state = vdf.get("AppState")?;
+
+    let beta = state
+        .get("MountedConfig")
+        .or_else(|| state.get("UserConfig"))
+        .and_then(|c| c.str("BetaKey"))
+        .filter(|b| !b.is_empty() && *b != "public")
+        .map(str::to_string);
+
+    Some(AppManifest {
+        install_dir: state.str("installdir")?.to_string(),
+        build_id: state.str("buildid").map(str::to_string),
+        beta,
+    })
+}
+
+/// A RimWorld installation found on this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detected {
+    pub path: PathBuf,
+    /// Where it was found, like `Steam` or `GOG`
+    pub source: String,
+    pub beta: Option<String>,
+}
+
+impl Detected {
+    /// The game version, as written in `Version.txt`
+    pub fn version(&self) -> Option<String> {
+        game_version(&self.path)
+    }
+}
+
+fn read_vdf(path: &Path) -> Option<Vdf> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match parse_vdf(&content) {
+        Ok(vdf) => Some(vdf),
+        Err(err) => {
+            log!(Warning: "Could not read \"{}\": {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// The folder rrm works with inside a Steam `common/<install_dir>`, which is an app bundle on macOS
+fn game_folder(path: PathBuf) -> PathBuf {
+    if cfg!(target_os = "macos") && path.join("RimWorldMac.app").is_dir() {
+        path.join("RimWorldMac.app")
+    } else {
+        path
+    }
+}
+
+/// RimWorld installs in every library known to the Steam installation at `steam_root`
+pub fn detect_in_steam(steam_root: &Path, source: &str) -> Vec<Detected> {
+    let mut libraries = vec![steam_root.to_path_buf()];
+    for vdf in [
+        steam_root.join("steamapps/libraryfolders.vdf"),
+        steam_root.join("config/libraryfolders.vdf"),
+    ] {
+        if let Some(vdf) = read_vdf(&vdf) {
+            libraries.extend(library_folders(&vdf));
+        }
+    }
+    libraries.dedup();
+
+    let mut result = vec![];
+    for library in libraries {
+        let steamapps = library.join("steamapps");
+        let manifest = read_vdf(&steamapps.join(format!("appmanifest_{RIMWORLD_APP_ID}.acf")))
+            .and_then(|vdf| parse_app_manifest(&vdf));
+
+        let install_dir = manifest
+            .as_ref()
+            .map(|m| m.install_dir.as_str())
+            .unwrap_or("RimWorld");
+        let path = steamapps.join("common").join(install_dir);
+
+        if dir_exists(&path) {
+            result.push(Detected {
+                path: game_folder(path),
+                source: source.to_string(),
+                beta: manifest.and_then(|m| m.beta),
+            });
+        }
+    }
+
+    result
+}
+
+fn steam_roots(home: &Path) -> Vec<(PathBuf, &'static str)> {
+    #[cfg(target_os = "linux")]
+    return vec![
+        (home.join(".local/share/Steam"), "Steam"),
+        (home.join(".steam/steam"), "Steam"),
+        (home.join(".steam/root"), "Steam"),
+        (
+            home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+            "Steam (Flatpak)",
+        ),
+        (
+            home.join("snap/steam/common/.local/share/Steam"),
+            "Steam (Snap)",
+        ),
+    ];
+
+    #[cfg(target_os = "macos")]
+    return vec![(home.join("Library/Application Support/Steam"), "Steam")];
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = home;
+        vec![
+            (PathBuf::from(r"C:\Program Files (x86)\Steam"), "Steam"),
+            (PathBuf::from(r"C:\Program Files\Steam"), "Steam"),
+        ]
+    }
+}
+
+fn gog_paths(home: &Path) -> Vec<PathBuf> {
+    #[cfg(target_os = "linux")]
+    return vec![
+        home.join("GOG Games/RimWorld"),
+        home.join("Games/gog/rimworld/drive_c/GOG Games/RimWorld"),
+    ];
+
+    #[cfg(target_os = "macos")]
+    return vec![
+        PathBuf::from("/Applications/RimWorld.app"),
+        home.join("Applications/RimWorld.app"),
+    ];
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = home;
+        vec![
+            PathBuf::from(r"C:\GOG Games\RimWorld"),
+            PathBuf::from(r"C:\Program Files (x86)\GOG Galaxy\Games\RimWorld"),
+        ]
+    }
+}
+
+/// Every RimWorld installation found, `$RIMWORLD_PATH` first
+pub fn detect_installs() -> Vec<Detected> {
+    let mut result = vec![];
+
+    if let Ok(path) = std::env::var("RIMWORLD_PATH") {
+        let path = PathBuf::from(path);
+        if dir_exists(&path) {
+            result.push(Detected {
+                path,
+                source: "$RIMWORLD_PATH".to_string(),
+                beta: None,
+            });
+        } else {
+            log!(Warning: "$RIMWORLD_PATH is set to \"{}\", which does not exist", path.display());
+        }
+    }
+
+    if let Some(dirs) = directories::UserDirs::new() {
+        let home = dirs.home_dir();
+
+        for (root, source) in steam_roots(home) {
+            if dir_exists(&root) {
+                result.extend(detect_in_steam(&root, source));
+            }
+        }
+
+        for path in gog_paths(home) {
+            if dir_exists(&path) {
+                result.push(Detected {
+                    path,
+                    source: "GOG".to_string(),
+                    beta: None,
+                });
+            }
+        }
+    }
+
+    // `~/.steam/steam` is usually a link to `~/.local/share/Steam`
+    let mut seen = HashSet::new();
+    result.retain(|d| seen.insert(d.path.canonicalize().unwrap_or_else(|_| d.path.clone())));
+    result
+}
+
+pub fn detect(mut i: Installer, set: Option<usize>) {
+    let found = detect_installs();
+
+    if found.is_empty() {
+        println!(
+            "No RimWorld installation found, set one with `rrm config set game-path <GAME_PATH>`"
+        );
+        return;
+    }
+
+    let current = i.rim_install.as_ref().map(|p| p.path().to_path_buf());
+    for (n, d) in found.iter().enumerate() {
+        println!(
+            "{} {:<3} {:<16} {:<22} {}{}",
+            if current.as_ref() == Some(&d.path) {
+                "*"
+            } else {
+                " "
+            },
+            n,
+            d.source,
+            d.version().unwrap_or_else(|| "Unknown version".to_string()),
+            d.path.display(),
+            d.beta
+                .as_ref()
+                .map(|b| format!(" (beta: {b})"))
+                .unwrap_or_default()
+        );
+    }
+
+    match set.map(|n| found.get(n)) {
+        Some(Some(d)) => {
+            i.set_path_value(d.path.clone());
+            log!(Status: "Game path set to \"{}\"", d.path.display());
+        }
+        Some(None) => log!(Error: "There is no installation #{}", set.unwrap()),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_library_folders() {
+        let vdf = parse_vdf(include_str!("../fixtures/steam/libraryfolders.vdf")).unwrap();
+        assert_eq!(
+            library_folders(&vdf),
+            vec![
+                PathBuf::from("/home/user/.local/share/Steam"),
+                PathBuf::from("/mnt/games/SteamLibrary"),
+                PathBuf::from(r"D:\Steam Library"),
+            ]
+        );
+
+        let legacy =
+            parse_vdf(include_str!("../fixtures/steam/libraryfolders_legacy.vdf")).unwrap();
+        assert_eq!(
+            library_folders(&legacy),
+            vec![
+                PathBuf::from("/mnt/games/SteamLibrary"),
+                PathBuf::from("/media/old/Steam"),
+            ]
+        );
+
+        assert!(parse_vdf("\"libraryfolders\" { \"0\" {").is_err());
+    }
+
+    #[test]
+    fn test_app_manifest() {
+        let vdf = parse_vdf(include_str!("../fixtures/steam/appmanifest_294100.acf")).unwrap();
+        assert_eq!(
+            parse_app_manifest(&vdf),
+            Some(AppManifest {
+                install_dir: "RimWorld".to_string(),
+                build_id: Some("15790124".to_string()),
+                beta: Some("unstable".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_detect_in_steam() {
+        let root = std::env::temp_dir().join(format!("rrm_detect_{}", std::process::id()));
+        let steam = root.join("Steam");
+        let library = root.join("Library");
+
+        fs::create_dir_all(steam.join("steamapps")).unwrap();
+        fs::create_dir_all(library.join("steamapps/common/RimWorld")).unwrap();
+        fs::write(
+            steam.join("steamapps/libraryfolders.vdf"),
+            format!(
+                "\"libraryfolders\" {{ \"0\" {{ \"path\" \"{}\" }} \"1\" {{ \"path\" \"{}\" }} }}",
+                steam.display(),
+                library.display()
+            ),
+        )
+        .unwrap();
+        fs::copy(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/fixtures/steam/appmanifest_294100.acf"
+            ),
+            library.join("steamapps/appmanifest_294100.acf"),
+        )
+        .unwrap();
+
+        let found = detect_in_steam(&steam, "Steam");
+        fs::remove_dir_all(root).unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].beta.as_deref(), Some("unstable"));
+        assert!(found[0].path.ends_with("Library/steamapps/common/RimWorld"));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::args::{DoctorCommandGroup, InstallCommandGroup};
+use crate::utils::*;
+use rrm_installer::steamcmd::{Origin, SteamCmdError};
+use rrm_xml::XMLFile;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::process::ExitCode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    EmptyFolder,
+    MissingAbout,
+    /// `About.xml` exists but under another case, like `about/about.xml`
+    AboutCase(PathBuf),
+    UnreadableXml(PathBuf, String),
+    MissingSteamId,
+    InvalidSteamId(String),
+    DuplicatePackageId(String, Vec<PathBuf>),
+    DuplicateSteamId(String, Vec<PathBuf>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// The mod folder inside `Mods/`
+    pub path: PathBuf,
+    pub kind: IssueKind,
+}
+
+impl Issue {
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            IssueKind::EmptyFolder => "Empty folder".to_string(),
+            IssueKind::MissingAbout => "Missing About/About.xml".to_string(),
+            IssueKind::AboutCase(found) => {
+                format!("About.xml is named \"{}\"", found.display())
+            }
+            IssueKind::UnreadableXml(file, err) => {
+                format!("Unreadable \"{}\": {}", file.display(), err)
+            }
+            IssueKind::MissingSteamId => "Missing About/PublishedFileId.txt".to_string(),
+            IssueKind::InvalidSteamId(content) => {
+                format!("PublishedFileId.txt does not hold a Steam ID: \"{content}\"")
+            }
+            IssueKind::DuplicatePackageId(id, others) => {
+                format!("packageId {id} is also used by {}", display_paths(others))
+            }
+            IssueKind::DuplicateSteamId(id, others) => {
+                format!("Steam ID {id} is also used by {}", display_paths(others))
+            }
+        }
+    }
+
+    /// What `--fix` does about the issue, if anything
+    pub fn fix_description(&self) -> Option<String> {
+        match &self.kind {
+            IssueKind::EmptyFolder => Some("remove the folder".to_string()),
+            IssueKind::AboutCase(_) => Some("rename it to About/About.xml".to_string()),
+            IssueKind::MissingAbout | IssueKind::UnreadableXml(..) => self
+                .steam_id()
+                .map(|id| format!("reinstall {id} from the Workshop")),
+            IssueKind::MissingSteamId | IssueKind::InvalidSteamId(_) => self
+                .folder_steam_id()
+                .map(|id| format!("write {id} to PublishedFileId.txt")),
+            IssueKind::DuplicatePackageId(..) | IssueKind::DuplicateSteamId(..) => None,
+        }
+    }
+
+    /// The Steam ID in `PublishedFileId.txt`, if valid
+    fn steam_id(&self) -> Option<usize> {
+        read_steam_id(&self.path).ok()
+    }
+
+    /// rrm installs Workshop mods into `Mods/<Steam ID>`
+    fn folder_steam_id(&self) -> Option<usize> {
+        self.path.file_name()?.to_str()?.parse().ok()
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| format!("\"{}\"", p.display()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Reads `About/PublishedFileId.txt`, `Err` holds the invalid contents or `None` if missing
+fn read_steam_id(path: &Path) -> Result<usize, Option<String>> {
+    let about = find_about_dir(path).ok_or(None)?;
+    let contents = std::fs::read_to_string(about.join("PublishedFileId.txt")).map_err(|_| None)?;
+    let contents = contents.trim();
+
+    contents.parse().map_err(|_| Some(contents.to_string()))
+}
+
+/// The `About` folder of a mod, whatever its case
+fn find_about_dir(path: &Path) -> Option<PathBuf> {
+    path.read_dir().ok()?.flatten().map(|e| e.path()).find(|p| {
+        p.is_dir()
+            && p.file_name()
+                .is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case("about"))
+    })
+}
+
+/// The `About.xml` of a mod, whatever its case
+fn find_about_xml(about_dir: &Path) -> Option<PathBuf> {
+    about_dir
+        .read_dir()
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            p.is_file()
+                && p.file_name()
+                    .is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case("about.xml"))
+        })
+}
+
+fn read_package_id(about_xml: &Path) -> Option<String> {
+    let file = File::open(about_xml).ok()?;
+    file.values_of(&["packageId"])
+        .into_iter()
+        .find(|e| e.name == "packageId")
+        .map(|e| e.value.trim().to_lowercase())
+}
+
+/// Checks every folder inside `mods_dir`
+pub fn scan(mods_dir: &Path) -> Vec<Issue> {
+    let mut issues = vec![];
+    let mut package_ids: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let mut steam_ids: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+    let Ok(entries) = mods_dir.read_dir() else {
+        return issues;
+    };
+
+    let mut folders: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .filter(|p| {
+            !p.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with(".rrm-")
+        })
+        .collect();
+    folders.sort();
+
+    for path in folders {
+        let issue = |kind| Issue {
+            path: path.clone(),
+            kind,
+        };
+
+        if path.read_dir().is_ok_and(|mut d| d.next().is_none()) {
+            issues.push(issue(IssueKind::EmptyFolder));
+            continue;
+        }
+
+        let Some(about_dir) = find_about_dir(&path) else {
+            issues.push(issue(IssueKind::MissingAbout));
+            continue;
+        };
+
+        let Some(about_xml) = find_about_xml(&about_dir) else {
+            issues.push(issue(IssueKind::MissingAbout));
+            continue;
+        };
+
+        if about_dir.file_name().unwrap() != "About"
+            || about_xml.file_name().unwrap() != "About.xml"
+        {
+            let found = about_xml
+                .strip_prefix(&path)
+                .unwrap_or(&about_xml)
+                .to_path_buf();
+            issues.push(issue(IssueKind::AboutCase(found)));
+        }
+
+        match File::open(&about_xml)
+            .map_err(|e| e.to_string())
+            .and_then(|f| f.validate())
+        {
+            Ok(()) => {
+                if let Some(package_id) = read_package_id(&about_xml) {
+                    package_ids
+                        .entry(package_id)
+                        .or_default()
+                        .push(path.clone());
+                }
+            }
+            Err(err) => issues.push(issue(IssueKind::UnreadableXml(about_xml.clone(), err))),
+        }
+
+        match read_steam_id(&path) {
+            Ok(id) => steam_ids
+                .entry(id.to_string())
+                .or_default()
+                .push(path.clone()),
+            // Local mods have none, only those in a Workshop folder need one
+            Err(None) => {
+                let missing = issue(IssueKind::MissingSteamId);
+                if missing.folder_steam_id().is_some() {
+                    issues.push(missing);
+                }
+            }
+            Err(Some(content)) => issues.push(issue(IssueKind::InvalidSteamId(content))),
+        }
+    }
+
+    let duplicates = |ids: BTreeMap<String, Vec<PathBuf>>,
+                      kind: fn(String, Vec<PathBuf>) -> IssueKind| {
+        ids.into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .flat_map(move |(id, paths)| {
+                paths.clone().into_iter().map(move |path| {
+                    let others = paths.iter().filter(|p| **p != path).cloned().collect();
+                    Issue {
+                        path,
+                        kind: kind(id.clone(), others),
+                    }
+                })
+            })
+            .collect::<Vec<Issue>>()
+    };
+
+    issues.extend(duplicates(package_ids, IssueKind::DuplicatePackageId));
+    issues.extend(duplicates(steam_ids, IssueKind::DuplicateSteamId));
+    issues
+}
+
+/// Applies the fix of `issue`, returning the Steam ID to reinstall when that is the fix
+fn fix(issue: &Issue) -> std::io::Result<Option<usize>> {
+    match &issue.kind {
+        IssueKind::EmptyFolder => std::fs::remove_dir(&issue.path).map(|_| None),
+        IssueKind::AboutCase(found) => {
+            let found = issue.path.join(found);
+            let about_dir = found.parent().unwrap();
+
+            // Go through a temporary name, a plain rename does nothing on case-insensitive disks
+            let tmp = issue.path.join(".rrm-about");
+            std::fs::rename(about_dir, &tmp)?;
+            std::fs::rename(&tmp, issue.path.join("About"))?;
+
+            let xml = issue.path.join("About").join(found.file_name().unwrap());
+            let tmp = issue.path.join("About").join(".rrm-about.xml");
+            std::fs::rename(xml, &tmp)?;
+            std::fs::rename(tmp, issue.path.join("About").join("About.xml"))?;
+            Ok(None)
+        }
+        IssueKind::MissingAbout | IssueKind::UnreadableXml(..) => Ok(issue.steam_id()),
+        IssueKind::MissingSteamId | IssueKind::InvalidSteamId(_) => {
+            let (Some(id), Some(about_dir)) =
+                (issue.folder_steam_id(), find_about_dir(&issue.path))
+            else {
+                return Ok(None);
+            };
+            std::fs::write(about_dir.join("PublishedFileId.txt"), id.to_string()).map(|_| None)
+        }
+        IssueKind::DuplicatePackageId(..) | IssueKind::DuplicateSteamId(..) => Ok(None),
+    }
+}
+
+/// Reports whether the steamcmd in use can run, and what to do when it can not
+fn check_steamcmd(i: &Installer) {
+    let (path, origin) = i.steamcmd();
+
+    if origin == Origin::Managed && !path.exists() {
+        log!(Info: "steamcmd will be downloaded on the first install, or now with `rrm steamcmd install`");
+        return;
+    }
+
+    match crate::steamcmd::check(i) {
+        Ok(version) => {
+            log!(Status: "steamcmd {} works, \"{}\" from {}", version, path.display(), origin)
+        }
+        Err(err) => {
+            log!(Warning: "steamcmd at \"{}\" from {}: {}", path.display(), origin, err);
+            if origin == Origin::Managed && !matches!(err, SteamCmdError::Missing32BitLibs(_)) {
+                log!(Info: "Run `rrm steamcmd update` to download it again");
+            }
+        }
+    }
+}
+
+pub async fn doctor(i: Installer, args: DoctorCommandGroup) -> Result<(), ExitCode> {
+    check_steamcmd(&i);
+
+    let rim_install = i.rim_install.as_ref().unwrap();
+    let mods_dir = rim_install.path().join("Mods");
+
+    let issues = scan(&mods_dir);
+
+    if issues.is_empty() {
+        log!(Status: "No problems found in \"{}\"", mods_dir.display());
+        return Ok(());
+    }
+
+    let mut to_reinstall = vec![];
+    for issue in &issues {
+        let name = issue
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        log!(Warning: "{}: {}", name, issue.describe());
+
+        let Some(fix_description) = issue.fix_description() else {
+            continue;
+        };
+
+        if !args.fix {
+            if args.verbose {
+                log!(Info: "--fix would {}", fix_description);
+            }
+            continue;
+        }
+
+        match fix(issue) {
+            Ok(Some(id)) => to_reinstall.push(id.to_string()),
+            Ok(None) => log!(Fixed: "{}: {}", name, fix_description),
+            Err(err) => log!(Error: "Could not {}: {}", fix_description, err),
+        }
+    }
+
+    let fixable = issues
+        .iter()
+        .filter(|i| i.fix_description().is_some())
+        .count();
+    log!(Status: "{} problem(s) found, {} can be fixed with --fix", issues.len(), fixable);
+
+    if issues.iter().any(|i| {
+        matches!(
+            i.kind,
+            IssueKind::DuplicatePackageId(..) | IssueKind::DuplicateSteamId(..)
+        )
+    }) {
+        log!(Info: "Run `rrm duplicates` to choose which copy of each duplicated mod to keep");
+    }
+
+    if !to_reinstall.is_empty() {
+        to_reinstall.sort_unstable();
+        to_reinstall.dedup();
+        crate::install::install(InstallCommandGroup::with_ids(to_reinstall), i).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IssueKind, scan};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_scan() {
+        let mods = std::env::temp_dir().join(format!("rrm_doctor_{}", std::process::id()));
+        let about = |id: &str| format!("<ModMetaData><packageId>{id}</packageId></ModMetaData>");
+
+        write(mods.join("1/About/About.xml"), &about("a.harmony"));
+        write(mods.join("1/About/PublishedFileId.txt"), "1");
+        write(mods.join("2/about/about.xml"), &about("A.Harmony"));
+        write(mods.join("2/about/PublishedFileId.txt"), "NOT AN ID");
+        write(mods.join("3/About/About.xml"), "<ModMetaData><name>");
+        write(mods.join("4/Textures/a.png"), "");
+        fs::create_dir_all(mods.join("5")).unwrap();
+        write(mods.join("MyMod/About/About.xml"), &about("me.mymod"));
+
+        let issues = scan(&mods);
+        let kinds = |folder: &str| {
+            issues
+                .iter()
+                .filter(|i| i.path == mods.join(folder))
+                .map(|i| i.kind.clone())
+                .collect::<Vec<IssueKind>>()
+        };
+
+        assert!(matches!(
+            kinds("1")[..],
+            [IssueKind::DuplicatePackageId(..)]
+        ));
+        assert!(matches!(
+            kinds("2")[..],
+            [
+                IssueKind::AboutCase(_),
+                IssueKind::InvalidSteamId(_),
+                IssueKind::DuplicatePackageId(..)
+            ]
+        ));
+        assert!(matches!(
+            kinds("3")[..],
+            [IssueKind::UnreadableXml(..), IssueKind::MissingSteamId]
+        ));
+        assert_eq!(kinds("4"), vec![IssueKind::MissingAbout]);
+        assert_eq!(kinds("5"), vec![IssueKind::EmptyFolder]);
+        assert!(kinds("MyMod").is_empty());
+
+        fs::remove_dir_all(mods).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+use crate::args::{DuplicatesCommandGroup, Keep};
+use crate::printf;
+use crate::utils::*;
+use std::collections::{BTreeMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use text_io::try_read;
+
+/// A folder inside `Mods/` that shares its packageId or Steam ID with another
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub m: Mod,
+    pub modified: u64,
+}
+
+impl Candidate {
+    pub fn is_workshop(&self) -> bool {
+        self.m.steam_id.parse::<usize>().is_ok()
+    }
+
+    fn folder(&self) -> String {
+        Path::new(&self.m.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Like `packageId brrainz.harmony` or `Steam ID 2009463077`
+    pub key: String,
+    pub candidates: Vec<Candidate>,
+}
+
+impl DuplicateGroup {
+    /// Index of the candidate `keep` prefers, falling back to the newest one on ties
+    pub fn pick(&self, keep: &Keep) -> usize {
+        let newest = |filter: &dyn Fn(&Candidate) -> bool| {
+            self.candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| filter(c))
+                .max_by_key(|(_, c)| c.modified)
+                .map(|(i, _)| i)
+        };
+
+        match keep {
+            Keep::Newest => newest(&|_| true),
+            Keep::Workshop => newest(&|c| c.is_workshop()),
+            Keep::Local => newest(&|c| !c.is_workshop()),
+        }
+        .or_else(|| newest(&|_| true))
+        .unwrap_or(0)
+    }
+
+    pub fn gen_display(&self) -> String {
+        let mut result = format!("{}\n", self.key);
+        self.candidates.iter().enumerate().for_each(|(i, c)| {
+            result.push_str(&format!(
+                " {:<4} {:<10} {:<20}   {:<9} {}\n",
+                i,
+                c.m.version.clone().unwrap_or_default(),
+                rrm_scrap::format_timestamp(c.modified),
+                if c.is_workshop() { "workshop" } else { "local" },
+                c.m.path
+            ));
+        });
+        result
+    }
+}
+
+fn modified(m: &Mod) -> u64 {
+    let about = Path::new(&m.path).join("About");
+    std::fs::metadata(&about)
+        .or_else(|_| std::fs::metadata(&m.path))
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        })
+}
+
+/// Groups installed mods by packageId and by Steam ID, keeping only the groups with more than
+/// one folder. Mods ignored with the `_` prefix are left out
+pub fn find_duplicates(mods: &GameMods) -> Vec<DuplicateGroup> {
+    let mut by_package_id: BTreeMap<String, Vec<Candidate>> = BTreeMap::new();
+    let mut by_steam_id: BTreeMap<String, Vec<Candidate>> = BTreeMap::new();
+
+    for m in mods.iter().filter(|m| !is_ignored(m)) {
+        let candidate = Candidate {
+            m: m.clone(),
+            modified: modified(m),
+        };
+
+        if let Some(package_id) = &m.package_id {
+            by_package_id
+                .entry(package_id.trim().to_lowercase())
+                .or_default()
+                .push(candidate.clone());
+        }
+
+        if candidate.is_workshop() {
+            by_steam_id
+                .entry(m.steam_id.clone())
+                .or_default()
+                .push(candidate);
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_package_id
+        .into_iter()
+        .filter(|(_, c)| c.len() > 1)
+        .map(|(id, candidates)| DuplicateGroup {
+            key: format!("packageId {id}"),
+            candidates,
+        })
+        .collect();
+
+    // A Steam ID group that only repeats a packageId group needs no second question
+    for (id, candidates) in by_steam_id.into_iter().filter(|(_, c)| c.len() > 1) {
+        let already_grouped = groups.iter().any(|g| {
+            candidates
+                .iter()
+                .all(|c| g.candidates.iter().any(|o| o.m.path == c.m.path))
+        });
+
+        if !already_grouped {
+            groups.push(DuplicateGroup {
+                key: format!("Steam ID {id}"),
+                candidates,
+            });
+        }
+    }
+
+    groups
+}
+
+/// Asks which candidate to keep, `None` to leave the group as it is
+fn ask(group: &DuplicateGroup) -> Option<usize> {
+    loop {
+        printf!(
+            "Keep which one? [0-{}/s(skip)]: ",
+            group.candidates.len() - 1
+        );
+        let read: Result<String, _> = try_read!();
+        let Ok(read) = read else {
+            log!(Error: "Somehting wrong happened. Re-input your answer.");
+            continue;
+        };
+
+        if read == "s" || read == "skip" {
+            return None;
+        }
+
+        match read.parse::<usize>() {
+            Ok(n) if n < group.candidates.len() => return Some(n),
+            _ => log!(Error: "Enter the # of the mod to keep or `s` to skip"),
+        }
+    }
+}
+
+/// The other copies of `group` once `keep` stays, leaving out those an earlier group already
+/// removed. `None` when `keep` itself was removed: the groups overlap, like a folder sharing
+/// its packageId with one mod and its Steam ID with another, so the rest are no duplicates of it
+fn losers<'a>(
+    group: &'a DuplicateGroup,
+    keep: usize,
+    removed: &HashSet<String>,
+) -> Option<Vec<&'a Candidate>> {
+    if removed.contains(&group.candidates[keep].m.path) {
+        return None;
+    }
+
+    Some(
+        group
+            .candidates
+            .iter()
+            .enumerate()
+            .filter(|(n, c)| *n != keep && !removed.contains(&c.m.path))
+            .map(|(_, c)| c)
+            .collect(),
+    )
+}
+
+pub fn duplicates(i: Installer, args: DuplicatesCommandGroup) {
+    let rim_install = i.rim_install.as_ref().unwrap();
+    let mods = GameMods::from(rim_install.clone());
+
+    let groups = find_duplicates(&mods);
+    if groups.is_empty() {
+        log!(Status: "No duplicated mods found");
+        return;
+    }
+
+    if args.dry_run {
+        log!(Warning: "Dry run, nothing will be changed");
+    }
+
+    // Also filled by a dry run, which removes nothing
+    let mut removed = HashSet::new();
+    for group in groups {
+        print!("{}", group.gen_display());
+
+        let keep = match &args.keep {
+            Some(keep) => group.pick(keep),
+            None if args.dry_run => continue,
+            None => match ask(&group) {
+                Some(n) => n,
+                None => continue,
+            },
+        };
+
+        let Some(losers) = losers(&group, keep, &removed) else {
+            log!(Skip: "{}, the copy to keep was removed as a duplicate of another mod", group.key);
+            continue;
+        };
+
+        for loser in losers {
+            // RimWorld loads every folder of `Mods/`, so the other copies leave it
+            let path = Path::new(&loser.m.path);
+            if args.dry_run {
+                log!(Backup: "\"{}\"", path.display());
+                removed.insert(loser.m.path.clone());
+                continue;
+            }
+            match crate::backup::store(path, &loser.folder()) {
+                Ok(backup) => {
+                    log!(Removed: "\"{}\", saved at \"{}\"", path.display(), backup.display());
+                    removed.insert(loser.m.path.clone());
+                }
+                Err(err) => log!(Error: "Could not remove \"{}\": {}", path.display(), err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Candidate, DuplicateGroup, losers};
+    use crate::args::Keep;
+    use rrm_locals::Mod;
+    use std::collections::HashSet;
+
+    fn candidate(steam_id: &str, modified: u64) -> Candidate {
+        Candidate {
+            m: Mod {
+                path: format!("/Mods/{steam_id}-{modified}"),
+                name: "Harmony".to_string(),
+                author: "".to_string(),
+                steam_id: steam_id.to_string(),
+                version: None,
+                package_id: Some("brrainz.harmony".to_string()),
+                identifier: None,
+                dependencies: None,
+            },
+            modified,
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let group = DuplicateGroup {
+            key: "packageId brrainz.harmony".to_string(),
+            candidates: vec![
+                candidate("2009463077", 10),
+                candidate("NOT FOUND", 30),
+                candidate("2009463077", 20),
+            ],
+        };
+
+        assert_eq!(group.pick(&Keep::Newest), 1);
+        assert_eq!(group.pick(&Keep::Workshop), 2);
+        assert_eq!(group.pick(&Keep::Local), 1);
+    }
+
+    #[test]
+    fn test_overlapping_groups() {
+        // A and B share packageId X, B and C share Steam ID 2, C is another mod
+        let mut a = candidate("1", 30);
+        let mut b = candidate("2", 20);
+        let mut c = candidate("2", 10);
+        a.m.package_id = Some("x".to_string());
+        b.m.package_id = Some("x".to_string());
+        c.m.package_id = Some("y".to_string());
+        let groups = [
+            DuplicateGroup {
+                key: "packageId x".to_string(),
+                candidates: vec![a.clone(), b.clone()],
+            },
+            DuplicateGroup {
+                key: "Steam ID 2".to_string(),
+                candidates: vec![b.clone(), c.clone()],
+            },
+        ];
+
+        let mut removed = HashSet::new();
+        let kept: Vec<Option<usize>> = groups
+            .iter()
+            .map(|group| {
+                let losers = losers(group, group.pick(&Keep::Newest), &removed)?;
+                removed.extend(losers.iter().map(|l| l.m.path.clone()));
+                Some(losers.len())
+            })
+            .collect();
+
+        // B goes with group X, which leaves nothing to choose in group 2
+        assert_eq!(kept, [Some(1), None]);
+        assert_eq!(removed, HashSet::from([b.m.path]));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::args::InfoCommandGroup;
+use crate::utils::*;
+use rrm_scrap::{ModSteamDetails, WorkshopClient, WorkshopError};
+
+pub async fn info(i: Installer, args: InfoCommandGroup) {
+    let mut ids = vec![];
+    for item in &args.items {
+        if let Some(id) = steam_id_from(item) {
+            ids.push(id);
+        } else {
+            log!(Error: "\"{}\" is not a Steam ID or a Workshop URL", item);
+        }
+    }
+
+    if ids.is_empty() {
+        return;
+    }
+
+    let large = args.display.large;
+    let details = match i.workshop().details(&ids, large).await {
+        Ok(details) => details,
+        Err(err @ WorkshopError::NotCached(_)) => {
+            log!(Warning: "{}", err);
+            log!(Warning: "Showing the About.xml of the installed mods instead");
+            let installed = GameMods::from(i.rim_install.clone().unwrap());
+            ids.iter()
+                .filter_map(|id| {
+                    let m = installed_with_id(&installed, *id).first().copied()?;
+                    Some(local_details(m, *id))
+                })
+                .collect()
+        }
+        Err(err) => {
+            log!(Error: "{}", err);
+            return;
+        }
+    };
+
+    if details.is_empty() {
+        println!("No results found");
+        return;
+    }
+
+    let d_type = DisplayType::from(large);
+    let biggest_name = details.iter().map(|d| d.title.len()).max().unwrap_or(0);
+
+    let mut output = String::new();
+    if let DisplayType::Short = d_type {
+        output.push_str(&ModSteamDetails::gen_headers(biggest_name));
+        output.push('\n');
+    }
+    details.iter().for_each(|d| {
+        output.push_str(&d.gen_display(&d_type, biggest_name));
+        output.push('\n');
+    });
+
+    if args.display.pager || i.use_more && !args.display.no_pager {
+        more_display(&output, &i.with_paging);
+    } else {
+        print!("{output}");
+    }
+}
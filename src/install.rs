@@ -1,111 +1,92 @@
 use crate::args::{InstallCommandGroup, InstallingOptions};
 use crate::printf;
+use crate::progress::Progress;
+use crate::resolve::InstallPlan;
+use crate::staging::Staging;
 use crate::utils::*;
-use async_recursion::async_recursion;
 use fs_extra::dir;
-use fs_extra::dir::CopyOptions;
-use notify::Event;
-use notify::Watcher;
-use notify::event::CreateKind;
-use regex::Regex;
-use rrm_locals::{FilterBy, Filtrable};
-use rrm_scrap::{FlagSet, ModSteamInfo};
-use std::cell::RefCell;
+use rrm_installer::{Pin, PinMode};
+use rrm_scrap::{ModSteamInfo, WorkshopClient, WorkshopError};
 use std::collections::HashSet;
+use std::process::ExitCode;
 use text_io::try_read;
 
-#[cfg(target_os = "windows")]
-const PATH: &str = r"steamcmd\steamapps\workshop\content\294100\";
+/// Prints what installing `plan` would change in `Mods/`, without touching anything
+fn print_dry_run(plan: &InstallPlan, installed: &GameMods, destination: &Path, pins: &[Pin]) {
+    log!(Warning: "Dry run, nothing will be downloaded or changed");
 
-#[cfg(target_os = "linux")]
-const PATH: &str = r"Steam/steamapps/workshop/content/294100/";
+    for m in plan.mods() {
+        let old_mods = installed_with_id(installed, m.id);
 
-#[cfg(target_os = "macos")]
-const PATH: &str = r"Library/Application Support/Steam/steamapps/workshop/content/294100";
-
-#[cfg(target_os = "linux")]
-pub const TMP_PATH: &str = "/tmp/rrm-downloads";
-
-fn download_cleanup() {
-    let tmp_path = PathBuf::from(TMP_PATH);
-
-    if !tmp_path.exists() {};
+        if pins
+            .iter()
+            .any(|p| p.id == m.id && matches!(p.mode, PinMode::Hash(_)))
+        {
+            log!(Download: "{} ({}), kept only if it matches the pinned hash", m.title, m.id);
+        } else {
+            log!(Download: "{} ({})", m.title, m.id);
+        }
+        for old_mod in old_mods {
+            log!(Backup: "\"{}\"", old_mod.path);
+        }
+        log!(Move: "\"{}\"", destination.join(m.id.to_string()).display());
+    }
+}
 
-    std::fs::remove_dir_all(tmp_path).unwrap();
+/// How an install ended when it did not fail
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Every mod was installed, or a dry run printed what would be
+    Done,
+    /// Nothing had to be installed, like when every mod asked for is pinned
+    NothingToDo,
+    /// The user did not want to continue
+    Declined,
 }
 
-#[async_recursion(?Send)]
-pub async fn install(
-    mut args: InstallCommandGroup,
-    i: Installer,
-    d: usize,
-    mut already_installed: HashSet<usize>,
-) {
-    if args.is_debug() {
-        log!(Warning: "Already installed {:?}", already_installed);
+/// Installs the mods of `args`, failing if one of them could not be downloaded or moved into
+/// `Mods/`
+pub async fn install(args: InstallCommandGroup, i: Installer) -> Result<Outcome, ExitCode> {
+    if args.rimmod.is_empty() {
+        return Ok(Outcome::NothingToDo);
     }
 
-    if args.rimmod.is_empty() {
-        std::process::exit(0);
+    let workshop = i.workshop();
+    if workshop.is_offline() && !args.dry_run {
+        log!(Error: "Mods can not be downloaded offline, use --dry-run to plan the install");
+        return Err(ExitCode::FAILURE);
     }
 
     use rrm_scrap::Filtrable;
 
     let mut to_install: Vec<ModSteamInfo> = Vec::new();
     let filter_obj = args.to_filter_obj();
-    let re = Regex::new(r"[a-zA-Z/:.]+[?0-9a-zA-Z0-9/=&]+[\?\&]{1}id=(?P<id>\d+).*").unwrap();
 
     for mod_identifier in &args.rimmod {
-        if mod_identifier.chars().all(char::is_numeric) {
-            if mod_identifier.trim().is_empty() {
-                continue;
-            }
+        if mod_identifier.trim().is_empty() {
+            continue;
+        }
 
+        if let Some(id) = steam_id_from(mod_identifier) {
             if args.is_verbose() {
-                log!(Status: "Adding {} to queue", mod_identifier);
+                log!(Status: "Adding {} to queue", id);
             }
 
             to_install.push(ModSteamInfo {
-                id: mod_identifier.parse().unwrap(),
-                title: mod_identifier.clone(),
+                id,
+                title: id.to_string(),
                 description: "".to_string(),
                 author: "".to_string(),
             });
             continue;
         }
 
-        if mod_identifier.contains("steamcommunity.com") {
-            let m = mod_identifier.replace(['\n', ' '], "");
-
-            if let Some(id) = extract_id(&m, &re) {
-                if id.trim().is_empty() {
-                    continue;
-                }
-
-                if args.is_verbose() {
-                    log!(Status: "Adding {} to queue", id);
-                }
-
-                to_install.push(ModSteamInfo {
-                    id: 0,
-                    title: id.clone(),
-                    description: "".to_string(),
-                    author: "".to_string(),
-                });
-                continue;
-            }
-        }
-
-        let mods = SteamMods::search(mod_identifier)
+        let mods = SteamMods::search(&workshop, mod_identifier)
             .await
             .with_raw_display(None);
 
-        let mut mods = if args.filter.is_some() {
-            let value = if args.filter.as_ref().unwrap().is_some() {
-                args.filter.as_ref().unwrap().clone().unwrap()
-            } else {
-                mod_identifier.clone()
-            };
+        let mut mods = if let Some(filter) = &args.filter {
+            let value = filter.clone().unwrap_or_else(|| mod_identifier.clone());
 
             mods.filter_by(filter_obj, &value)
         } else {
@@ -134,7 +115,7 @@ pub async fn install(
         if n == "yes" || n == "y" {
             to_install.push(mods[0].clone());
         } else if n == "n" || n == "no" {
-            std::process::exit(0)
+            return Ok(Outcome::Declined);
         } else if n == "s"
             || n == "select_other"
             || n == "select"
@@ -180,42 +161,121 @@ pub async fn install(
         }
     }
 
-    if d == 0 {
-        log!( Status:
-            "Installing mod{}",
-            if args.rimmod.len() > 1 { "s" } else { "" },
-        )
+    // A Workshop collection stands for the mods in it
+    let ids: Vec<usize> = to_install.iter().map(|m| m.id).collect();
+    match workshop.collections(&ids).await {
+        Ok(collections) if !collections.is_empty() => {
+            let mut expanded: Vec<ModSteamInfo> = vec![];
+            for m in to_install {
+                let children = collections.get(&m.id).cloned().unwrap_or_default();
+                if children.is_empty() {
+                    expanded.push(m);
+                    continue;
+                }
+
+                log!(Status: "Collection {} has {} mods", m.id, children.len());
+                for id in children {
+                    expanded.push(ModSteamInfo {
+                        id,
+                        title: id.to_string(),
+                        description: "".to_string(),
+                        author: "".to_string(),
+                    });
+                }
+            }
+            let mut seen = HashSet::new();
+            expanded.retain(|m| seen.insert(m.id));
+            to_install = expanded;
+        }
+        Ok(_) | Err(WorkshopError::NotCached(_)) => {}
+        Err(err) => log!(Warning: "Could not check for Workshop collections: {}", err),
+    }
+
+    let rim_install = i.rim_install.as_ref().unwrap();
+    let installed_mods = GameMods::from(rim_install.clone());
+
+    let pins = if args.ignore_pins {
+        vec![]
+    } else {
+        i.pins.clone()
     };
 
-    let path_downloads = PathBuf::from([TMP_PATH, PATH].join("/"));
-    log!(Info: "download path is: {}", &path_downloads.display());
+    to_install.retain(|m| {
+        let Some(pin) = crate::pin::held(&pins, m.id, &installed_mods) else {
+            return true;
+        };
+        log!(Skip: "{} ({}) is pinned, use `rrm unpin {}` or --ignore-pins to update it", pin.name, m.id, m.id);
+        false
+    });
 
-    if args.is_verbose() {
-        log!(Warning: "Starting file watcher");
+    if to_install.is_empty() {
+        return Ok(Outcome::NothingToDo);
     }
 
-    let mut cur = 0;
-    let mut last_printed = String::new();
-    let to_install_closure = to_install.clone();
-    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
-        Ok(event) => {
-            if let notify::EventKind::Create(CreateKind::Folder) = event.kind {
-                let path = event.paths[0].to_owned();
-                let current = path.file_name().unwrap().to_str().unwrap().to_owned();
-                if let Some(n) = path.parent() {
-                    if let Some(name) = n.file_name() {
-                        let name = name.to_str().unwrap();
-                        if name == "294100" && current != last_printed {
-                            cur += 1;
-                            log!(Status: "[{1:0>3}/{2:0>3}] Downloading {0}", current, cur, &to_install_closure.len());
-                            last_printed = current;
-                        }
-                    }
-                }
-            }
+    // Work out every mod to download from the Workshop metadata before spawning SteamCMD
+    let plan = crate::resolve::plan(
+        &workshop,
+        &args,
+        args.resolve,
+        to_install,
+        &installed_mods,
+        &pins,
+    )
+    .await;
+    let to_install = plan.mods();
+
+    plan.display();
+
+    if args.dry_run {
+        print_dry_run(
+            &plan,
+            &installed_mods,
+            &rim_install.path().join("Mods"),
+            &pins,
+        );
+        return Ok(Outcome::Done);
+    }
+
+    if !args.yes {
+        printf!("Want to continue? [y/n]: ");
+        let n = loop {
+            let read: Result<String, _> = try_read!();
+            if let Ok(read) = read {
+                break read;
+            } else {
+                log!(Error: "Somehting wrong happened. Re-input your answer.");
+            };
+        };
+
+        if n != "yes" && n != "y" {
+            return Ok(Outcome::Declined);
         }
-        Err(e) => log!(Error: "error event: {:?}", e),
-    }).unwrap();
+    }
+
+    if crate::steamcmd::ensure(&i).await.is_err() {
+        return Err(ExitCode::FAILURE);
+    }
+
+    log!( Status:
+        "Installing mod{}",
+        if to_install.len() > 1 { "s" } else { "" },
+    );
+
+    let staging = match Staging::create(&i) {
+        Ok(staging) => staging,
+        Err(err) => {
+            log!(Error: "{}", err);
+            return Err(ExitCode::FAILURE);
+        }
+    };
+    let path_downloads = staging.content_dir();
+    log!(Info: "download path is: {}", &path_downloads.display());
+
+    let mut progress = Progress::new(&to_install, &plan.sizes, staging.workshop_dir());
+    if args.is_debug() {
+        // Every line of steamcmd is printed, which would scroll the bars away
+        progress = progress.without_bars();
+    }
 
     let result = {
         let mut result = String::new();
@@ -233,12 +293,12 @@ pub async fn install(
                 args.clone(),
                 to_install.get(n..n + 200).unwrap().to_vec(),
                 i.clone(),
-                &mut watcher,
-                &path_downloads,
+                staging.dir(),
+                &mut progress,
             )
             .await;
             result.push_str(&r);
-            num = 200;
+            num -= 200;
             n += 200;
         }
 
@@ -249,8 +309,8 @@ pub async fn install(
             args.clone(),
             to_install.get(n..n + num).unwrap().to_vec(),
             i.clone(),
-            &mut watcher,
-            &path_downloads,
+            staging.dir(),
+            &mut progress,
         )
         .await;
         result.push_str(&r);
@@ -261,9 +321,7 @@ pub async fn install(
         log!(Status: "Installer finished");
     }
 
-    watcher
-        .unwatch(&rrm_installer::get_or_create_config_dir().join(path_downloads.parent().unwrap()))
-        .unwrap();
+    progress.finish();
 
     let mut successful_ids: HashSet<usize> = HashSet::new();
 
@@ -275,10 +333,10 @@ pub async fn install(
         }
     }
 
-    let rim_install = i.rim_install.as_ref().unwrap();
-
-    let mut dependencies_ids = RefCell::new(HashSet::new());
-
+    let mut failed = to_install
+        .iter()
+        .filter(|m| !successful_ids.contains(&m.id))
+        .count();
     let destination = rim_install.path().join("Mods");
 
     if args.is_verbose() {
@@ -289,37 +347,21 @@ pub async fn install(
     }
 
     for id in successful_ids {
-        let id_download_path: PathBuf = PathBuf::from([TMP_PATH, PATH, &id.to_string()].join("/"));
-        let options = CopyOptions {
-            overwrite: true,
-            ..Default::default()
-        };
-
-        let installed_mods =
-            GameMods::from(rim_install.path().to_str().unwrap()).with_display(DisplayType::Short);
-        let filtered = installed_mods.filter_by(FlagSet::from(FilterBy::SteamID), id);
-
-        let mut ignored = false;
-
-        for old_mod in filtered.mods {
-            //dbg!(&old_mod.path);
-            if old_mod.path.starts_with('_') {
-                if args.is_verbose() {
-                    log!( Warning: "Ignoring {}", old_mod.path);
-                }
-                ignored = true;
-                dir::remove(&id_download_path).unwrap();
-                break;
-            } else {
-                dir::remove(old_mod.path).unwrap();
-            }
-        }
+        let id_download_path = path_downloads.join(id.to_string());
+        let installed_mods = GameMods::from(rim_install.clone());
+        let old_mods = installed_with_id(&installed_mods, id);
 
-        if ignored {
+        if let Some(pin) = pins.iter().find(|p| p.id == id)
+            && crate::pin::rejects(pin, &id_download_path)
+        {
+            log!(Skip: "{} ({}), the Workshop version does not match the pinned hash", pin.name, id);
+            dir::remove(&id_download_path).unwrap();
             continue;
         }
 
-        if args.verbose {
+        // Stage the download next to its destination so the swap is a rename within `Mods/`
+        let staged = destination.join(format!(".rrm-staging-{id}"));
+        if args.is_verbose() {
             log!( Status:
                 "Moving \"{}\" to \"{}\"",
                 id_download_path.display().to_string(),
@@ -327,29 +369,42 @@ pub async fn install(
             );
         }
 
-        dir::move_dir(&id_download_path, &destination, &options).unwrap();
+        crate::interrupt::remove_on_interrupt(&staged);
+        if let Err(err) = crate::backup::move_folder(&id_download_path, &staged) {
+            log!(Error: "Could not move {} into \"{}\": {}", id, destination.display(), err);
+            crate::interrupt::keep_on_interrupt(&staged);
+            failed += 1;
+            continue;
+        }
 
-        let installed_mods =
-            GameMods::from(rim_install.path().to_str().unwrap()).with_display(DisplayType::Short);
-        let filtered = installed_mods.filter_by(FlagSet::from(FilterBy::SteamID), id);
+        for old_mod in &old_mods {
+            crate::settings::backup_mod(&i, old_mod);
+        }
 
-        already_installed.insert(id);
+        let old_paths: Vec<PathBuf> = old_mods.iter().map(|m| PathBuf::from(&m.path)).collect();
+        if let Err(err) =
+            crate::backup::replace(&staged, &destination.join(id.to_string()), &old_paths)
+        {
+            log!(Error: "Could not replace {}, the installed version was kept: {}", id, err);
+            let _ = dir::remove(&staged);
+            crate::interrupt::keep_on_interrupt(&staged);
+            failed += 1;
+            continue;
+        }
+        crate::interrupt::keep_on_interrupt(&staged);
+
+        let installed_mods = GameMods::from(rim_install.clone());
+        let filtered = installed_with_id(&installed_mods, id);
 
-        match filtered.mods.len() {
+        match filtered.len() {
             1 => {
-                let m: Mod = filtered.mods[0].clone(); // Get the installed mod as Mod instance (read its dependencies)
-
-                // If it does have dependencies
-                if let Some(dependencies) = m.dependencies {
-                    // Then add the ids to the queue
-                    let deps = dependencies.iter().map(|dep_id| dep_id.parse().unwrap());
-                    for id in deps {
-                        if id == 294100 {
-                            continue;
-                        }
-                        if !already_installed.contains(&id) {
-                            dependencies_ids.get_mut().insert(id);
-                        }
+                let m: Mod = filtered[0].clone(); // Get the installed mod as Mod instance (read its dependencies)
+
+                // Warn about About.xml dependencies the Workshop metadata did not list
+                let deps = m.dependencies.unwrap_or_default();
+                for dep in deps.iter().filter(|dep| dep.as_str() != "294100") {
+                    if !installed_mods.iter().any(|m| &m.steam_id == dep) {
+                        log!(Warning: "{} depends on {}, which is not installed", m.name, dep);
                     }
                 }
             }
@@ -370,37 +425,16 @@ pub async fn install(
         }
     }
 
-    // TODO: Update this log
-    // if !ids.is_empty() {
-    //     log!(Warning: "Did not install {:?}", ids);
-    // }
-
-    if d == 0 && args.is_verbose() {
-        log!(Status: "Done!");
-    };
-
-    //dbg!(&dependencies_ids);
-    //
-
     log!(Info: "Cleaning up temporary folders...");
-    download_cleanup();
+    drop(staging);
 
-    if args.resolve && !dependencies_ids.get_mut().is_empty() {
-        if d == 0 {
-            log!( Status:
-                "Installing dependencies",
-            );
-        };
-        args.rimmod = Vec::from_iter(
-            dependencies_ids
-                .get_mut()
-                .iter()
-                .map(|id| id.to_string())
-                .clone(),
-        );
-        install(args.clone(), i.clone(), d + 1, already_installed.clone()).await;
-        if d == 0 {
-            log!(Status: "Done!");
-        };
+    if failed > 0 {
+        log!(Error: "{} of {} mod(s) could not be installed", failed, to_install.len());
+        return Err(ExitCode::FAILURE);
     }
+
+    if args.is_verbose() {
+        log!(Status: "Done!");
+    };
+    Ok(Outcome::Done)
 }
@@ -0,0 +1,118 @@
+use crate::utils::*;
+use rrm_installer::Instance;
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "default"
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !name.starts_with('.')
+}
+
+pub fn add(
+    mut i: Installer,
+    name: String,
+    game_path: PathBuf,
+    user_data_dir: Option<PathBuf>,
+    use_it: bool,
+) {
+    if !valid_name(&name) {
+        log!(Error: "\"{}\" can not be used as an instance name, use letters, numbers, `-`, `_` and `.`", name);
+        return;
+    }
+
+    if i.instances.iter().any(|i| i.name == name) {
+        log!(Error: "There is already an instance named \"{}\"", name);
+        return;
+    }
+
+    if !dir_exists(&game_path) {
+        log!(Error: "\"{}\" is not a valid RimWorld installation path", game_path.display());
+        return;
+    }
+
+    // The Config folder itself was asked for before user data folders were
+    let user_data_dir = match user_data_dir {
+        Some(path) if path.ends_with("Config") => path.parent().map(Path::to_path_buf),
+        path => path,
+    };
+    let user_data_dir = match user_data_dir.as_deref().map(UserDataPath::create) {
+        Some(Err(err)) => {
+            log!(Error: "{}", err);
+            return;
+        }
+        Some(Ok(data)) => Some(data),
+        None => None,
+    };
+
+    let game_path = game_path.canonicalize().unwrap_or(game_path);
+    i.add_instance(Instance {
+        name: name.clone(),
+        game_path: GamePath::from(&game_path),
+        user_data_dir,
+    });
+    log!(Added: "Instance \"{}\" at \"{}\"", name, game_path.display());
+
+    if use_it {
+        i.set_current_instance(Some(name.clone()));
+        log!(Using: "\"{}\"", name);
+    }
+}
+
+pub fn list(i: Installer) {
+    let active = i
+        .instance
+        .as_ref()
+        .map(|i| i.name.as_str())
+        .unwrap_or("default");
+
+    let row = |name: &str, path: Option<&GamePath>, user_data: Option<&UserDataPath>| {
+        println!(
+            "{} {:<16} {:<50} {}",
+            if name == active { "*" } else { " " },
+            name,
+            path.map(|p| p.path().display().to_string())
+                .unwrap_or_else(|| "Not set".to_string()),
+            user_data
+                .map(|p| p.path().display().to_string())
+                .unwrap_or_else(|| "Default".to_string())
+        )
+    };
+
+    println!("  {:<16} {:<50} User data", "Name", "Game path");
+    println!("  {:<16} {:<50} ---------", "----", "---------");
+    row("default", i.default_install(), None);
+    for instance in &i.instances {
+        row(
+            &instance.name,
+            Some(&instance.game_path),
+            instance.user_data_dir.as_ref(),
+        );
+    }
+}
+
+pub fn use_instance(mut i: Installer, name: String) {
+    if name == "default" {
+        i.set_current_instance(None);
+    } else if i.instances.iter().any(|i| i.name == name) {
+        i.set_current_instance(Some(name.clone()));
+    } else {
+        log!(Error: "There is no instance named \"{}\", see `rrm instance list`", name);
+        return;
+    }
+
+    log!(Using: "\"{}\"", name);
+}
+
+pub fn remove(mut i: Installer, name: String) {
+    match i.remove_instance(&name) {
+        Some(instance) => {
+            log!(Removed: "Instance \"{}\"", name);
+            if instance.data_dir().exists() {
+                log!(Info: "Its backups and snapshots are still at \"{}\"", instance.data_dir().display());
+            }
+        }
+        None => log!(Error: "There is no instance named \"{}\"", name),
+    }
+}
@@ -0,0 +1,101 @@
+use crate::utils::*;
+use std::sync::{Mutex, MutexGuard, Once};
+use std::time::Duration;
+
+/// What has to be removed when rrm is stopped with Ctrl-C
+static ON_INTERRUPT: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// How many uninterruptible sections run, and whether rrm is exiting so no new one may start
+static CRITICAL: Mutex<(usize, bool)> = Mutex::new((0, false));
+
+fn registered() -> MutexGuard<'static, Vec<PathBuf>> {
+    ON_INTERRUPT.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn critical() -> MutexGuard<'static, (usize, bool)> {
+    CRITICAL.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Marks rrm as exiting, unless an uninterruptible section still runs
+fn start_exiting() -> bool {
+    let mut state = critical();
+    if state.0 == 0 {
+        state.1 = true;
+    }
+    state.1
+}
+
+/// Removes everything registered before exiting on Ctrl-C, once no uninterruptible work runs
+fn handle() {
+    static HANDLER: Once = Once::new();
+
+    if tokio::runtime::Handle::try_current().is_err() {
+        return;
+    }
+
+    HANDLER.call_once(|| {
+        tokio::spawn(async {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+
+            while !start_exiting() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+
+            for path in std::mem::take(&mut *registered()) {
+                if path.is_dir() {
+                    let _ = std::fs::remove_dir_all(&path);
+                } else {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+            log!(Warning: "Interrupted, the unfinished work of this run was removed");
+            std::process::exit(130);
+        });
+    });
+}
+
+/// Has `path`, a file or a folder, removed if rrm is stopped with Ctrl-C
+pub fn remove_on_interrupt(path: &Path) {
+    registered().push(path.to_path_buf());
+    handle();
+}
+
+/// Undoes `remove_on_interrupt`, once `path` was removed or has to be kept
+pub fn keep_on_interrupt(path: &Path) {
+    registered().retain(|p| p != path);
+}
+
+/// Runs `f` to the end even if Ctrl-C is pressed meanwhile, so rrm only exits after it. Once
+/// rrm is exiting `f` does not start at all
+pub fn uninterruptible<T>(f: impl FnOnce() -> T) -> T {
+    handle();
+    {
+        let mut state = critical();
+        if state.1 {
+            drop(state);
+            loop {
+                std::thread::park();
+            }
+        }
+        state.0 += 1;
+    }
+
+    let result = f();
+    critical().0 -= 1;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{critical, uninterruptible};
+
+    #[test]
+    fn test_uninterruptible() {
+        let nested = uninterruptible(|| uninterruptible(|| critical().0));
+        assert_eq!(nested, 2);
+        assert_eq!(critical().0, 0);
+        assert!(!critical().1);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::utils::*;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+
+pub const LOCK_FILE: &str = "rrm.lock";
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another rrm holds the lock, with this PID when it had written it yet
+    Held(Option<u32>),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::Held(Some(pid)) => {
+                write!(f, "Another rrm (PID {pid}) is changing this instance")
+            }
+            LockError::Held(None) => write!(f, "Another rrm is changing this instance"),
+            LockError::Io(err) => write!(f, "Could not lock the instance: {err}"),
+        }
+    }
+}
+
+/// An advisory lock on a file that stays in place, released when dropped or when the process
+/// ends however it ends. The PID written in it is only there to tell who holds it
+#[derive(Debug)]
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    pub fn acquire(dir: &Path) -> Result<Lock, LockError> {
+        std::fs::create_dir_all(dir).map_err(LockError::Io)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOCK_FILE))
+            .map_err(LockError::Io)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                return Err(LockError::Held(pid.trim().parse().ok()));
+            }
+            Err(TryLockError::Error(err)) => return Err(LockError::Io(err)),
+        }
+
+        file.set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| write!(file, "{}", std::process::id()))
+            .map_err(LockError::Io)?;
+        Ok(Lock { _file: file })
+    }
+}
+
+/// Locks the selected instance for a command that changes its mods, once RimWorld is not running
+/// from it. With `wait` it waits for both instead of stopping
+pub async fn lock_instance(i: &Installer, wait: bool) -> Result<Lock, ExitCode> {
+    let dir = rrm_installer::get_or_create_data_dir();
+    let player_log = UserDataPath::player_log();
+    let mut waiting_for = String::new();
+
+    loop {
+        let games = crate::running::rimworld(player_log.as_deref());
+        let game_path = i.rim_install.as_ref().map(|g| g.path().to_path_buf());
+        let (this, other): (Vec<_>, Vec<_>) = games
+            .into_iter()
+            .partition(|g| game_path.as_deref().is_none_or(|path| g.runs_from(path)));
+
+        let blocker = match this.first() {
+            Some(game) => Err(format!(
+                "{game} is running, close it before changing its mods"
+            )),
+            None => Lock::acquire(&dir).map_err(|err| err.to_string()),
+        };
+
+        match blocker {
+            Ok(lock) => {
+                for game in other {
+                    log!(Warning: "{} is running from another installation", game);
+                }
+                return Ok(lock);
+            }
+            Err(err) if wait => {
+                if err != waiting_for {
+                    log!(Status: "Waiting: {}", err);
+                    waiting_for = err;
+                }
+            }
+            Err(err) => {
+                log!(Error: "{}", err);
+                log!(Info: "Run it with --wait to start once that is done");
+                return Err(ExitCode::FAILURE);
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lock, LockError};
+
+    #[test]
+    fn test_lock() {
+        let dir = std::env::temp_dir().join(format!("rrm_lock_{}", std::process::id()));
+        let path = dir.join(super::LOCK_FILE);
+
+        let lock = Lock::acquire(&dir).unwrap();
+        assert!(matches!(
+            Lock::acquire(&dir),
+            Err(LockError::Held(Some(pid))) if pid == std::process::id()
+        ));
+
+        // The file stays, only the lock on it goes
+        drop(lock);
+        assert!(path.exists());
+
+        // An empty file is never a reason to take or delete a lock held by someone else
+        std::fs::write(&path, "").unwrap();
+        let lock = Lock::acquire(&dir).unwrap();
+        assert!(matches!(Lock::acquire(&dir), Err(LockError::Held(_))));
+        drop(lock);
+
+        // The PID of a run that no longer exists does not hold anything
+        std::fs::write(&path, i32::MAX.to_string()).unwrap();
+        drop(Lock::acquire(&dir).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,16 +1,36 @@
 extern crate core;
 
-use crate::args::Options;
 use clap::CommandFactory;
-use std::{collections::HashSet, process::ExitCode};
+use std::process::ExitCode;
 
 mod args;
 mod async_installer;
+mod backup;
+mod cache;
+mod config;
+mod detect;
+mod doctor;
+mod duplicates;
+mod info;
 mod install;
+mod instance;
+mod interrupt;
 mod list;
+mod lock;
 mod logger;
+mod modpack;
+mod pin;
+mod player_log;
+mod progress;
 mod pull;
+mod resolve;
+mod run;
+mod running;
 mod search;
+mod settings;
+mod snapshot;
+mod staging;
+mod steamcmd;
 mod utils;
 use clap_complete::{Shell, generate};
 
@@ -31,15 +51,33 @@ fn main() -> ExitCode {
 
 #[tokio::main]
 async fn app() -> Result<(), ExitCode> {
-    let args: args::App = args::App::load();
+    let mut args: args::App = args::App::load();
+    if args.offline {
+        args.overrides
+            .push(("offline".to_string(), "true".to_string()));
+    }
 
-    let mut installer = utils::try_get_path(
+    let installer = utils::try_get_path(
         None,
-        matches!(&args.command, args::Commands::Set {
-            command: Options::GamePath { .. }
-        }),
+        matches!(
+            &args.command,
+            args::Commands::Set { .. }
+                | args::Commands::Config { .. }
+                | args::Commands::Instance { .. }
+                | args::Commands::Detect { .. }
+                | args::Commands::Steamcmd { .. }
+        ),
+        args.instance.as_deref(),
+        &args.overrides,
     );
 
+    // Held until the command is done
+    let _lock = if args.command.changes_mods() {
+        Some(lock::lock_instance(&installer, args.wait).await?)
+    } else {
+        None
+    };
+
     match args.command {
         args::Commands::Completions { shell } => {
             let mut matches = args::App::command();
@@ -59,28 +97,88 @@ async fn app() -> Result<(), ExitCode> {
             Ok(())
         }
 
-        args::Commands::Set { command } => match command {
-            Options::UsePager { value } => {
-                installer.set_more_value(value == "true" || value == "1");
+        args::Commands::Config { command } => match command {
+            args::Config::Get { key } => config::get(installer, key),
+            args::Config::Set { key, value } => config::set(installer, key, value),
+            args::Config::Unset { key } => config::unset(installer, key),
+            args::Config::List => {
+                config::list(installer);
                 Ok(())
             }
+            args::Config::Edit => config::edit(installer),
+        },
 
-            Options::GamePath { value } => {
-                installer.set_path_value(value);
+        args::Commands::Cache { command } => match command {
+            args::Cache::Info => {
+                cache::info(installer);
                 Ok(())
             }
+            args::Cache::Clear => cache::clear(),
+        },
 
-            Options::Pager { value } => {
-                installer.set_paging_software(value.to_str().unwrap());
-                Ok(())
-            }
+        args::Commands::Steamcmd { command } => match command {
+            args::Steamcmd::Install => steamcmd::install(installer).await,
+            args::Steamcmd::Update => steamcmd::update(installer).await,
+            args::Steamcmd::Path => steamcmd::path(installer),
+            args::Steamcmd::Reset => steamcmd::reset(installer),
         },
 
-        args::Commands::Pull { args, ignored } => {
-            pull::pull(args, installer, ignored).await;
+        args::Commands::Set { key, value } => {
+            let key = match key.as_str() {
+                "path" => "game-path",
+                "paging" => "pager",
+                "use-paging" => "use-pager",
+                key => key,
+            };
+            config::set(installer, key.to_string(), value)
+        }
+
+        args::Commands::Pull { args, ignored } => pull::pull(args, installer, ignored).await,
+
+        args::Commands::Pin { args } => {
+            pin::pin(installer, args);
             Ok(())
         }
 
+        args::Commands::Unpin { r#mod } => {
+            pin::unpin(installer, r#mod);
+            Ok(())
+        }
+
+        args::Commands::Pins => {
+            pin::pins(installer);
+            Ok(())
+        }
+
+        args::Commands::Detect { set } => {
+            detect::detect(installer, set);
+            Ok(())
+        }
+
+        args::Commands::Instance { command } => match command {
+            args::Instance::Add {
+                name,
+                game_path,
+                user_data_dir,
+                use_it,
+            } => {
+                instance::add(installer, name, game_path, user_data_dir, use_it);
+                Ok(())
+            }
+            args::Instance::List => {
+                instance::list(installer);
+                Ok(())
+            }
+            args::Instance::Use { name } => {
+                instance::use_instance(installer, name);
+                Ok(())
+            }
+            args::Instance::Remove { name } => {
+                instance::remove(installer, name);
+                Ok(())
+            }
+        },
+
         args::Commands::List { display } => {
             list::list(installer, display);
             Ok(())
@@ -107,9 +205,60 @@ async fn app() -> Result<(), ExitCode> {
             Ok(())
         }
 
-        args::Commands::Install { args } => {
-            install::install(args, installer, 0, HashSet::new()).await;
+        args::Commands::Rollback { args } => {
+            backup::rollback(installer, args);
+            Ok(())
+        }
+
+        args::Commands::Backups { command } => match command {
+            args::Backups::List { display } => {
+                backup::list(display);
+                Ok(())
+            }
+            args::Backups::Prune { keep, dry_run } => {
+                backup::prune(keep, dry_run);
+                Ok(())
+            }
+        },
+
+        args::Commands::Snapshot { command } => match command {
+            args::Snapshot::Create { name } => {
+                snapshot::create(installer, name);
+                Ok(())
+            }
+            args::Snapshot::List => {
+                snapshot::list();
+                Ok(())
+            }
+            args::Snapshot::Restore { name, dry_run, yes } => {
+                snapshot::restore(installer, name, dry_run, yes)
+            }
+            args::Snapshot::Delete { name } => {
+                snapshot::delete(name);
+                Ok(())
+            }
+        },
+
+        args::Commands::Doctor { args } => doctor::doctor(installer, args).await,
+
+        args::Commands::Apply { args } => modpack::apply(installer, args).await,
+
+        args::Commands::Log { args } => player_log::log(installer, args),
+
+        args::Commands::Run { args: run_args } => run::run(installer, run_args, args.wait).await,
+
+        args::Commands::Settings { command } => settings::settings(installer, command),
+
+        args::Commands::Duplicates { args } => {
+            duplicates::duplicates(installer, args);
+            Ok(())
+        }
+
+        args::Commands::Info { args } => {
+            info::info(installer, args).await;
             Ok(())
         }
+
+        args::Commands::Install { args } => install::install(args, installer).await.map(|_| ()),
     }
 }
@@ -0,0 +1,757 @@
+use crate::args::{ApplyCommandGroup, InstallCommandGroup};
+use crate::install::Outcome;
+use crate::printf;
+use crate::utils::*;
+use regex::Regex;
+use rrm_installer::{Pin, PinMode};
+use rrm_locals::ModVec;
+use serde::Deserialize;
+use std::process::{Command, ExitCode};
+use text_io::try_read;
+
+pub const MODPACK_FILE: &str = "modpack.toml";
+const CORE: &str = "ludeon.rimworld";
+
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Meta {
+    pub name: Option<String>,
+    /// Like `1.5`, matched against the start of `Version.txt`
+    pub game_version: Option<String>,
+}
+
+/// A `[[mods]]` table as written, before checking it names exactly one source
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawEntry {
+    steam_id: Option<usize>,
+    package_id: Option<String>,
+    git: Option<String>,
+    rev: Option<String>,
+    path: Option<PathBuf>,
+    folder: Option<String>,
+    pin: Option<PinMode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawModpack {
+    #[serde(default)]
+    modpack: Meta,
+    #[serde(default)]
+    mods: Vec<RawEntry>,
+}
+
+/// Where a mod of the modpack comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Workshop(usize),
+    /// Already there, like Core, a DLC or a mod installed by hand. Only sets its load order
+    PackageId(String),
+    Git {
+        url: String,
+        rev: Option<String>,
+    },
+    Local(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub source: Source,
+    folder: Option<String>,
+    pub pin: Option<PinMode>,
+}
+
+impl Entry {
+    /// The folder inside `Mods/` the mod is installed to, `None` for packageId entries
+    pub fn folder(&self) -> Option<String> {
+        match &self.source {
+            Source::Workshop(id) => Some(id.to_string()),
+            Source::PackageId(_) => None,
+            Source::Git { url, .. } => self.folder.clone().or_else(|| {
+                url.trim_end_matches('/')
+                    .rsplit(['/', ':'])
+                    .next()
+                    .map(|name| name.trim_end_matches(".git").to_string())
+            }),
+            Source::Local(path) => self.folder.clone().or_else(|| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            }),
+        }
+    }
+
+    pub fn matches(&self, m: &Mod) -> bool {
+        match &self.source {
+            Source::Workshop(id) => m.steam_id == id.to_string(),
+            Source::PackageId(package_id) => m
+                .package_id
+                .as_ref()
+                .is_some_and(|p| p.trim().eq_ignore_ascii_case(package_id)),
+            Source::Git { .. } | Source::Local(_) => {
+                Path::new(&m.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    == self.folder()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            Source::Workshop(id) => write!(f, "steam-id {id}"),
+            Source::PackageId(package_id) => write!(f, "package-id {package_id}"),
+            Source::Git { url, .. } => write!(f, "git {url}"),
+            Source::Local(path) => write!(f, "path {}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modpack {
+    pub meta: Meta,
+    /// In load order
+    pub mods: Vec<Entry>,
+}
+
+impl Modpack {
+    /// Parses a manifest, with `path` entries relative to `base`
+    pub fn parse(content: &str, base: &Path) -> Result<Modpack, String> {
+        let raw: RawModpack = toml::from_str(content).map_err(|err| err.to_string())?;
+
+        let mut mods = vec![];
+        for (n, raw) in raw.mods.into_iter().enumerate() {
+            let n = n + 1;
+            let sources = [
+                raw.steam_id.is_some(),
+                raw.package_id.is_some(),
+                raw.git.is_some(),
+                raw.path.is_some(),
+            ];
+            if sources.iter().filter(|s| **s).count() != 1 {
+                return Err(format!(
+                    "mod #{n} needs exactly one of steam-id, package-id, git or path"
+                ));
+            }
+            if raw.rev.is_some() && raw.git.is_none() {
+                return Err(format!("mod #{n} has a rev but no git URL"));
+            }
+            // git would take them as options
+            if [&raw.git, &raw.rev]
+                .into_iter()
+                .flatten()
+                .any(|value| value.starts_with('-'))
+            {
+                return Err(format!("mod #{n} has a git URL or rev starting with \"-\""));
+            }
+            if raw.folder.is_some() && raw.git.is_none() && raw.path.is_none() {
+                return Err(format!(
+                    "mod #{n} can only set the folder of git and path mods"
+                ));
+            }
+            if raw.pin.is_some() && raw.steam_id.is_none() {
+                return Err(format!("mod #{n} can only be pinned if it has a steam-id"));
+            }
+
+            let source = if let Some(id) = raw.steam_id {
+                Source::Workshop(id)
+            } else if let Some(package_id) = raw.package_id {
+                Source::PackageId(package_id.trim().to_lowercase())
+            } else if let Some(url) = raw.git {
+                Source::Git { url, rev: raw.rev }
+            } else {
+                Source::Local(base.join(raw.path.unwrap()))
+            };
+
+            let entry = Entry {
+                source,
+                folder: raw.folder,
+                pin: raw.pin,
+            };
+            let invalid = |f: &String| f.is_empty() || f == ".." || f.contains(['/', '\\']);
+            if !matches!(entry.source, Source::PackageId(_))
+                && entry.folder().is_none_or(|f| invalid(&f))
+            {
+                return Err(format!(
+                    "mod #{n} has no valid folder name, set one with `folder`"
+                ));
+            }
+            mods.push(entry);
+        }
+
+        Ok(Modpack {
+            meta: raw.modpack,
+            mods,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Modpack, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read \"{}\": {err}", path.display()))?;
+        Modpack::parse(&content, path.parent().unwrap_or(Path::new(".")))
+            .map_err(|err| format!("Invalid modpack \"{}\": {err}", path.display()))
+    }
+}
+
+/// Looks for a `modpack.toml` in `start` and every folder above it
+pub fn find_modpack(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(MODPACK_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Whether the installed `version`, like `1.5.4104 rev435`, is the `required` one, like `1.5`
+pub fn matches_version(required: &str, version: &str) -> bool {
+    let required = required.trim();
+    version
+        .strip_prefix(required)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', ' ']))
+}
+
+/// The packageIds in the `activeMods` list of a `ModsConfig.xml`
+pub fn active_mods(xml: &str) -> Vec<String> {
+    let Some(start) = xml.find("<activeMods>") else {
+        return vec![];
+    };
+    let end = xml[start..]
+        .find("</activeMods>")
+        .map(|end| start + end)
+        .unwrap_or(xml.len());
+
+    Regex::new(r"<li>\s*([^<]*?)\s*</li>")
+        .unwrap()
+        .captures_iter(&xml[start..end])
+        .map(|c| c[1].to_lowercase())
+        .collect()
+}
+
+/// `existing` with its `activeMods` replaced by `active`, keeping the known expansions and
+/// everything else RimWorld wrote. Without an existing file a new one is made for `version`
+pub fn mods_config_xml(existing: Option<&str>, version: &str, active: &[String]) -> String {
+    let mut list = String::from("<activeMods>\n");
+    for package_id in active {
+        list.push_str(&format!("    <li>{package_id}</li>\n"));
+    }
+    list.push_str("  </activeMods>");
+
+    if let Some(xml) = existing {
+        if let Some(start) = xml.find("<activeMods>")
+            && let Some(end) = xml[start..].find("</activeMods>")
+        {
+            let end = start + end + "</activeMods>".len();
+            return format!("{}{}{}", &xml[..start], list, &xml[end..]);
+        }
+        if let Some(start) = xml.find("<activeMods />") {
+            let end = start + "<activeMods />".len();
+            return format!("{}{}{}", &xml[..start], list, &xml[end..]);
+        }
+        if let Some(start) = xml.find("</ModsConfigData>") {
+            return format!("{}  {}\n{}", &xml[..start], list, &xml[start..]);
+        }
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ModsConfigData>\n  \
+         <version>{version}</version>\n  \
+         {list}\n  \
+         <knownExpansions />\n\
+         </ModsConfigData>\n"
+    )
+}
+
+/// The mods of the game's `Data/` folder: Core and the DLCs
+fn official_mods(game: &Path) -> Vec<Mod> {
+    let data = game.join("Data");
+    if !dir_exists(&data) {
+        return vec![];
+    }
+    rrm_locals::mods_at(&data).parse().0
+}
+
+fn git(args: &[&str], dir: &Path) -> Result<(), String> {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .map_err(|err| format!("could not run git: {err}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("`git {}` failed", args.join(" ")))
+    }
+}
+
+fn checkout(dir: &Path, rev: &str) -> Result<(), String> {
+    git(&["fetch", "--depth", "1", "--", "origin", rev], dir)?;
+    git(&["checkout", "--quiet", "--detach", "FETCH_HEAD"], dir)
+}
+
+/// Clones `url` at `rev` next to `destination` and swaps it in
+fn clone(url: &str, rev: Option<&str>, destination: &Path) -> Result<(), String> {
+    let mods_dir = destination.parent().unwrap();
+    let staged = mods_dir.join(format!(
+        ".rrm-staging-{}",
+        destination.file_name().unwrap().to_string_lossy()
+    ));
+    if staged.exists() {
+        let _ = std::fs::remove_dir_all(&staged);
+    }
+
+    let staged_str = staged.to_string_lossy().to_string();
+    crate::interrupt::remove_on_interrupt(&staged);
+    let result = git(
+        &["clone", "--quiet", "--depth", "1", "--", url, &staged_str],
+        mods_dir,
+    )
+    .and_then(|_| rev.map_or(Ok(()), |rev| checkout(&staged, rev)))
+    .and_then(|_| {
+        crate::interrupt::uninterruptible(|| std::fs::rename(&staged, destination))
+            .map_err(|err| err.to_string())
+    })
+    .inspect_err(|_| {
+        let _ = std::fs::remove_dir_all(&staged);
+    });
+    crate::interrupt::keep_on_interrupt(&staged);
+    result
+}
+
+/// Copies the local mod at `from` into `destination`, backing up what was there
+fn copy_local(from: &Path, destination: &Path) -> Result<(), String> {
+    let mods_dir = destination.parent().unwrap();
+    let staged = mods_dir.join(format!(
+        ".rrm-staging-{}",
+        destination.file_name().unwrap().to_string_lossy()
+    ));
+    if staged.exists() {
+        let _ = std::fs::remove_dir_all(&staged);
+    }
+
+    crate::interrupt::remove_on_interrupt(&staged);
+    let result = copy_staged(from, &staged, destination);
+    crate::interrupt::keep_on_interrupt(&staged);
+    result
+}
+
+fn copy_staged(from: &Path, staged: &Path, destination: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(staged).map_err(|err| err.to_string())?;
+    let options = fs_extra::dir::CopyOptions {
+        content_only: true,
+        ..Default::default()
+    };
+    if let Err(err) = fs_extra::dir::copy(from, staged, &options) {
+        let _ = std::fs::remove_dir_all(staged);
+        return Err(err.to_string());
+    }
+
+    let old = if destination.exists() {
+        vec![destination.to_path_buf()]
+    } else {
+        vec![]
+    };
+    crate::backup::replace(staged, destination, &old).map_err(|err| {
+        let _ = std::fs::remove_dir_all(staged);
+        err.to_string()
+    })
+}
+
+pub(crate) fn confirm(question: &str) -> bool {
+    printf!("{} [y/n]: ", question);
+    let n = loop {
+        let read: Result<String, _> = try_read!();
+        if let Ok(read) = read {
+            break read;
+        } else {
+            log!(Error: "Somehting wrong happened. Re-input your answer.");
+        };
+    };
+    n == "yes" || n == "y"
+}
+
+/// Saves the pins of the modpack, so updates and dependency resolution respect them
+fn apply_pins(i: &mut Installer, pack: &Modpack, installed: &GameMods, dry_run: bool) {
+    for entry in &pack.mods {
+        let (Source::Workshop(id), Some(mode)) = (&entry.source, &entry.pin) else {
+            continue;
+        };
+        if i.pin_of(*id).is_some_and(|p| &p.mode == mode) {
+            continue;
+        }
+
+        let name = installed_with_id(installed, *id)
+            .first()
+            .map(|m| m.name.clone())
+            .unwrap_or_else(|| id.to_string());
+        log!(Pinned: "{} ({}), {}", name, id, mode);
+        if !dry_run {
+            i.add_pin(Pin {
+                id: *id,
+                name,
+                mode: mode.clone(),
+            });
+        }
+    }
+}
+
+/// Clones, checks out or copies the git and path mods
+fn apply_sources(pack: &Modpack, mods_dir: &Path, dry_run: bool) {
+    for entry in &pack.mods {
+        let Some(folder) = entry.folder() else {
+            continue;
+        };
+        let destination = mods_dir.join(&folder);
+
+        match &entry.source {
+            Source::Git { url, rev } => {
+                let result = if !destination.exists() {
+                    log!(Clone: "{} into \"{}\"", url, destination.display());
+                    if dry_run {
+                        continue;
+                    }
+                    clone(url, rev.as_deref(), &destination)
+                } else if let Some(rev) = rev {
+                    if !destination.join(".git").exists() {
+                        log!(Warning: "\"{}\" is not a git checkout, it is left as it is", destination.display());
+                        continue;
+                    }
+                    log!(Checkout: "{} in \"{}\"", rev, destination.display());
+                    if dry_run {
+                        continue;
+                    }
+                    checkout(&destination, rev)
+                } else {
+                    continue;
+                };
+
+                if let Err(err) = result {
+                    log!(Error: "Could not get {}: {}", url, err);
+                }
+            }
+            Source::Local(from) => {
+                if !dir_exists(from) {
+                    log!(Error: "\"{}\" does not exist", from.display());
+                    continue;
+                }
+                if destination.exists() {
+                    let same = crate::pin::hash_folder(from).ok()
+                        == crate::pin::hash_folder(&destination).ok();
+                    if same {
+                        continue;
+                    }
+                }
+
+                log!(Copy: "\"{}\" to \"{}\"", from.display(), destination.display());
+                if dry_run {
+                    continue;
+                }
+                if let Err(err) = copy_local(from, &destination) {
+                    log!(Error: "Could not copy \"{}\": {}", from.display(), err);
+                }
+            }
+            Source::Workshop(_) | Source::PackageId(_) => {}
+        }
+    }
+}
+
+/// Moves the mods the modpack does not list to the backup store
+fn prune(pack: &Modpack, installed: &GameMods, args: &ApplyCommandGroup) {
+    let extra: Vec<&Mod> = installed
+        .iter()
+        .filter(|m| !is_ignored(m))
+        .filter(|m| {
+            !Path::new(&m.path)
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with(".rrm-"))
+        })
+        .filter(|m| !pack.mods.iter().any(|e| e.matches(m)))
+        .collect();
+
+    if extra.is_empty() {
+        return;
+    }
+
+    for m in &extra {
+        log!(Backup: "{} \"{}\"", m.name, m.path);
+    }
+    if args.dry_run
+        || !args.yes && !confirm(&format!("Remove {} mods not in the modpack?", extra.len()))
+    {
+        return;
+    }
+
+    for m in extra {
+        let path = Path::new(&m.path);
+        let folder = path.file_name().unwrap().to_string_lossy().to_string();
+        match crate::backup::store(path, &folder) {
+            Ok(backup) => {
+                log!(Removed: "\"{}\", saved at \"{}\"", path.display(), backup.display())
+            }
+            Err(err) => log!(Error: "Could not remove \"{}\": {}", path.display(), err),
+        }
+    }
+}
+
+/// The packageIds to activate, in load order. Core is added first if the modpack leaves it out
+fn load_order(pack: &Modpack, installed: &GameMods) -> Vec<String> {
+    let mut active: Vec<String> = vec![];
+    for entry in &pack.mods {
+        let package_id = match &entry.source {
+            Source::PackageId(package_id) => Some(package_id.clone()),
+            _ => installed
+                .iter()
+                .find(|m| entry.matches(m))
+                .and_then(|m| m.package_id.as_ref())
+                .map(|p| p.trim().to_lowercase()),
+        };
+
+        match package_id {
+            Some(package_id) if !active.contains(&package_id) => active.push(package_id),
+            Some(_) => {}
+            None => log!(Warning: "{} has no packageId, it is left out of ModsConfig.xml", entry),
+        }
+    }
+
+    if !active.iter().any(|p| p == CORE) {
+        active.insert(0, CORE.to_string());
+    }
+    active
+}
+
+/// Checks every mod of the modpack is installed and `ModsConfig.xml` has the load order
+fn verify(pack: &Modpack, game: &GamePath, mods_config: &Path, active: &[String]) -> bool {
+    let installed = GameMods::from(game.clone());
+    let official = official_mods(game.path());
+    let mut ok = true;
+
+    for entry in &pack.mods {
+        let found =
+            installed.iter().any(|m| entry.matches(m)) || official.iter().any(|m| entry.matches(m));
+        if !found {
+            log!(Missing: "{}", entry);
+            ok = false;
+        }
+    }
+
+    let written = std::fs::read_to_string(mods_config)
+        .map(|xml| active_mods(&xml))
+        .unwrap_or_default();
+    if written != active {
+        log!(Error: "\"{}\" does not have the load order of the modpack", mods_config.display());
+        ok = false;
+    }
+
+    ok
+}
+
+pub async fn apply(mut i: Installer, args: ApplyCommandGroup) -> Result<(), ExitCode> {
+    let cwd = rrm_installer::working_dir();
+    let Some(file) = args
+        .file
+        .as_ref()
+        .map(|f| cwd.join(f))
+        .or_else(|| find_modpack(&cwd))
+    else {
+        log!(Error: "No {} found in \"{}\" or above it, pass one with --file", MODPACK_FILE, cwd.display());
+        return Err(ExitCode::FAILURE);
+    };
+
+    let pack = Modpack::load(&file).map_err(|err| {
+        log!(Error: "{}", err);
+        ExitCode::FAILURE
+    })?;
+
+    let game = i.rim_install.clone().unwrap();
+    let mods_dir = game.path().join("Mods");
+    let version = game_version(game.path());
+
+    if let Some(required) = &pack.meta.game_version {
+        match &version {
+            Some(version) if matches_version(required, version) => {}
+            Some(version) => {
+                log!(Error: "The modpack needs RimWorld {}, \"{}\" has {}", required, game.path().display(), version);
+                return Err(ExitCode::FAILURE);
+            }
+            None => {
+                log!(Warning: "Could not read the game version, the modpack needs {}", required)
+            }
+        }
+    }
+
+    let Some(config_dir) = rimworld_config_dir(&i) else {
+        log!(Error: "Could not find the RimWorld Config folder, run the game once or set it with `rrm config set user-data-dir`");
+        return Err(ExitCode::FAILURE);
+    };
+    let mods_config = config_dir.join("ModsConfig.xml");
+
+    log!(Status: "Applying {}", pack.meta.name.as_deref().unwrap_or(&file.display().to_string()));
+    if args.dry_run {
+        log!(Warning: "Dry run, nothing will be changed");
+    }
+
+    let installed = GameMods::from(game.clone());
+    apply_pins(&mut i, &pack, &installed, args.dry_run);
+
+    let missing: Vec<String> = pack
+        .mods
+        .iter()
+        .filter_map(|e| match e.source {
+            Source::Workshop(id) if installed_with_id(&installed, id).is_empty() => {
+                Some(id.to_string())
+            }
+            _ => None,
+        })
+        .collect();
+    if !missing.is_empty() {
+        let mut install_args = InstallCommandGroup::with_ids(missing);
+        install_args.yes = args.yes;
+        install_args.resolve = args.resolve;
+        install_args.dry_run = args.dry_run;
+        // Without them the load order would name mods that are not there
+        if crate::install::install(install_args, i.clone()).await? == Outcome::Declined {
+            log!(Warning: "Stopped, the modpack was not applied");
+            return Err(ExitCode::FAILURE);
+        }
+    }
+
+    apply_sources(&pack, &mods_dir, args.dry_run);
+
+    let installed = GameMods::from(game.clone());
+    if args.prune {
+        prune(&pack, &installed, &args);
+    }
+
+    // Mod settings shipped next to the manifest, as written by `rrm settings export`
+    if let Some(settings) = file.parent().map(|dir| dir.join("settings"))
+        && settings.is_dir()
+    {
+        let files = crate::settings::settings_files(&settings);
+        crate::settings::import(&installed, &config_dir, &files, &settings, args.dry_run)?;
+    }
+
+    // Mods a dry run did not install have no packageId to read yet
+    if args.dry_run {
+        log!(Write: "\"{}\" with the load order of the modpack", mods_config.display());
+        return Ok(());
+    }
+
+    let active = load_order(&pack, &installed);
+    log!(Write: "\"{}\" with {} active mods", mods_config.display(), active.len());
+
+    let existing = std::fs::read_to_string(&mods_config).ok();
+    let xml = mods_config_xml(
+        existing.as_deref(),
+        version.as_deref().unwrap_or(""),
+        &active,
+    );
+    let staged = mods_config.with_extension("xml.rrm-new");
+    if let Err(err) =
+        std::fs::write(&staged, xml).and_then(|_| std::fs::rename(&staged, &mods_config))
+    {
+        log!(Error: "Could not write \"{}\": {}", mods_config.display(), err);
+        return Err(ExitCode::FAILURE);
+    }
+
+    if verify(&pack, &game, &mods_config, &active) {
+        log!(Done: "The instance matches the modpack");
+        Ok(())
+    } else {
+        log!(Error: "The instance does not match the modpack, see the messages above");
+        Err(ExitCode::FAILURE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODPACK: &str = r#"
+        [modpack]
+        name = "Medieval"
+        game-version = "1.5"
+
+        [[mods]]
+        steam-id = 2009463077
+        pin = "hold"
+
+        [[mods]]
+        package-id = "Ludeon.RimWorld"
+
+        [[mods]]
+        git = "https://github.com/someone/SomeMod.git"
+        rev = "v1.2"
+
+        [[mods]]
+        path = "local/MyMod"
+        folder = "my-mod"
+    "#;
+
+    #[test]
+    fn test_parse_modpack() {
+        let pack = Modpack::parse(MODPACK, Path::new("/packs")).unwrap();
+
+        assert_eq!(pack.meta.game_version.as_deref(), Some("1.5"));
+        assert_eq!(pack.mods.len(), 4);
+        assert_eq!(pack.mods[0].source, Source::Workshop(2009463077));
+        assert_eq!(pack.mods[0].pin, Some(PinMode::Hold));
+        assert_eq!(
+            pack.mods[1].source,
+            Source::PackageId("ludeon.rimworld".to_string())
+        );
+        assert_eq!(pack.mods[2].folder().as_deref(), Some("SomeMod"));
+        assert_eq!(
+            pack.mods[3].source,
+            Source::Local(PathBuf::from("/packs/local/MyMod"))
+        );
+        assert_eq!(pack.mods[3].folder().as_deref(), Some("my-mod"));
+
+        assert!(Modpack::parse("[[mods]]\nsteam-id = 1\ngit = \"x\"", Path::new("/")).is_err());
+        assert!(
+            Modpack::parse(
+                "[[mods]]\npackage-id = \"a\"\npin = \"hold\"",
+                Path::new("/")
+            )
+            .is_err()
+        );
+        assert!(Modpack::parse("[[mods]]\nsteamid = 1", Path::new("/")).is_err());
+        assert!(
+            Modpack::parse(
+                "[[mods]]\ngit = \"--upload-pack=touch /tmp/x\"",
+                Path::new("/")
+            )
+            .is_err()
+        );
+        assert!(
+            Modpack::parse(
+                "[[mods]]\ngit = \"https://example.com/a.git\"\nrev = \"-x\"",
+                Path::new("/")
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_matches_version() {
+        assert!(matches_version("1.5", "1.5.4104 rev435"));
+        assert!(matches_version("1.5.4104", "1.5.4104 rev435"));
+        assert!(!matches_version("1.5", "1.50.1 rev1"));
+        assert!(!matches_version("1.4", "1.5.4104 rev435"));
+    }
+
+    #[test]
+    fn test_mods_config_xml() {
+        let existing = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<ModsConfigData>\n  <version>1.5.4104 rev435</version>\n  <activeMods>\n    <li>ludeon.rimworld</li>\n    <li>old.mod</li>\n  </activeMods>\n  <knownExpansions>\n    <li>ludeon.rimworld.royalty</li>\n  </knownExpansions>\n</ModsConfigData>\n";
+        let active = vec!["brrainz.harmony".to_string(), CORE.to_string()];
+
+        let xml = mods_config_xml(Some(existing), "", &active);
+        assert_eq!(active_mods(&xml), active);
+        assert!(xml.contains("<li>ludeon.rimworld.royalty</li>"));
+        assert!(xml.contains("<version>1.5.4104 rev435</version>"));
+
+        let xml = mods_config_xml(None, "1.5.4104 rev435", &active);
+        assert_eq!(active_mods(&xml), active);
+        assert!(xml.contains("<version>1.5.4104 rev435</version>"));
+    }
+}
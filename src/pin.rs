@@ -0,0 +1,156 @@
+use crate::args::PinCommandGroup;
+use crate::snapshot::{hash_file, walk};
+use crate::utils::*;
+use rrm_installer::{Pin, PinMode};
+use sha2::{Digest, Sha256};
+use std::io;
+
+/// Hash of every file and folder name under `path`, used by `--mode hash` pins
+pub fn hash_folder(path: &Path) -> io::Result<String> {
+    let mut files = vec![];
+    walk(path, "", &mut files)?;
+
+    let mut hasher = Sha256::new();
+    for (rel, file) in files {
+        let hash = if file.is_dir() {
+            "-".to_string()
+        } else {
+            hash_file(&file)?
+        };
+        hasher.update(format!("{rel}\t{hash}\n"));
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// The `hold` pin of `id`, if it is installed and so must not be downloaded again
+pub fn held<'a>(pins: &'a [Pin], id: usize, installed: &GameMods) -> Option<&'a Pin> {
+    pins.iter()
+        .find(|p| p.id == id && p.mode == PinMode::Hold)
+        .filter(|_| !installed_with_id(installed, id).is_empty())
+}
+
+/// Whether a `hash` pin turns down the download at `download` because its content changed
+pub fn rejects(pin: &Pin, download: &Path) -> bool {
+    match &pin.mode {
+        PinMode::Hash(hash) => hash_folder(download).map_or(true, |h| &h != hash),
+        PinMode::Hold | PinMode::NoDependency => false,
+    }
+}
+
+/// Finds the Steam ID of `query`, given as the ID, a Workshop URL or the packageId or name of
+/// an installed mod
+fn find(query: &str, installed: &GameMods) -> Option<(usize, Option<Mod>)> {
+    if let Some(id) = steam_id_from(query) {
+        let m = installed_with_id(installed, id)
+            .first()
+            .map(|m| (*m).clone());
+        return Some((id, m));
+    }
+
+    installed
+        .iter()
+        .find(|m| {
+            m.name.eq_ignore_ascii_case(query)
+                || m.package_id
+                    .as_ref()
+                    .is_some_and(|p| p.eq_ignore_ascii_case(query))
+        })
+        .and_then(|m| m.steam_id.parse().ok().map(|id| (id, Some(m.clone()))))
+}
+
+pub fn pin(mut i: Installer, args: PinCommandGroup) {
+    let installed = GameMods::from(i.rim_install.clone().unwrap());
+
+    let Some((id, m)) = find(&args.r#mod, &installed) else {
+        log!(Error: "\"{}\" is not a Steam ID, a Workshop URL or an installed mod with one", args.r#mod);
+        return;
+    };
+
+    let mode = match args.mode.as_str() {
+        "hash" => {
+            let Some(m) = &m else {
+                log!(Error: "{} is not installed, there is no content to pin", id);
+                return;
+            };
+            match hash_folder(Path::new(&m.path)) {
+                Ok(hash) => PinMode::Hash(hash),
+                Err(err) => {
+                    log!(Error: "Could not read \"{}\": {}", m.path, err);
+                    return;
+                }
+            }
+        }
+        "no-dependency" => PinMode::NoDependency,
+        _ => PinMode::Hold,
+    };
+
+    let pin = Pin {
+        id,
+        name: m.map(|m| m.name).unwrap_or_else(|| id.to_string()),
+        mode,
+    };
+
+    log!(Pinned: "{} ({}), {}", pin.name, pin.id, pin.mode);
+    i.add_pin(pin);
+}
+
+pub fn unpin(mut i: Installer, query: String) {
+    let installed = GameMods::from(i.rim_install.clone().unwrap());
+
+    // Pins of mods that are not installed anymore can still be removed by name
+    let id = find(&query, &installed).map(|(id, _)| id).or_else(|| {
+        i.pins
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(&query))
+            .map(|p| p.id)
+    });
+
+    match id.and_then(|id| i.remove_pin(id)) {
+        Some(pin) => log!(Unpinned: "{} ({})", pin.name, pin.id),
+        None => log!(Error: "\"{}\" is not pinned", query),
+    }
+}
+
+pub fn pins(i: Installer) {
+    if i.pins.is_empty() {
+        println!("No pinned mods");
+        return;
+    }
+
+    println!("{:>15}   {:<20}   Name", "Steam ID", "Mode");
+    println!("{:>15}   {:<20}   ----", "--------", "----");
+    for pin in &i.pins {
+        println!(
+            "{:>15}   {:<20}   {}",
+            pin.id,
+            pin.mode.to_string(),
+            pin.name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash_folder;
+    use std::fs;
+
+    #[test]
+    fn test_hash_folder() {
+        let dir = std::env::temp_dir().join(format!("rrm_pin_{}", std::process::id()));
+        fs::create_dir_all(dir.join("About")).unwrap();
+        fs::write(dir.join("About/About.xml"), "<ModMetaData/>").unwrap();
+
+        let before = hash_folder(&dir).unwrap();
+        assert_eq!(before, hash_folder(&dir).unwrap());
+
+        fs::write(dir.join("About/Preview.png"), "").unwrap();
+        assert_ne!(before, hash_folder(&dir).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,566 @@
+use crate::args::LogCommandGroup;
+use crate::utils::*;
+use colored::Colorize;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::process::ExitCode;
+
+/// Namespaces of the game, Unity, .NET and Harmony, which say nothing about the mod at fault
+const GAME_NAMESPACES: [&str; 9] = [
+    "Verse",
+    "RimWorld",
+    "RuntimeAudioClipLoader",
+    "UnityEngine",
+    "Unity",
+    "System",
+    "Mono",
+    "HarmonyLib",
+    "Harmony",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Exception,
+    Xml,
+    DuplicateDef,
+    Error,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Exception => write!(f, "exception"),
+            Kind::Xml => write!(f, "XML error"),
+            Kind::DuplicateDef => write!(f, "duplicate def"),
+            Kind::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// One message of Player.log with the stack trace Unity writes under it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub kind: Kind,
+    pub message: String,
+    pub lines: Vec<String>,
+}
+
+fn classify(message: &str, lines: &[String]) -> Option<Kind> {
+    let trace = |pattern: &str| lines.iter().any(|line| line.contains(pattern));
+
+    if (message.contains(" has multiple ") && message.contains(" named "))
+        || message.contains("Adding duplicate")
+        || message.to_lowercase().contains("duplicate def")
+    {
+        Some(Kind::DuplicateDef)
+    } else if message.starts_with("XML error")
+        || message.contains("Could not find type")
+        || message.starts_with("Config error")
+        || (message.starts_with("Exception") && message.contains(".xml"))
+    {
+        Some(Kind::Xml)
+    } else if message.contains("Exception") || lines.iter().any(|l| l.starts_with("at ")) {
+        Some(Kind::Exception)
+    } else if trace("Verse.Log:Error") || trace("Verse.Log.Error") {
+        Some(Kind::Error)
+    } else {
+        None
+    }
+}
+
+/// Puts the messages of Player.log back together line by line, also while the game writes it.
+/// Unity ends each message with a blank line, after its stack trace
+#[derive(Debug, Default)]
+pub struct Blocks {
+    block: Vec<String>,
+}
+
+impl Blocks {
+    /// The error that `line` completes, if any
+    pub fn push(&mut self, line: &str) -> Option<Entry> {
+        let line = line.trim();
+        if line.is_empty() {
+            return self.finish();
+        }
+        if !line.starts_with("(Filename:") {
+            self.block.push(line.to_string());
+        }
+        None
+    }
+
+    /// The error of the message read so far, once no more lines come
+    pub fn finish(&mut self) -> Option<Entry> {
+        let lines = std::mem::take(&mut self.block);
+        let message = lines.first()?.clone();
+        let kind = classify(&message, &lines)?;
+        Some(Entry {
+            kind,
+            message,
+            lines,
+        })
+    }
+}
+
+/// The errors of a Player.log
+pub fn entries(log: &str) -> Vec<Entry> {
+    let mut blocks = Blocks::default();
+    let mut result: Vec<Entry> = log.lines().filter_map(|line| blocks.push(line)).collect();
+    result.extend(blocks.finish());
+    result
+}
+
+/// The `Assemblies/*.dll` of a mod, also those in version folders like `1.5/Assemblies`
+fn assemblies(dir: &Path, depth: usize) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut result = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if entry.file_name().eq_ignore_ascii_case("Assemblies") {
+            let dlls = std::fs::read_dir(&path).into_iter().flatten().flatten();
+            result.extend(dlls.filter_map(|dll| {
+                let dll = dll.path();
+                let is_dll = dll
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("dll"));
+                is_dll
+                    .then(|| dll.file_stem()?.to_str().map(str::to_string))
+                    .flatten()
+            }));
+        } else if depth > 0 {
+            result.extend(assemblies(&path, depth - 1));
+        }
+    }
+    result
+}
+
+/// The type a stack frame runs, from Mono's `at NS.Type.Method (...)` or Unity's
+/// `NS.Type:Method (...)`
+fn frame_type(line: &str) -> Option<&str> {
+    let frame = line.strip_prefix("at ").unwrap_or(line);
+    if frame.starts_with('(') || frame.starts_with('<') {
+        return None;
+    }
+    let end = frame.find([' ', '(', ':']).unwrap_or(frame.len());
+    let frame = &frame[..end];
+    frame.contains('.').then_some(frame)
+}
+
+/// Finds the mod an error comes from
+pub struct Attribution<'a> {
+    mods: &'a [Mod],
+    /// The mod that ships each assembly
+    assemblies: HashMap<String, usize>,
+    /// The packageId of each mod, as a whole word
+    package_ids: Vec<Option<Regex>>,
+    /// Mod folders in paths the game prints relative to its own folder
+    relative: Regex,
+}
+
+impl<'a> Attribution<'a> {
+    pub fn new(mods: &'a [Mod]) -> Self {
+        let mut by_assembly = HashMap::new();
+        for (index, m) in mods.iter().enumerate() {
+            for assembly in assemblies(Path::new(&m.path), 2) {
+                // Shipped by many mods, and only patches the code of others
+                if assembly != "0Harmony" {
+                    by_assembly.entry(assembly).or_insert(index);
+                }
+            }
+        }
+
+        let package_ids = mods
+            .iter()
+            .map(|m| {
+                let id = m.package_id.as_ref().filter(|id| id.contains('.'))?;
+                Regex::new(&format!(r"(?i)\b{}\b", regex::escape(id))).ok()
+            })
+            .collect();
+
+        Attribution {
+            mods,
+            assemblies: by_assembly,
+            package_ids,
+            relative: Regex::new(r"(?:Mods|294100)/([^/:]+)/").unwrap(),
+        }
+    }
+
+    fn by_path(&self, text: &str) -> Option<usize> {
+        let text = text.replace('\\', "/");
+        self.mods
+            .iter()
+            .position(|m| {
+                // So `Mods/Better` is not found in `Mods/BetterGuns/...`
+                let folder = format!("{}/", m.path.replace('\\', "/").trim_end_matches('/'));
+                text.contains(&folder)
+            })
+            .or_else(|| {
+                self.relative.captures_iter(&text).find_map(|c| {
+                    self.mods.iter().position(|m| {
+                        Path::new(&m.path)
+                            .file_name()
+                            .is_some_and(|folder| folder.to_string_lossy() == c[1])
+                    })
+                })
+            })
+    }
+
+    fn by_name(&self, message: &str) -> Option<usize> {
+        // Like `[Combat Extended] ...` or `Mod Combat Extended has multiple ThingDefs named ...`
+        let name = message
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .map(|(name, _)| name)
+            .or_else(|| {
+                message
+                    .strip_prefix("Mod ")
+                    .and_then(|rest| rest.split_once(" has multiple "))
+                    .map(|(name, _)| name)
+            })?;
+
+        self.mods
+            .iter()
+            .position(|m| m.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn by_package_id(&self, text: &str) -> Option<usize> {
+        self.package_ids
+            .iter()
+            .position(|id| id.as_ref().is_some_and(|id| id.is_match(text)))
+    }
+
+    /// The first frame of the stack trace that runs the code of a mod
+    fn by_frames(&self, lines: &[String]) -> Option<usize> {
+        lines.iter().skip(1).find_map(|line| {
+            let frame = frame_type(line)?;
+            let namespace = frame.split('.').next()?;
+            if GAME_NAMESPACES.contains(&namespace) {
+                return None;
+            }
+
+            self.assemblies
+                .iter()
+                .filter(|(assembly, _)| {
+                    frame == assembly.as_str()
+                        || frame
+                            .strip_prefix(assembly.as_str())
+                            .is_some_and(|rest| rest.starts_with('.'))
+                })
+                .max_by_key(|(assembly, _)| assembly.len())
+                .map(|(_, index)| *index)
+        })
+    }
+
+    /// The mod at fault, from def file paths, names, packageIds and then stack frames
+    pub fn culprit(&self, entry: &Entry) -> Option<&'a Mod> {
+        let text = entry.lines.join("\n");
+        let index = self
+            .by_path(&text)
+            .or_else(|| self.by_name(&entry.message))
+            .or_else(|| self.by_package_id(&text))
+            .or_else(|| self.by_frames(&entry.lines))?;
+        self.mods.get(index)
+    }
+}
+
+/// The errors of each mod, `None` for those that could not be traced to one
+pub fn group<'a>(
+    entries: &'a [Entry],
+    mods: &'a [Mod],
+) -> BTreeMap<Option<String>, Vec<&'a Entry>> {
+    let attribution = Attribution::new(mods);
+    let mut groups: BTreeMap<Option<String>, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        let owner = attribution.culprit(entry).map(|m| m.path.clone());
+        groups.entry(owner).or_default().push(entry);
+    }
+    groups
+}
+
+fn shorten(message: &str) -> String {
+    const MAX: usize = 120;
+    match message.char_indices().nth(MAX) {
+        Some((end, _)) => format!("{}...", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        format!("{count} {word}")
+    } else {
+        format!("{count} {word}s")
+    }
+}
+
+/// Prints the errors of each mod and a summary like `3 mods produced 47 errors`
+pub fn report(entries: &[Entry], mods: &[Mod], verbose: bool) {
+    let groups = group(entries, mods);
+    let name = |path: &Option<String>| match path {
+        Some(path) => mods
+            .iter()
+            .find(|m| &m.path == path)
+            .map(|m| match &m.package_id {
+                Some(id) => format!("{} ({id})", m.name),
+                None => m.name.clone(),
+            })
+            .unwrap_or_default(),
+        None => "Not traced to a mod".to_string(),
+    };
+
+    // The mods with the most errors first, the ones not traced last
+    let mut ordered: Vec<_> = groups.iter().collect();
+    ordered.sort_by_key(|(owner, entries)| (owner.is_none(), std::cmp::Reverse(entries.len())));
+
+    for (owner, entries) in &ordered {
+        println!(
+            "{}   {}",
+            name(owner).bold(),
+            plural(entries.len(), "error")
+        );
+
+        let mut messages: Vec<(Kind, &str, usize, &Entry)> = vec![];
+        for entry in entries.iter() {
+            match messages
+                .iter_mut()
+                .find(|(kind, message, ..)| *kind == entry.kind && *message == entry.message)
+            {
+                Some((_, _, count, _)) => *count += 1,
+                None => messages.push((entry.kind, &entry.message, 1, entry)),
+            }
+        }
+        messages.sort_by_key(|(kind, _, count, _)| (std::cmp::Reverse(*count), *kind));
+
+        for (kind, message, count, entry) in messages {
+            println!("  {:>4}x  {:<13} {}", count, kind, shorten(message));
+            if verbose {
+                for line in entry.lines.iter().skip(1) {
+                    println!("{:23}{line}", "");
+                }
+            }
+        }
+        println!();
+    }
+
+    let traced: usize = groups
+        .iter()
+        .filter(|(owner, _)| owner.is_some())
+        .map(|(_, entries)| entries.len())
+        .sum();
+    let mod_count = groups.keys().filter(|owner| owner.is_some()).count();
+    let untraced = entries.len() - traced;
+
+    match (mod_count, untraced) {
+        (0, 0) => log!(Done: "No errors found"),
+        (0, _) => log!(Done: "{}, none traced to a mod", plural(untraced, "error")),
+        (_, 0) => log!(Done: "{} produced {}", plural(mod_count, "mod"), plural(traced, "error")),
+        _ => {
+            log!(Done: "{} produced {}, {} more not traced to a mod", plural(mod_count, "mod"), plural(traced, "error"), untraced)
+        }
+    }
+}
+
+pub fn log(i: Installer, args: LogCommandGroup) -> Result<(), ExitCode> {
+    let file = match args.file {
+        Some(file) => rrm_installer::working_dir().join(file),
+        None => {
+            let Some(file) = UserDataPath::player_log() else {
+                log!(Error: "Could not find where RimWorld writes Player.log, pass it with --file");
+                return Err(ExitCode::FAILURE);
+            };
+            if args.previous {
+                file.with_file_name("Player-prev.log")
+            } else {
+                file
+            }
+        }
+    };
+
+    let content = std::fs::read(&file).map_err(|err| {
+        log!(Error: "Could not read \"{}\": {}", file.display(), err);
+        ExitCode::FAILURE
+    })?;
+    let content = String::from_utf8_lossy(&content);
+
+    let installed = GameMods::from(i.rim_install.clone().unwrap());
+    let mut entries = entries(&content);
+
+    if let Some(query) = &args.r#mod {
+        let Some(m) = crate::settings::find_mod(query, &installed) else {
+            log!(Error: "\"{}\" is not an installed mod", query);
+            return Err(ExitCode::FAILURE);
+        };
+        let attribution = Attribution::new(&installed);
+        entries.retain(|entry| attribution.culprit(entry).is_some_and(|c| c.path == m.path));
+    }
+
+    report(&entries, &installed, args.verbose);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attribution, Blocks, Entry, Kind, entries, frame_type};
+    use rrm_locals::Mod;
+
+    const LOG: &str = "\
+Mono path[0] = '/games/RimWorld/RimWorldLinux_Data/Managed'
+RimWorld 1.5.4104 rev435
+
+Could not find type named CombatExtended.AmmoSetDef from node <li Class=\"CombatExtended.AmmoSetDef\">
+UnityEngine.StackTraceUtility:ExtractStackTrace ()
+Verse.Log:Error (string)
+
+(Filename: C:\\buildslave\\unity\\build\\Runtime/Export/Debug/Debug.bindings.h Line: 39)
+
+XML error: <damage>5</damage> doesn't correspond to any field in type ThingDef. Context: file /games/RimWorld/Mods/BetterGuns/Defs/Guns.xml
+Verse.Log:Error (string)
+
+Exception ticking Pawn1234: System.NullReferenceException: Object reference not set to an instance of an object
+  at BetterGuns.Patches.Tick_Patch.Postfix (Verse.Pawn pawn) [0x00012] in <abc>:0
+  at (wrapper dynamic-method) Verse.Pawn.Verse.Pawn.Tick_Patch1(Verse.Pawn)
+Verse.Log:Error (string)
+
+Mod Better Guns has multiple ThingDefs named Gun_Rifle. Skipping.
+Verse.Log:Warning (string)
+
+[Better Guns] Could not read its settings
+Verse.Log:Error (string)
+
+Initializing Steam
+";
+
+    fn mods(dir: &std::path::Path) -> Vec<Mod> {
+        vec![Mod {
+            path: dir.join("BetterGuns").display().to_string(),
+            name: "Better Guns".to_string(),
+            author: "Someone".to_string(),
+            steam_id: "123".to_string(),
+            version: None,
+            package_id: Some("someone.betterguns".to_string()),
+            identifier: None,
+            dependencies: None,
+        }]
+    }
+
+    #[test]
+    fn test_entries() {
+        let entries = entries(LOG);
+        let kinds: Vec<Kind> = entries.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                Kind::Xml,
+                Kind::Xml,
+                Kind::Exception,
+                Kind::DuplicateDef,
+                Kind::Error
+            ]
+        );
+        assert!(entries[2].message.starts_with("Exception ticking"));
+        assert!(!entries[0].lines.iter().any(|l| l.starts_with("(Filename:")));
+    }
+
+    #[test]
+    fn test_blocks() {
+        let mut blocks = Blocks::default();
+        let live: Vec<Entry> = LOG.lines().filter_map(|line| blocks.push(line)).collect();
+
+        // Trace frames like `Verse.Log:Error (string)` are never an error of their own
+        assert_eq!(live, entries(LOG));
+        assert!(live.iter().all(|e| !e.message.starts_with("Verse.Log")));
+        assert_eq!(blocks.finish(), None);
+
+        assert!(
+            blocks
+                .push("Exception in a message still being written")
+                .is_none()
+        );
+        assert_eq!(blocks.finish().map(|e| e.kind), Some(Kind::Exception));
+        assert_eq!(blocks.finish(), None);
+    }
+
+    #[test]
+    fn test_frame_type() {
+        assert_eq!(
+            frame_type("at BetterGuns.Patches.Tick_Patch.Postfix (Verse.Pawn pawn)"),
+            Some("BetterGuns.Patches.Tick_Patch.Postfix")
+        );
+        assert_eq!(frame_type("Verse.Log:Error (string)"), Some("Verse.Log"));
+        assert_eq!(
+            frame_type("at (wrapper dynamic-method) Verse.Pawn.Tick"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_culprit() {
+        let dir = std::env::temp_dir().join(format!("rrm_player_log_{}", std::process::id()));
+        let assemblies = dir.join("BetterGuns").join("1.5").join("Assemblies");
+        std::fs::create_dir_all(&assemblies).unwrap();
+        std::fs::write(assemblies.join("BetterGuns.dll"), "").unwrap();
+        std::fs::write(assemblies.join("0Harmony.dll"), "").unwrap();
+
+        let mods = mods(&dir);
+        let attribution = Attribution::new(&mods);
+        let culprits: Vec<Option<&str>> = entries(LOG)
+            .iter()
+            .map(|e| attribution.culprit(e).map(|m| m.name.as_str()))
+            .collect();
+        assert_eq!(
+            culprits,
+            [
+                None,
+                Some("Better Guns"),
+                Some("Better Guns"),
+                Some("Better Guns"),
+                Some("Better Guns")
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_culprit_of_similar_mod() {
+        let dir = std::path::Path::new("/games/RimWorld/Mods");
+        let mods = vec![Mod {
+            path: dir.join("Better").display().to_string(),
+            name: "Better".to_string(),
+            package_id: Some("someone.gun".to_string()),
+            ..mods(dir).remove(0)
+        }];
+        let attribution = Attribution::new(&mods);
+        let culprit = |log: &str| {
+            let entry = entries(log).remove(0);
+            attribution.culprit(&entry).map(|m| m.name.clone())
+        };
+
+        assert_eq!(
+            culprit(
+                "XML error: in /games/RimWorld/Mods/BetterGuns/Defs/Guns.xml\nVerse.Log:Error (string)\n"
+            ),
+            None
+        );
+        assert_eq!(
+            culprit("Could not resolve someone.gunsplus\nVerse.Log:Error (string)\n"),
+            None
+        );
+        assert_eq!(
+            culprit(
+                "XML error: in /games/RimWorld/Mods/Better/Defs/Guns.xml\nVerse.Log:Error (string)\n"
+            ),
+            Some("Better".to_string())
+        );
+        assert_eq!(
+            culprit("Could not resolve Someone.Gun.\nVerse.Log:Error (string)\n"),
+            Some("Better".to_string())
+        );
+    }
+}
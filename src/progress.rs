@@ -0,0 +1,373 @@
+use crate::utils::*;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rrm_scrap::{ModSteamInfo, human_size};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+/// What a line of steamcmd output says about a download
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Started(usize),
+    /// Bytes done and total of the item being downloaded
+    Progress(u64, u64),
+    Finished(usize, u64),
+    Failed(usize, String),
+}
+
+pub fn parse(line: &str) -> Option<Event> {
+    let line = line.trim();
+    let id_after = |prefix: &str| -> Option<usize> {
+        let (_, rest) = line.split_once(prefix)?;
+        rest.split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()
+    };
+
+    if let Some(id) = id_after("Success. Downloaded item ") {
+        let bytes = line
+            .rsplit_once('(')
+            .and_then(|(_, rest)| rest.split_whitespace().next()?.parse().ok())
+            .unwrap_or(0);
+        return Some(Event::Finished(id, bytes));
+    }
+
+    if let Some(id) = id_after("ERROR! Download item ") {
+        let reason = line
+            .rsplit_once('(')
+            .map(|(_, rest)| rest.trim_end_matches(['.', ')']).to_string())
+            .unwrap_or_default();
+        return Some(Event::Failed(id, reason));
+    }
+
+    if let Some(id) = id_after("Downloading item ") {
+        return Some(Event::Started(id));
+    }
+
+    // Like `Update state (0x61) downloading, progress: 45.23 (123456 / 272973)`
+    if line.contains("progress:") {
+        let (_, counts) = line.rsplit_once('(')?;
+        let (done, total) = counts.trim_end_matches(')').split_once('/')?;
+        return Some(Event::Progress(
+            done.trim().parse().ok()?,
+            total.trim().parse().ok()?,
+        ));
+    }
+
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Queued,
+    Downloading,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    id: usize,
+    name: String,
+    total: u64,
+    done: u64,
+    state: State,
+    bar: Option<ProgressBar>,
+}
+
+/// The size of everything under `path`
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|e| match e.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&e.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn item_style() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{msg:28!} [{bar:24}] {bytes} / {total_bytes}  {bytes_per_sec}  ETA {eta}",
+    )
+    .unwrap()
+    .progress_chars("#-")
+}
+
+/// One progress bar per mod being downloaded, driven by steamcmd's output and the size of the
+/// folders it downloads into. Without a terminal each change is logged on its own line instead
+pub struct Progress {
+    items: Vec<Item>,
+    /// steamcmd's `steamapps/workshop` folder
+    workshop_dir: PathBuf,
+    bars: bool,
+    multi: MultiProgress,
+    /// How many items are done and the bytes of all of them, under the bars
+    overall: ProgressBar,
+}
+
+impl Progress {
+    pub fn new(mods: &[ModSteamInfo], sizes: &HashMap<usize, u64>, workshop_dir: PathBuf) -> Self {
+        let bars = std::io::stdout().is_terminal()
+            && std::env::var("TERM").map_or(true, |term| term != "dumb");
+        let multi = MultiProgress::with_draw_target(if bars {
+            ProgressDrawTarget::stdout()
+        } else {
+            ProgressDrawTarget::hidden()
+        });
+        let overall =
+            multi.add(ProgressBar::new(0).with_style(
+                ProgressStyle::with_template("[{msg}] {bytes} / {total_bytes}").unwrap(),
+            ));
+
+        Progress {
+            items: mods
+                .iter()
+                .map(|m| Item {
+                    id: m.id,
+                    name: m.title.clone(),
+                    total: sizes.get(&m.id).copied().unwrap_or(0),
+                    done: 0,
+                    state: State::Queued,
+                    bar: None,
+                })
+                .collect(),
+            workshop_dir,
+            bars,
+            multi,
+            overall,
+        }
+    }
+
+    /// Logs every change on its own line, like when the output is read by another program
+    pub fn without_bars(mut self) -> Self {
+        self.bars = false;
+        self.multi.set_draw_target(ProgressDrawTarget::hidden());
+        self
+    }
+
+    fn position(&self, id: usize) -> Option<usize> {
+        self.items.iter().position(|i| i.id == id)
+    }
+
+    fn count(&self, state: State) -> usize {
+        self.items.iter().filter(|i| i.state == state).count()
+    }
+
+    fn start(&mut self, pos: usize) {
+        let n = self.items.len() - self.count(State::Queued) + 1;
+        let item = &mut self.items[pos];
+        if item.state != State::Queued {
+            return;
+        }
+        item.state = State::Downloading;
+
+        if self.bars {
+            let bar = ProgressBar::new(item.total)
+                .with_style(item_style())
+                .with_message(item.name.clone());
+            item.bar = Some(self.multi.insert_before(&self.overall, bar));
+        } else {
+            let total = self.items.len();
+            let item = &self.items[pos];
+            log!(Status: "[{:0>3}/{:0>3}] Downloading {} ({})", n, total, item.name, item.id);
+        }
+    }
+
+    /// Removes the bar of the item at `pos` once it is done or failed
+    fn end(&mut self, pos: usize, state: State) {
+        let item = &mut self.items[pos];
+        item.state = state;
+        if let Some(bar) = item.bar.take() {
+            bar.finish_and_clear();
+            self.multi.remove(&bar);
+        }
+    }
+
+    /// Updates the bars with a line of steamcmd output
+    pub fn line(&mut self, line: &str) {
+        let Some(event) = parse(line) else {
+            return;
+        };
+
+        match event {
+            Event::Started(id) => {
+                if let Some(pos) = self.position(id) {
+                    self.start(pos);
+                }
+            }
+            Event::Progress(done, total) => {
+                if let Some(item) = self
+                    .items
+                    .iter_mut()
+                    .find(|i| i.state == State::Downloading)
+                {
+                    item.done = done;
+                    item.total = total;
+                }
+            }
+            Event::Finished(id, bytes) => {
+                let Some(pos) = self.position(id) else {
+                    return;
+                };
+                self.start(pos);
+                let item = &mut self.items[pos];
+                item.done = if bytes > 0 { bytes } else { item.total };
+                item.total = item.total.max(item.done);
+                self.end(pos, State::Done);
+
+                let item = &self.items[pos];
+                self.multi.suspend(
+                    || log!(Done: "{} ({}), {}", item.name, item.id, human_size(item.done)),
+                );
+            }
+            Event::Failed(id, reason) => {
+                let Some(pos) = self.position(id) else {
+                    return;
+                };
+                self.end(pos, State::Failed);
+
+                let item = &self.items[pos];
+                self.multi.suspend(
+                    || log!(Error: "Could not download {} ({}): {}", item.name, item.id, reason),
+                );
+            }
+        }
+    }
+
+    /// Reads how much of each item is on disk, in steamcmd's staging folder or already moved
+    pub fn poll(&mut self) {
+        let downloads = self.workshop_dir.join("downloads").join("294100");
+        let content = self.workshop_dir.join("content").join("294100");
+
+        // steamcmd does not always say which item it starts, so the first one growing on disk
+        // is the one being downloaded
+        if self.count(State::Downloading) == 0 {
+            let growing = self.items.iter().position(|i| {
+                i.state == State::Queued
+                    && (downloads.join(i.id.to_string()).exists()
+                        || content.join(i.id.to_string()).exists())
+            });
+            if let Some(pos) = growing {
+                self.start(pos);
+            }
+        }
+
+        for item in self.items.iter_mut() {
+            if item.state != State::Downloading {
+                continue;
+            }
+            let id = item.id.to_string();
+            let on_disk = dir_size(&downloads.join(&id)).max(dir_size(&content.join(&id)));
+            item.done = item.done.max(on_disk);
+            // The Workshop size can be outdated
+            item.total = item.total.max(item.done);
+        }
+    }
+
+    /// Removes the bars from the screen, so something else can be printed. The next `draw`
+    /// brings them back
+    pub fn clear(&mut self) {
+        let _ = self.multi.clear();
+    }
+
+    pub fn draw(&mut self) {
+        if !self.bars {
+            return;
+        }
+
+        for item in &self.items {
+            if let Some(bar) = &item.bar {
+                bar.set_length(item.total);
+                bar.set_position(item.done);
+            }
+        }
+
+        let done = self.count(State::Done) + self.count(State::Failed);
+        self.overall
+            .set_message(format!("{:0>3}/{:0>3}", done, self.items.len()));
+        self.overall
+            .set_length(self.items.iter().map(|i| i.total).sum());
+        self.overall
+            .set_position(self.items.iter().map(|i| i.done.min(i.total)).sum());
+    }
+
+    /// Removes the bars once steamcmd is done
+    pub fn finish(&mut self) {
+        for item in &mut self.items {
+            if let Some(bar) = item.bar.take() {
+                bar.finish_and_clear();
+            }
+        }
+        self.overall.finish_and_clear();
+        let _ = self.multi.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Progress, parse};
+    use rrm_scrap::ModSteamInfo;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("Downloading item 2009463077 ..."),
+            Some(Event::Started(2009463077))
+        );
+        assert_eq!(
+            parse(
+                "Success. Downloaded item 2009463077 to \"/tmp/Steam/steamapps/workshop/content/294100/2009463077\" (1953488 bytes) "
+            ),
+            Some(Event::Finished(2009463077, 1953488))
+        );
+        assert_eq!(
+            parse("ERROR! Download item 818773962 failed (Timeout)."),
+            Some(Event::Failed(818773962, "Timeout".to_string()))
+        );
+        assert_eq!(
+            parse(" Update state (0x61) downloading, progress: 45.23 (123456 / 272973)"),
+            Some(Event::Progress(123456, 272973))
+        );
+        assert_eq!(parse("Waiting for client config...OK"), None);
+    }
+
+    #[test]
+    fn test_progress() {
+        let mods = vec![
+            ModSteamInfo {
+                id: 1,
+                title: "Harmony".to_string(),
+                ..Default::default()
+            },
+            ModSteamInfo {
+                id: 2,
+                title: "HugsLib".to_string(),
+                ..Default::default()
+            },
+        ];
+        let sizes = HashMap::from([(1, 100), (2, 300)]);
+        let dir = std::env::temp_dir().join(format!("rrm_progress_{}", std::process::id()));
+        let mut progress = Progress::new(&mods, &sizes, dir.clone()).without_bars();
+
+        // The staging folder of the first item appears before steamcmd names it
+        std::fs::create_dir_all(dir.join("downloads/294100/1")).unwrap();
+        std::fs::write(dir.join("downloads/294100/1/About.xml"), [0; 40]).unwrap();
+        progress.poll();
+        assert_eq!(progress.items[0].done, 40);
+        assert_eq!(progress.items[0].total, 100);
+
+        progress.line("Success. Downloaded item 1 to \"x\" (100 bytes)");
+        progress.line("ERROR! Download item 2 failed (Failure).");
+        assert_eq!(progress.count(super::State::Done), 1);
+        assert_eq!(progress.count(super::State::Failed), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,18 +1,59 @@
-use crate::args::{InstallCommandGroup, InstallingOptions, Pull};
+use crate::args::{InstallCommandGroup, InstallingOptions, PullCommandGroup};
 use crate::install::install;
 use crate::utils::Path;
 use crate::utils::*;
+use rrm_installer::{Pin, PinMode};
 use std::collections::HashSet;
+use std::process::ExitCode;
 
-pub async fn pull(args: Pull, i: Installer, ignored: bool) {
+/// Written once the mods in `_` folders were pinned, so a later `rrm unpin` is not undone
+const UNDERSCORE_PINS_MARKER: &str = "underscore-pins";
+
+/// Pull used to skip the mods in folders named with the `_` prefix. The first pull that does
+/// not holds them with a pin instead, before anything is updated
+fn pin_underscored(i: &mut Installer, mods: &GameMods, dry_run: bool) {
+    let marker = rrm_installer::get_or_create_data_dir().join(UNDERSCORE_PINS_MARKER);
+    if marker.exists() {
+        return;
+    }
+
+    for m in mods.iter().filter(|m| is_ignored(m)) {
+        let Ok(id) = m.steam_id.parse() else {
+            continue;
+        };
+        if i.pin_of(id).is_some() {
+            continue;
+        }
+
+        let pin = Pin {
+            id,
+            name: m.name.clone(),
+            mode: PinMode::Hold,
+        };
+        log!(Pinned: "{} ({}), {}, as its folder \"{}\" was skipped for the `_` prefix", pin.name, pin.id, pin.mode, m.path);
+        // A dry run plans with the pin without saving it
+        if dry_run {
+            i.pins.push(pin);
+        } else {
+            i.add_pin(pin);
+        }
+    }
+
+    if !dry_run && let Err(err) = std::fs::write(&marker, "") {
+        log!(Warning: "Could not write \"{}\": {}", marker.display(), err);
+    }
+}
+
+pub async fn pull(args: PullCommandGroup, mut i: Installer, ignored: bool) -> Result<(), ExitCode> {
     let mods: GameMods =
         GameMods::from(i.rim_install.clone().unwrap()).with_display(DisplayType::Short);
+    pin_underscored(&mut i, &mods, args.dry_run);
 
     if args.is_verbose() {
         println!("Listing installed ids: ");
     }
 
-    let ids = mods.iter().filter_map(|mo| {
+    let ids = mods.iter().map(|mo| {
         if args.is_debug() {
             println!(
                 "Turn of {}",
@@ -20,43 +61,23 @@ pub async fn pull(args: Pull, i: Installer, ignored: bool) {
             );
         }
 
-        if Path::new(&mo.path)
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
-            .starts_with('_')
-            && !ignored
-        {
-            if args.is_verbose() {
-                println!("Ignoring {}", mo.steam_id);
-            }
-            None
-        } else {
-            if args.is_verbose() {
-                println!("Adding {}", mo.steam_id);
-            }
-            Some(mo.steam_id.clone())
+        if args.is_verbose() {
+            println!("Adding {}", mo.steam_id);
         }
+        mo.steam_id.clone()
     });
 
     let to_install: HashSet<String> = HashSet::from_iter(ids);
     let ids: Vec<String> = to_install.iter().cloned().collect();
 
     let to_install = InstallCommandGroup {
-        rimmod: ids,
-        filter: None,
-        author: false,
-        version: false,
-        steam_id: false,
-        name: false,
-        all: false,
-        yes: true,
         resolve: args.resolve,
+        dry_run: args.dry_run,
+        ignore_pins: ignored,
         verbose: args.verbose,
         debug: args.debug,
+        ..InstallCommandGroup::with_ids(ids)
     };
 
-    install(to_install, i, 0, HashSet::new()).await;
+    install(to_install, i).await.map(|_| ())
 }
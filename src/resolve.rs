@@ -0,0 +1,250 @@
+use crate::args::InstallingOptions;
+use crate::utils::*;
+use rrm_installer::{Pin, PinMode};
+use rrm_scrap::{
+    ModSteamDetails, ModSteamInfo, RequiredItem, Workshop, WorkshopClient, WorkshopError,
+};
+use std::collections::{HashMap, HashSet};
+
+/// RimWorld itself, which some mods list as a dependency
+const RIMWORLD_ID: usize = 294100;
+
+/// Everything an install will download, worked out before spawning SteamCMD
+#[derive(Default, Debug)]
+pub struct InstallPlan {
+    /// Mods asked for by the user
+    pub requested: Vec<ModSteamInfo>,
+    /// Mods required by the requested ones (or by other dependencies) that are not installed
+    pub dependencies: Vec<ModSteamInfo>,
+    /// Title of the mod that pulled in each dependency
+    pub required_by: HashMap<usize, String>,
+    /// Required items that are not installed and will not be, because resolving is off
+    pub missing: Vec<(RequiredItem, String)>,
+    /// Required items left out because they are pinned with `no-dependency`
+    pub pinned: Vec<(RequiredItem, String)>,
+    pub download_size: u64,
+    /// The Workshop file size of each mod, by its ID
+    pub sizes: HashMap<usize, u64>,
+}
+
+impl InstallPlan {
+    pub fn mods(&self) -> Vec<ModSteamInfo> {
+        self.requested
+            .iter()
+            .chain(self.dependencies.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.requested.len() + self.dependencies.len()
+    }
+
+    pub fn gen_display(&self) -> String {
+        let mut result = format!(
+            "Will install {} mod{}, {} as dependencies ({})\n",
+            self.len(),
+            if self.len() == 1 { "" } else { "s" },
+            self.dependencies.len(),
+            rrm_scrap::human_size(self.download_size)
+        );
+
+        self.requested.iter().for_each(|m| {
+            result.push_str(&format!("{:>15}   {}\n", m.id, m.title));
+        });
+
+        self.dependencies.iter().for_each(|m| {
+            result.push_str(&format!(
+                "{:>15}   {} (required by {})\n",
+                m.id,
+                m.title,
+                self.required_by
+                    .get(&m.id)
+                    .map(String::as_str)
+                    .unwrap_or("?")
+            ));
+        });
+
+        if !self.missing.is_empty() {
+            result.push_str("Not installed, use --resolve to add them:\n");
+            self.missing.iter().for_each(|(item, by)| {
+                result.push_str(&format!(
+                    "{:>15}   {} (required by {})\n",
+                    item.id, item.title, by
+                ));
+            });
+        }
+
+        if !self.pinned.is_empty() {
+            result.push_str("Not installed, pinned with no-dependency:\n");
+            self.pinned.iter().for_each(|(item, by)| {
+                result.push_str(&format!(
+                    "{:>15}   {} (required by {})\n",
+                    item.id, item.title, by
+                ));
+            });
+        }
+
+        result
+    }
+
+    pub fn display(&self) {
+        print!("{}", self.gen_display())
+    }
+}
+
+/// Resolves the full dependency closure of `requested` from the Workshop "Required items" and
+/// the About.xml of the copies already in `installed`, without downloading anything
+pub async fn plan<T: InstallingOptions>(
+    workshop: &Workshop,
+    args: &T,
+    resolve: bool,
+    requested: Vec<ModSteamInfo>,
+    installed: &GameMods,
+    pins: &[Pin],
+) -> InstallPlan {
+    let installed: HashMap<usize, &Mod> = installed
+        .iter()
+        .filter_map(|m| m.steam_id.parse().ok().map(|id| (id, m)))
+        .collect();
+
+    let mut plan = InstallPlan {
+        requested,
+        ..Default::default()
+    };
+
+    let mut seen: HashSet<usize> = plan.requested.iter().map(|m| m.id).collect();
+    let mut frontier: Vec<usize> = seen.iter().copied().collect();
+    let mut details: HashMap<usize, ModSteamDetails> = HashMap::new();
+
+    while !frontier.is_empty() {
+        if args.is_verbose() {
+            log!(Status: "Reading Workshop metadata of {} mod(s)", frontier.len());
+        }
+
+        // The required items are only on the pages, which are read even without `resolve` so
+        // that missing dependencies are still reported
+        let fetched = match workshop.details(&frontier, true).await {
+            Ok(fetched) => fetched,
+            Err(err @ WorkshopError::NotCached(_)) => {
+                log!(Warning: "{}", err);
+                log!(Warning: "Using the About.xml of the installed mods instead");
+                frontier
+                    .iter()
+                    .filter_map(|id| installed.get(id).map(|m| local_details(m, *id)))
+                    .collect()
+            }
+            Err(err) => {
+                log!(Error: "{}", err);
+                std::process::exit(1);
+            }
+        };
+        for id in frontier
+            .iter()
+            .filter(|id| !fetched.iter().any(|d| d.id == **id))
+        {
+            log!(Warning: "Could not find Workshop item {}", id);
+        }
+
+        let mut next = vec![];
+        for item in fetched {
+            let mut required = item.required_items.clone();
+            if let Some(local) = installed.get(&item.id)
+                && let Some(deps) = &local.dependencies
+            {
+                deps.iter()
+                    .filter_map(|dep| dep.parse().ok())
+                    .for_each(|id| {
+                        if !required.iter().any(|r| r.id == id) {
+                            required.push(RequiredItem {
+                                id,
+                                title: id.to_string(),
+                            });
+                        }
+                    });
+            }
+
+            for r in required {
+                if r.id == RIMWORLD_ID || seen.contains(&r.id) || installed.contains_key(&r.id) {
+                    continue;
+                }
+                seen.insert(r.id);
+
+                if pins
+                    .iter()
+                    .any(|p| p.id == r.id && p.mode == PinMode::NoDependency)
+                {
+                    plan.pinned.push((r, item.title.clone()));
+                    continue;
+                }
+
+                if !resolve {
+                    plan.missing.push((r, item.title.clone()));
+                    continue;
+                }
+
+                if args.is_verbose() {
+                    log!(Status: "{} requires {} ({})", item.title, r.title, r.id);
+                }
+                plan.required_by.insert(r.id, item.title.clone());
+                plan.dependencies.push(ModSteamInfo {
+                    id: r.id,
+                    title: r.title,
+                    description: "".to_string(),
+                    author: "".to_string(),
+                });
+                next.push(r.id);
+            }
+
+            details.insert(item.id, item);
+        }
+
+        frontier = next;
+    }
+
+    for m in plan
+        .requested
+        .iter_mut()
+        .chain(plan.dependencies.iter_mut())
+    {
+        if let Some(d) = details.get(&m.id) {
+            if m.title.is_empty() || m.title == m.id.to_string() {
+                m.title = d.title.clone();
+            }
+            if m.author.is_empty() {
+                m.author = d.author.clone();
+            }
+            plan.download_size += d.file_size;
+            plan.sizes.insert(m.id, d.file_size);
+        }
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstallPlan;
+    use rrm_scrap::ModSteamInfo;
+
+    #[test]
+    fn test_plan_display() {
+        let m = |id: usize, title: &str| ModSteamInfo {
+            id,
+            title: title.to_string(),
+            ..Default::default()
+        };
+
+        let mut plan = InstallPlan {
+            requested: vec![m(1, "Combat Extended"), m(2, "Vanilla Expanded")],
+            dependencies: vec![m(3, "Harmony")],
+            download_size: 2048,
+            ..Default::default()
+        };
+        plan.required_by.insert(3, "Combat Extended".to_string());
+
+        let display = plan.gen_display();
+        assert!(display.starts_with("Will install 3 mods, 1 as dependencies (2.0 KB)\n"));
+        assert!(display.contains("              3   Harmony (required by Combat Extended)\n"));
+    }
+}
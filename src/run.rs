@@ -0,0 +1,330 @@
+use crate::args::{ApplyCommandGroup, RunCommandGroup};
+use crate::modpack::active_mods;
+use crate::player_log::{Blocks, Entry};
+use crate::utils::*;
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom};
+use std::process::{Command, ExitCode, Stdio};
+use std::time::{Duration, SystemTime};
+
+const SAVE_DATA_FOLDER: &str = "-savedatafolder=";
+
+/// The executable of the RimWorld installed at `game_path`
+pub fn game_executable(game_path: &Path) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    let candidates = ["RimWorldLinux"];
+
+    #[cfg(target_os = "windows")]
+    let candidates = ["RimWorldWin64.exe"];
+
+    #[cfg(target_os = "macos")]
+    let candidates = [
+        "RimWorldMac.app/Contents/MacOS/RimWorldMac",
+        "RimWorld.app/Contents/MacOS/RimWorld",
+    ];
+
+    candidates
+        .iter()
+        .map(|c| game_path.join(c))
+        .find(|exe| exe.is_file())
+}
+
+/// The options the game is started with: those of `launch-args`, then the save data folder,
+/// which replaces the one of `launch-args`, `-quicktest` and the extra ones
+fn compose_args(
+    configured: Vec<String>,
+    save_data: Option<&Path>,
+    quicktest: bool,
+    extra: &[String],
+) -> Vec<String> {
+    let mut args: Vec<String> = configured
+        .into_iter()
+        .filter(|arg| save_data.is_none() || !arg.starts_with(SAVE_DATA_FOLDER))
+        .collect();
+
+    if let Some(folder) = save_data {
+        args.push(format!("{SAVE_DATA_FOLDER}{}", folder.display()));
+    }
+    if quicktest && !args.iter().any(|arg| arg == "-quicktest") {
+        args.push("-quicktest".to_string());
+    }
+    args.extend(extra.iter().cloned());
+    args
+}
+
+/// Follows the Player.log written by one run of the game
+struct LogTail {
+    path: PathBuf,
+    /// Its size and modification time before the game started, to tell when it is rewritten
+    before: Option<(u64, SystemTime)>,
+    fresh: bool,
+    offset: u64,
+    pending: String,
+}
+
+fn stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+impl LogTail {
+    fn new(path: PathBuf) -> LogTail {
+        LogTail {
+            before: stamp(&path),
+            path,
+            fresh: false,
+            offset: 0,
+            pending: String::new(),
+        }
+    }
+
+    /// The lines written since the last call
+    fn read(&mut self) -> Vec<String> {
+        let Some(now) = stamp(&self.path) else {
+            return vec![];
+        };
+        // The game moves the log of its last run to Player-prev.log when it starts
+        if !self.fresh {
+            if self.before == Some(now) {
+                return vec![];
+            }
+            self.fresh = true;
+        }
+        if now.0 < self.offset {
+            self.offset = 0;
+            self.pending.clear();
+        }
+
+        let mut bytes = vec![];
+        let read = std::fs::File::open(&self.path).and_then(|mut file| {
+            file.seek(SeekFrom::Start(self.offset))?;
+            file.read_to_end(&mut bytes)
+        });
+        if read.is_err() {
+            return vec![];
+        }
+        self.offset += bytes.len() as u64;
+        self.pending.push_str(&String::from_utf8_lossy(&bytes));
+
+        let Some(end) = self.pending.rfind('\n') else {
+            return vec![];
+        };
+        let lines = self.pending[..end]
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect();
+        self.pending.drain(..=end);
+        lines
+    }
+}
+
+/// Keeps the game out of rrm's process group, so Ctrl-C only stops following its log
+fn detach(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x200;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+}
+
+pub async fn run(mut i: Installer, args: RunCommandGroup, wait: bool) -> Result<(), ExitCode> {
+    let game = i.rim_install.clone().unwrap();
+    let Some(exe) = game_executable(game.path()) else {
+        log!(Error: "Could not find the RimWorld executable in \"{}\"", game.path().display());
+        return Err(ExitCode::FAILURE);
+    };
+
+    // The folder asked for becomes the user data folder, so a profile is applied to it
+    let save_data = match &args.save_data_folder {
+        Some(folder) => {
+            let folder = rrm_installer::working_dir().join(folder);
+            if let Err(err) = std::fs::create_dir_all(folder.join("Config")) {
+                log!(Error: "Could not create \"{}\": {}", folder.display(), err);
+                return Err(ExitCode::FAILURE);
+            }
+            let data = UserDataPath::create(&folder).map_err(|err| {
+                log!(Error: "{}", err);
+                ExitCode::FAILURE
+            })?;
+            match &mut i.instance {
+                Some(instance) => instance.user_data_dir = Some(data.clone()),
+                None => i.user_data_dir = Some(data.clone()),
+            }
+            Some(data)
+        }
+        // The folder of the instance, which the game only uses when told
+        None => i.user_data().filter(|data| {
+            UserDataPath::default_location().as_deref() != Some(data.path())
+                && UserDataPath::from_launch_args(
+                    i.launch_args().iter().map(String::as_str),
+                    game.path(),
+                )
+                .is_none()
+        }),
+    };
+
+    let lock = crate::lock::lock_instance(&i, wait).await?;
+    if let Some(profile) = args.profile {
+        let apply = ApplyCommandGroup {
+            file: Some(profile),
+            prune: args.prune,
+            resolve: args.resolve,
+            dry_run: false,
+            yes: args.yes,
+        };
+        crate::modpack::apply(i.clone(), apply).await?;
+    } else if let Some(mods_config) = i.user_data().map(|data| data.mods_config())
+        && let Ok(xml) = std::fs::read_to_string(&mods_config)
+    {
+        log!(Status: "Using the {} active mods of \"{}\"", active_mods(&xml).len(), mods_config.display());
+    }
+    drop(lock);
+
+    let launch_args = compose_args(
+        i.launch_args(),
+        save_data.as_ref().map(UserDataPath::path),
+        args.quicktest,
+        &args.extra,
+    );
+    let mut tail = UserDataPath::player_log().map(LogTail::new);
+
+    let mut command = Command::new(&exe);
+    command
+        .args(&launch_args)
+        .current_dir(game.path())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    detach(&mut command);
+
+    let mut child = command.spawn().map_err(|err| {
+        log!(Error: "Could not start \"{}\": {}", exe.display(), err);
+        ExitCode::FAILURE
+    })?;
+    log!(Started: "RimWorld (PID {}) {}", child.id(), launch_args.join(" "));
+
+    if args.detach {
+        return Ok(());
+    }
+    let Some(tail) = tail.as_mut() else {
+        log!(Warning: "Could not find Player.log, its errors will not be shown");
+        return Ok(());
+    };
+    log!(Following: "\"{}\" for errors, Ctrl-C stops following and leaves the game running", tail.path.display());
+
+    // Each message once, the summary at the end counts them
+    let mut seen = HashSet::new();
+    let mut show = |entry: Entry| {
+        if seen.insert(entry.message.clone()) {
+            log!(Error: "{}", entry.message);
+        }
+    };
+
+    let mut blocks = Blocks::default();
+    let mut interval = tokio::time::interval(Duration::from_millis(500));
+    let status = loop {
+        interval.tick().await;
+        let exited = child.try_wait().ok().flatten();
+
+        for line in tail.read() {
+            if let Some(entry) = blocks.push(&line) {
+                show(entry);
+            }
+        }
+        if let Some(status) = exited {
+            if let Some(entry) = blocks.finish() {
+                show(entry);
+            }
+            break status;
+        }
+    };
+
+    if !status.success() {
+        log!(Warning: "RimWorld exited with {}", status);
+    }
+    println!();
+    let log = std::fs::read(&tail.path).unwrap_or_default();
+    let installed = GameMods::from(game);
+    crate::player_log::report(
+        &crate::player_log::entries(&String::from_utf8_lossy(&log)),
+        &installed,
+        false,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LogTail, compose_args, game_executable};
+    use std::io::Write;
+    use std::path::Path;
+
+    #[test]
+    fn test_compose_args() {
+        let configured = vec!["-savedatafolder=Old".to_string(), "-logfile".to_string()];
+        assert_eq!(
+            compose_args(
+                configured.clone(),
+                Some(Path::new("/data/new")),
+                true,
+                &["-popupwindow".to_string()]
+            ),
+            [
+                "-logfile",
+                "-savedatafolder=/data/new",
+                "-quicktest",
+                "-popupwindow"
+            ]
+        );
+        assert_eq!(
+            compose_args(configured.clone(), None, false, &[]),
+            configured
+        );
+    }
+
+    #[test]
+    fn test_game_executable() {
+        let dir = std::env::temp_dir().join(format!("rrm_run_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(game_executable(&dir), None);
+
+        #[cfg(target_os = "linux")]
+        let exe = dir.join("RimWorldLinux");
+        #[cfg(target_os = "windows")]
+        let exe = dir.join("RimWorldWin64.exe");
+        #[cfg(target_os = "macos")]
+        let exe = dir.join("RimWorldMac.app/Contents/MacOS/RimWorldMac");
+
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        std::fs::write(&exe, "").unwrap();
+        assert_eq!(game_executable(&dir), Some(exe));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_log_tail() {
+        let dir = std::env::temp_dir().join(format!("rrm_tail_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("Player.log");
+        std::fs::write(&log, "last run\n").unwrap();
+
+        let mut tail = LogTail::new(log.clone());
+        assert!(tail.read().is_empty());
+
+        std::fs::write(&log, "first\nse").unwrap();
+        assert_eq!(tail.read(), ["first"]);
+        let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"cond\r\nthird\n").unwrap();
+        assert_eq!(tail.read(), ["second", "third"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
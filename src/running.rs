@@ -0,0 +1,208 @@
+use crate::utils::*;
+
+/// Whether a process with this PID is still running
+pub fn is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // 0 and negative PIDs would address groups of processes
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        if pid <= 0 {
+            return false;
+        }
+        // Signal 0 only checks the process exists, EPERM means it belongs to someone else
+        let result = unsafe { libc::kill(pid, 0) };
+        result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    #[cfg(windows)]
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
+
+/// A running RimWorld
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub pid: Option<u32>,
+    /// Its executable, when the system tells
+    pub exe: Option<PathBuf>,
+}
+
+impl Game {
+    /// Whether it was started from `game_path`. Without its executable it could be
+    pub fn runs_from(&self, game_path: &Path) -> bool {
+        let Some(exe) = &self.exe else {
+            return true;
+        };
+
+        exe.starts_with(game_path)
+            || game_path
+                .canonicalize()
+                .is_ok_and(|game_path| exe.starts_with(game_path))
+    }
+}
+
+impl std::fmt::Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pid {
+            Some(pid) => write!(f, "RimWorld (PID {pid})"),
+            None => write!(f, "RimWorld"),
+        }
+    }
+}
+
+/// Like `RimWorldLinux`, `RimWorldMac` or `RimWorldWin64.exe`
+fn is_rimworld(name: &str) -> bool {
+    name.starts_with("RimWorld")
+}
+
+/// Reads the output of `ps -axo pid=,comm=`, where `comm` is the path of the executable
+#[cfg(any(target_os = "macos", test))]
+fn parse_ps(output: &str) -> Vec<Game> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (pid, exe) = line.trim().split_once(' ')?;
+            let exe = PathBuf::from(exe.trim());
+            is_rimworld(exe.file_name()?.to_str()?).then(|| Game {
+                pid: pid.parse().ok(),
+                exe: exe.is_absolute().then_some(exe),
+            })
+        })
+        .collect()
+}
+
+/// Every RimWorld running. `player_log` is checked on Windows, where the game keeps it locked
+#[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+pub fn rimworld(player_log: Option<&Path>) -> Vec<Game> {
+    #[cfg(target_os = "linux")]
+    let games = std::fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let comm = std::fs::read_to_string(entry.path().join("comm")).ok()?;
+            if !is_rimworld(comm.trim()) {
+                return None;
+            }
+
+            // Under Proton the executable is Wine's, which says nothing about the install
+            let exe = std::fs::read_link(entry.path().join("exe"))
+                .ok()
+                .filter(|exe| {
+                    exe.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(is_rimworld)
+                });
+            Some(Game {
+                pid: Some(pid),
+                exe,
+            })
+        })
+        .collect();
+
+    #[cfg(target_os = "macos")]
+    let games = std::process::Command::new("ps")
+        .args(["-axo", "pid=,comm="])
+        .output()
+        .map(|out| parse_ps(&String::from_utf8_lossy(&out.stdout)))
+        .unwrap_or_default();
+
+    #[cfg(target_os = "windows")]
+    let games = {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        let mut games: Vec<Game> = std::process::Command::new("tasklist")
+            .args(["/FO", "CSV", "/NH", "/FI", "IMAGENAME eq RimWorldWin64.exe"])
+            .output()
+            .map(|out| {
+                String::from_utf8_lossy(&out.stdout)
+                    .lines()
+                    .filter_map(|line| {
+                        let pid = line.split(',').nth(1)?.trim_matches('"').parse().ok()?;
+                        Some(Game {
+                            pid: Some(pid),
+                            exe: None,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Opening it without sharing fails while the game writes to it
+        let locked = player_log.is_some_and(|log| {
+            log.is_file()
+                && std::fs::OpenOptions::new()
+                    .read(true)
+                    .share_mode(0)
+                    .open(log)
+                    .is_err()
+        });
+        if games.is_empty() && locked {
+            games.push(Game {
+                pid: None,
+                exe: None,
+            });
+        }
+        games
+    };
+
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Game, is_running, parse_ps};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_is_running() {
+        assert!(is_running(std::process::id()));
+        assert!(!is_running(i32::MAX as u32));
+        assert!(!is_running(0));
+    }
+
+    #[test]
+    fn test_parse_ps() {
+        let output = "    1 /sbin/launchd\n\
+            \x20 812 /Applications/RimWorld.app/Contents/MacOS/RimWorldMac\n\
+            \x20 990 RimWorldMac\n";
+
+        assert_eq!(
+            parse_ps(output),
+            vec![
+                Game {
+                    pid: Some(812),
+                    exe: Some(PathBuf::from(
+                        "/Applications/RimWorld.app/Contents/MacOS/RimWorldMac"
+                    )),
+                },
+                Game {
+                    pid: Some(990),
+                    exe: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runs_from() {
+        let game = Game {
+            pid: Some(812),
+            exe: Some(PathBuf::from("/games/stable/RimWorldLinux")),
+        };
+        assert!(game.runs_from(Path::new("/games/stable")));
+        assert!(!game.runs_from(Path::new("/games/beta")));
+
+        let unknown = Game {
+            pid: None,
+            exe: None,
+        };
+        assert!(unknown.runs_from(Path::new("/games/beta")));
+    }
+}
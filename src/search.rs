@@ -1,6 +1,7 @@
-use crate::args::{Local, Steam};
+use crate::args::{LocalCommandGroup, SteamCommandGroup};
 use crate::search_in_steam;
 use crate::utils::*;
+use rrm_scrap::{ModSteamInfo, WorkshopClient, WorkshopError};
 
 #[macro_export]
 macro_rules! display_search {
@@ -17,7 +18,7 @@ macro_rules! display_search {
     };
 }
 
-pub fn search_locally(i: Installer, args: Local) {
+pub fn search_locally(i: Installer, args: LocalCommandGroup) {
     use rrm_locals::Filtrable;
 
     let d_type = rrm_locals::DisplayType::from(args.display.large);
@@ -28,12 +29,41 @@ pub fn search_locally(i: Installer, args: Local) {
     display_search!(filtered, args, i);
 }
 
-pub async fn search_steam(i: Installer, args: Steam) {
+/// Installed Workshop mods whose name contains `query`, for searching offline
+fn installed_matching(i: &Installer, query: &str) -> Vec<ModSteamInfo> {
+    let query = query.to_lowercase();
+    GameMods::from(i.rim_install.clone().unwrap())
+        .iter()
+        .filter(|m| m.name.to_lowercase().contains(&query))
+        .filter_map(|m| {
+            Some(ModSteamInfo {
+                id: m.steam_id.parse().ok()?,
+                title: m.name.clone(),
+                description: "".to_string(),
+                author: m.author.clone(),
+            })
+        })
+        .collect()
+}
+
+pub async fn search_steam(i: Installer, args: SteamCommandGroup) {
     use rrm_scrap::Filtrable;
 
-    let mods = SteamMods::search(&args.r#mod)
-        .await
-        .with_display(rrm_locals::DisplayType::from(args.display.large));
+    let found = match i.workshop().search(&args.r#mod).await {
+        Ok(found) => found,
+        Err(err @ WorkshopError::NotCached(_)) => {
+            log!(Warning: "{}", err);
+            log!(Warning: "Showing the installed mods instead");
+            installed_matching(&i, &args.r#mod)
+        }
+        Err(err) => {
+            log!(Error: "{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mods =
+        SteamMods::from_mods(found).with_display(rrm_locals::DisplayType::from(args.display.large));
 
     let mods = search_in_steam!(args, mods);
 
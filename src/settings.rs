@@ -0,0 +1,484 @@
+use crate::args::Settings;
+use crate::utils::*;
+use std::io;
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A `Config/Mod_<identifier>_<class>.xml` file, holding the settings one mod class saves
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsFile {
+    pub file: String,
+    /// The packageId of the mod, or the name of its folder in `Mods/` for older mods
+    pub identifier: String,
+    pub class: String,
+}
+
+impl SettingsFile {
+    pub fn parse(file: &str) -> Option<SettingsFile> {
+        let (identifier, class) = file
+            .strip_prefix("Mod_")?
+            .strip_suffix(".xml")?
+            .rsplit_once('_')?;
+
+        (!identifier.is_empty() && !class.is_empty()).then(|| SettingsFile {
+            file: file.to_string(),
+            identifier: identifier.to_string(),
+            class: class.to_string(),
+        })
+    }
+
+    pub fn belongs_to(&self, m: &Mod) -> bool {
+        m.package_id
+            .as_ref()
+            .is_some_and(|p| p.eq_ignore_ascii_case(&self.identifier))
+            || Path::new(&m.path)
+                .file_name()
+                .is_some_and(|folder| folder.to_string_lossy() == self.identifier)
+            || m.steam_id == self.identifier
+    }
+}
+
+/// Every settings file in `dir`, sorted by name
+pub fn settings_files(dir: &Path) -> Vec<SettingsFile> {
+    let Ok(entries) = dir.read_dir() else {
+        return vec![];
+    };
+
+    let mut files: Vec<SettingsFile> = entries
+        .flatten()
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| SettingsFile::parse(&e.file_name().to_string_lossy()))
+        .collect();
+    files.sort_by(|a, b| a.file.cmp(&b.file));
+    files
+}
+
+pub(crate) fn find_mod<'a>(query: &str, installed: &'a GameMods) -> Option<&'a Mod> {
+    if let Some(id) = steam_id_from(query) {
+        return installed_with_id(installed, id).into_iter().next();
+    }
+
+    installed.iter().find(|m| {
+        m.name.eq_ignore_ascii_case(query)
+            || m.package_id
+                .as_ref()
+                .is_some_and(|p| p.eq_ignore_ascii_case(query))
+            || Path::new(&m.path)
+                .file_name()
+                .is_some_and(|folder| folder.to_string_lossy() == query)
+    })
+}
+
+/// The files of `dir` that belong to the mod named by `query`, or all of them. A mod that is not
+/// installed anymore can still be named by the identifier in its file names
+fn select(
+    dir: &Path,
+    query: Option<&str>,
+    installed: &GameMods,
+) -> Result<Vec<SettingsFile>, String> {
+    let files = settings_files(dir);
+    let Some(query) = query else {
+        return Ok(files);
+    };
+
+    if let Some(m) = find_mod(query, installed) {
+        return Ok(files.into_iter().filter(|f| f.belongs_to(m)).collect());
+    }
+
+    let files: Vec<SettingsFile> = files
+        .into_iter()
+        .filter(|f| f.identifier.eq_ignore_ascii_case(query))
+        .collect();
+    if files.is_empty() {
+        Err(format!(
+            "\"{query}\" is not an installed mod nor the identifier of a settings file"
+        ))
+    } else {
+        Ok(files)
+    }
+}
+
+fn owner<'a>(file: &SettingsFile, installed: &'a GameMods) -> Option<&'a Mod> {
+    installed.iter().find(|m| file.belongs_to(m))
+}
+
+pub fn settings_backups_dir() -> PathBuf {
+    rrm_installer::get_or_create_data_dir().join("settings")
+}
+
+/// Copies `files` of `config_dir` into a new folder of the settings backup store
+fn backup(config_dir: &Path, files: &[SettingsFile]) -> io::Result<PathBuf> {
+    let mut created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    while settings_backups_dir().join(created.to_string()).exists() {
+        created += 1;
+    }
+
+    let destination = settings_backups_dir().join(created.to_string());
+    std::fs::create_dir_all(&destination)?;
+    for f in files {
+        std::fs::copy(config_dir.join(&f.file), destination.join(&f.file))?;
+    }
+
+    Ok(destination)
+}
+
+/// Backs up the settings of `m` before it is replaced by an update
+pub fn backup_mod(i: &Installer, m: &Mod) {
+    let Some(config_dir) = rimworld_config_dir(i) else {
+        return;
+    };
+    let files: Vec<SettingsFile> = settings_files(&config_dir)
+        .into_iter()
+        .filter(|f| f.belongs_to(m))
+        .collect();
+    if files.is_empty() {
+        return;
+    }
+
+    match backup(&config_dir, &files) {
+        Ok(path) => log!(Backup: "Settings of {} saved at \"{}\"", m.name, path.display()),
+        Err(err) => log!(Warning: "Could not back up the settings of {}: {}", m.name, err),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The lines to remove from `old` and add to get `new`, by their longest common subsequence
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut result = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            result.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            result.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            result.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Relative paths given by the user are relative to where rrm was started
+fn user_path(path: PathBuf) -> PathBuf {
+    if path.is_relative() {
+        rrm_installer::working_dir().join(path)
+    } else {
+        path
+    }
+}
+
+fn list(installed: &GameMods, config_dir: &Path, files: Vec<SettingsFile>) {
+    if files.is_empty() {
+        println!("No mod settings found in \"{}\"", config_dir.display());
+        return;
+    }
+
+    println!("{:<48}   Mod", "File");
+    println!("{:<48}   ---", "----");
+    for f in files {
+        let m = owner(&f, installed)
+            .map(|m| m.name.clone())
+            .unwrap_or_else(|| "(not installed)".to_string());
+        println!("{:<48}   {}", f.file, m);
+    }
+}
+
+fn export(config_dir: &Path, files: &[SettingsFile], to: &Path) -> Result<(), ExitCode> {
+    if files.is_empty() {
+        log!(Warning: "No mod settings to export");
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(to).map_err(|err| {
+        log!(Error: "Could not create \"{}\": {}", to.display(), err);
+        ExitCode::FAILURE
+    })?;
+
+    for f in files {
+        if let Err(err) = std::fs::copy(config_dir.join(&f.file), to.join(&f.file)) {
+            log!(Error: "Could not export {}: {}", f.file, err);
+            return Err(ExitCode::FAILURE);
+        }
+    }
+    log!(Done: "Exported {} settings files to \"{}\"", files.len(), to.display());
+    Ok(())
+}
+
+pub fn import(
+    installed: &GameMods,
+    config_dir: &Path,
+    files: &[SettingsFile],
+    from: &Path,
+    dry_run: bool,
+) -> Result<(), ExitCode> {
+    let changed: Vec<&SettingsFile> = files
+        .iter()
+        .filter(|f| {
+            std::fs::read(from.join(&f.file)).ok() != std::fs::read(config_dir.join(&f.file)).ok()
+        })
+        .collect();
+
+    if changed.is_empty() {
+        log!(Status: "The settings already match \"{}\"", from.display());
+        return Ok(());
+    }
+
+    for f in &changed {
+        if owner(f, installed).is_none() {
+            log!(Warning: "{} belongs to a mod that is not installed", f.file);
+        }
+    }
+
+    if dry_run {
+        for f in &changed {
+            log!(Copy: "{}", f.file);
+        }
+        return Ok(());
+    }
+
+    let replaced: Vec<SettingsFile> = changed
+        .iter()
+        .filter(|f| config_dir.join(&f.file).exists())
+        .map(|f| (*f).clone())
+        .collect();
+    if !replaced.is_empty() {
+        match backup(config_dir, &replaced) {
+            Ok(path) => log!(Backup: "Current settings saved at \"{}\"", path.display()),
+            Err(err) => {
+                log!(Error: "Could not back up the current settings, nothing was imported: {}", err);
+                return Err(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    for f in &changed {
+        if let Err(err) = std::fs::copy(from.join(&f.file), config_dir.join(&f.file)) {
+            log!(Error: "Could not import {}: {}", f.file, err);
+            return Err(ExitCode::FAILURE);
+        }
+        log!(Copy: "{}", f.file);
+    }
+    log!(Done: "Imported {} settings files", changed.len());
+    Ok(())
+}
+
+fn diff(config_dir: &Path, files: &[SettingsFile], from: &Path) {
+    let mut differences = 0;
+
+    for f in files {
+        let current = std::fs::read_to_string(config_dir.join(&f.file)).ok();
+        let other = std::fs::read_to_string(from.join(&f.file)).ok();
+
+        match (current, other) {
+            (Some(current), Some(other)) if current != other => {
+                differences += 1;
+                println!("~ {}", f.file);
+                for line in diff_lines(&current, &other) {
+                    match line {
+                        Line::Removed(line) => println!("  - {line}"),
+                        Line::Added(line) => println!("  + {line}"),
+                        Line::Same(_) => {}
+                    }
+                }
+            }
+            (Some(_), None) => {
+                differences += 1;
+                println!("- {} is not in \"{}\"", f.file, from.display());
+            }
+            (None, Some(_)) => {
+                differences += 1;
+                println!("+ {} is only in \"{}\"", f.file, from.display());
+            }
+            _ => {}
+        }
+    }
+
+    if differences == 0 {
+        log!(Status: "The settings match \"{}\"", from.display());
+    }
+}
+
+fn reset(
+    config_dir: &Path,
+    files: &[SettingsFile],
+    dry_run: bool,
+    yes: bool,
+) -> Result<(), ExitCode> {
+    if files.is_empty() {
+        log!(Status: "There are no settings to reset");
+        return Ok(());
+    }
+
+    for f in files {
+        log!(Remove: "{}", f.file);
+    }
+    if dry_run
+        || !yes && !crate::modpack::confirm("The game will use the default settings, continue?")
+    {
+        return Ok(());
+    }
+
+    match backup(config_dir, files) {
+        Ok(path) => log!(Backup: "Settings saved at \"{}\"", path.display()),
+        Err(err) => {
+            log!(Error: "Could not back up the settings, nothing was reset: {}", err);
+            return Err(ExitCode::FAILURE);
+        }
+    }
+
+    for f in files {
+        if let Err(err) = std::fs::remove_file(config_dir.join(&f.file)) {
+            log!(Error: "Could not remove {}: {}", f.file, err);
+            return Err(ExitCode::FAILURE);
+        }
+    }
+    log!(Done: "Reset {} settings files", files.len());
+    Ok(())
+}
+
+pub fn settings(i: Installer, command: Settings) -> Result<(), ExitCode> {
+    let Some(config_dir) = rimworld_config_dir(&i) else {
+        log!(Error: "Could not find RimWorld's Config folder, set it with `rrm config set user-data-dir`");
+        return Err(ExitCode::FAILURE);
+    };
+    let installed = GameMods::from(i.rim_install.clone().unwrap());
+
+    let select = |query: Option<&str>, dir: &Path| {
+        select(dir, query, &installed).map_err(|err| {
+            log!(Error: "{}", err);
+            ExitCode::FAILURE
+        })
+    };
+
+    match command {
+        Settings::List { r#mod } => {
+            let files = select(r#mod.as_deref(), &config_dir)?;
+            list(&installed, &config_dir, files);
+            Ok(())
+        }
+        Settings::Export { r#mod, to } => {
+            let files = select(r#mod.as_deref(), &config_dir)?;
+            export(&config_dir, &files, &user_path(to))
+        }
+        Settings::Import {
+            r#mod,
+            from,
+            dry_run,
+        } => {
+            let from = user_path(from);
+            let files = select(r#mod.as_deref(), &from)?;
+            import(&installed, &config_dir, &files, &from, dry_run)
+        }
+        Settings::Diff { r#mod, from } => {
+            let from = user_path(from);
+            let mut files = select(r#mod.as_deref(), &config_dir)?;
+            for f in select(r#mod.as_deref(), &from).unwrap_or_default() {
+                if !files.contains(&f) {
+                    files.push(f);
+                }
+            }
+            files.sort_by(|a, b| a.file.cmp(&b.file));
+            diff(&config_dir, &files, &from);
+            Ok(())
+        }
+        Settings::Reset {
+            r#mod,
+            dry_run,
+            yes,
+        } => {
+            let files = select(Some(&r#mod), &config_dir)?;
+            reset(&config_dir, &files, dry_run, yes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Line, SettingsFile, diff_lines};
+    use crate::utils::Mod;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            SettingsFile::parse("Mod_brrainz.harmony_HarmonySettings.xml"),
+            Some(SettingsFile {
+                file: "Mod_brrainz.harmony_HarmonySettings.xml".to_string(),
+                identifier: "brrainz.harmony".to_string(),
+                class: "HarmonySettings".to_string(),
+            })
+        );
+        assert_eq!(
+            SettingsFile::parse("Mod_Some_Folder_Settings.xml").map(|f| f.identifier),
+            Some("Some_Folder".to_string())
+        );
+        assert_eq!(SettingsFile::parse("ModsConfig.xml"), None);
+        assert_eq!(SettingsFile::parse("Mod_NoClass.xml"), None);
+    }
+
+    #[test]
+    fn test_belongs_to() {
+        let m = Mod {
+            path: "/games/RimWorld/Mods/2009463077".to_string(),
+            name: "Harmony".to_string(),
+            author: "Brrainz".to_string(),
+            steam_id: "2009463077".to_string(),
+            version: None,
+            package_id: Some("brrainz.harmony".to_string()),
+            identifier: None,
+            dependencies: None,
+        };
+
+        let by_package_id = SettingsFile::parse("Mod_Brrainz.Harmony_Settings.xml").unwrap();
+        let by_folder = SettingsFile::parse("Mod_2009463077_Settings.xml").unwrap();
+        let other = SettingsFile::parse("Mod_unlimitedhugs.hugslib_Settings.xml").unwrap();
+        assert!(by_package_id.belongs_to(&m));
+        assert!(by_folder.belongs_to(&m));
+        assert!(!other.belongs_to(&m));
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "<a>\n<b>1</b>\n<c>2</c>\n</a>";
+        let new = "<a>\n<b>3</b>\n<c>2</c>\n<d/>\n</a>";
+
+        assert_eq!(
+            diff_lines(old, new),
+            vec![
+                Line::Same("<a>"),
+                Line::Removed("<b>1</b>"),
+                Line::Added("<b>3</b>"),
+                Line::Same("<c>2</c>"),
+                Line::Added("<d/>"),
+                Line::Same("</a>"),
+            ]
+        );
+    }
+}
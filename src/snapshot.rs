@@ -0,0 +1,735 @@
+use crate::printf;
+use crate::utils::*;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+use text_io::try_read;
+
+const HEADER: &str = "# rrm snapshot v1";
+
+/// The part of the game a snapshot entry belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Root {
+    /// `GamePath/Mods`
+    Mods,
+    /// The game's `Config/` folder, only `ModsConfig.xml` and `Mod_*.xml`
+    Config,
+}
+
+impl Root {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Root::Mods => "mods",
+            Root::Config => "config",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Root> {
+        match s {
+            "mods" => Some(Root::Mods),
+            "config" => Some(Root::Config),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub root: Root,
+    /// `None` for directories
+    pub hash: Option<String>,
+    pub size: u64,
+    /// Relative to the root, always with `/` separators
+    pub path: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub name: String,
+    pub created: u64,
+    pub game_path: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Snapshot {
+    pub fn size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+
+    pub fn to_manifest(&self) -> String {
+        let mut result = format!(
+            "{HEADER}\ncreated\t{}\ngame\t{}\n",
+            self.created, self.game_path
+        );
+
+        self.entries.iter().for_each(|e| {
+            result.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                e.root.as_str(),
+                e.hash.as_deref().unwrap_or("-"),
+                e.size,
+                e.path
+            ));
+        });
+
+        result
+    }
+
+    pub fn from_manifest(name: &str, contents: &str) -> Option<Snapshot> {
+        let mut lines = contents.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+
+        let mut snapshot = Snapshot {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        for line in lines {
+            let fields: Vec<&str> = line.splitn(4, '\t').collect();
+            match fields.as_slice() {
+                ["created", created] => snapshot.created = created.parse().ok()?,
+                ["game", path] => snapshot.game_path = path.to_string(),
+                [root, hash, size, path] => snapshot.entries.push(Entry {
+                    root: Root::parse(root)?,
+                    hash: (*hash != "-").then(|| hash.to_string()),
+                    size: size.parse().ok()?,
+                    path: path.to_string(),
+                }),
+                _ => return None,
+            }
+        }
+
+        Some(snapshot)
+    }
+}
+
+pub fn snapshots_dir() -> PathBuf {
+    rrm_installer::get_or_create_data_dir().join("snapshots")
+}
+
+fn objects_dir() -> PathBuf {
+    snapshots_dir().join("objects")
+}
+
+fn object_path(hash: &str) -> PathBuf {
+    object_in(&objects_dir(), hash)
+}
+
+fn object_in(objects: &Path, hash: &str) -> PathBuf {
+    objects.join(&hash[..2]).join(hash)
+}
+
+fn manifest_path(name: &str) -> PathBuf {
+    snapshots_dir().join(format!("{name}.snapshot"))
+}
+
+pub(crate) fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Lists every file and folder under `dir`, relative to it
+pub(crate) fn walk(
+    dir: &Path,
+    prefix: &str,
+    result: &mut Vec<(String, PathBuf)>,
+) -> io::Result<()> {
+    let mut entries: Vec<_> = dir.read_dir()?.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let rel = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        let path = entry.path();
+        result.push((rel.clone(), path.clone()));
+        if path.is_dir() {
+            walk(&path, &format!("{rel}/"), result)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_config_file(name: &str) -> bool {
+    name == "ModsConfig.xml" || name.starts_with("Mod_") && name.ends_with(".xml")
+}
+
+/// Files and folders of each root that a snapshot captures
+fn tracked_files(
+    mods_dir: &Path,
+    config_dir: Option<&Path>,
+) -> io::Result<Vec<(Root, String, PathBuf)>> {
+    let mut result = vec![];
+
+    let mut mods = vec![];
+    walk(mods_dir, "", &mut mods)?;
+    mods.into_iter()
+        .filter(|(rel, _)| !rel.starts_with(".rrm-"))
+        .for_each(|(rel, path)| result.push((Root::Mods, rel, path)));
+
+    if let Some(config_dir) = config_dir
+        && let Ok(files) = config_dir.read_dir()
+    {
+        let mut files: Vec<_> = files.flatten().collect();
+        files.sort_by_key(|e| e.file_name());
+        files
+            .into_iter()
+            .filter(|f| f.path().is_file() && is_config_file(&f.file_name().to_string_lossy()))
+            .for_each(|f| {
+                result.push((
+                    Root::Config,
+                    f.file_name().to_string_lossy().to_string(),
+                    f.path(),
+                ))
+            });
+    }
+
+    Ok(result)
+}
+
+/// Copies `path` into the object store unless an object with the same content is already there
+fn store_object(path: &Path, hash: &str) -> io::Result<()> {
+    let object = object_path(hash);
+    if object.exists() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(object.parent().unwrap())?;
+    let tmp = object.with_extension("tmp");
+    std::fs::copy(path, &tmp)?;
+    std::fs::rename(tmp, object)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "objects"
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+pub fn load(name: &str) -> Option<Snapshot> {
+    if !valid_name(name) {
+        return None;
+    }
+    let contents = std::fs::read_to_string(manifest_path(name)).ok()?;
+    Snapshot::from_manifest(name, &contents)
+}
+
+pub fn list_snapshots() -> Vec<Snapshot> {
+    let Ok(files) = snapshots_dir().read_dir() else {
+        return vec![];
+    };
+
+    let mut result: Vec<Snapshot> = files
+        .flatten()
+        .filter_map(|f| {
+            let name = f.file_name().to_string_lossy().to_string();
+            load(name.strip_suffix(".snapshot")?)
+        })
+        .collect();
+
+    result.sort_by_key(|s| s.created);
+    result
+}
+
+pub fn create(i: Installer, name: Option<String>) {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let name = name.unwrap_or_else(|| created.to_string());
+
+    if !valid_name(&name) {
+        log!(Error: "Invalid snapshot name \"{}\", use letters, numbers, `-`, `_` and `.`", name);
+        return;
+    }
+
+    if manifest_path(&name).exists() {
+        log!(Error: "A snapshot named \"{}\" already exists", name);
+        return;
+    }
+
+    let rim_install = i.rim_install.as_ref().unwrap();
+    let config_dir = rimworld_config_dir(&i);
+    if config_dir.is_none() {
+        log!(Warning: "Could not find the RimWorld Config folder, only Mods/ will be saved");
+    }
+
+    let files = tracked_files(&rim_install.path().join("Mods"), config_dir.as_deref())
+        .unwrap_or_else(|err| {
+            log!(Error: "Could not read the mods folder: {}", err);
+            std::process::exit(1);
+        });
+
+    let mut snapshot = Snapshot {
+        name: name.clone(),
+        created,
+        game_path: rim_install.path().display().to_string(),
+        entries: vec![],
+    };
+
+    for (root, rel, path) in files {
+        if path.is_dir() {
+            snapshot.entries.push(Entry {
+                root,
+                hash: None,
+                size: 0,
+                path: rel,
+            });
+            continue;
+        }
+
+        let result = hash_file(&path).and_then(|hash| store_object(&path, &hash).map(|_| hash));
+        match result {
+            Ok(hash) => snapshot.entries.push(Entry {
+                root,
+                hash: Some(hash),
+                size: path.metadata().map(|m| m.len()).unwrap_or_default(),
+                path: rel,
+            }),
+            Err(err) => {
+                log!(Error: "Could not save \"{}\": {}", path.display(), err);
+                return;
+            }
+        }
+    }
+
+    let manifest = snapshot.to_manifest();
+    if let Err(err) = std::fs::create_dir_all(snapshots_dir())
+        .and_then(|_| File::create(manifest_path(&name)))
+        .and_then(|mut f| f.write_all(manifest.as_bytes()))
+    {
+        log!(Error: "Could not write the snapshot: {}", err);
+        return;
+    }
+
+    log!(Created: "Snapshot \"{}\" ({} files, {})", name, snapshot.entries.iter().filter(|e| e.hash.is_some()).count(), rrm_scrap::human_size(snapshot.size()));
+}
+
+pub fn list() {
+    let snapshots = list_snapshots();
+
+    if snapshots.is_empty() {
+        println!("No snapshots found");
+        return;
+    }
+
+    println!(
+        "{:<24} {:<20}   {:>10}   Game path",
+        "Name", "Created", "Size"
+    );
+    println!(
+        "{:<24} {:<20}   {:>10}   ---------",
+        "----", "-------", "----"
+    );
+    for s in snapshots {
+        println!(
+            "{:<24} {:<20}   {:>10}   {}",
+            s.name,
+            rrm_scrap::format_timestamp(s.created),
+            rrm_scrap::human_size(s.size()),
+            s.game_path
+        );
+    }
+}
+
+/// What restoring a snapshot changes in one root
+#[derive(Debug, Default, PartialEq)]
+pub struct RestorePlan {
+    pub remove: Vec<PathBuf>,
+    pub write: Vec<(PathBuf, String)>,
+    pub create_dirs: Vec<PathBuf>,
+}
+
+fn plan_restore(
+    snapshot: &Snapshot,
+    mods_dir: &Path,
+    config_dir: Option<&Path>,
+) -> io::Result<RestorePlan> {
+    let mut plan = RestorePlan::default();
+
+    let root_dir = |root: Root| match root {
+        Root::Mods => Some(mods_dir),
+        Root::Config => config_dir,
+    };
+
+    let wanted: HashMap<(Root, &str), &Entry> = snapshot
+        .entries
+        .iter()
+        .map(|e| ((e.root, e.path.as_str()), e))
+        .collect();
+
+    let current = tracked_files(mods_dir, config_dir)?;
+    let present: HashSet<(Root, &str)> = current
+        .iter()
+        .map(|(r, rel, _)| (*r, rel.as_str()))
+        .collect();
+
+    for (root, rel, path) in &current {
+        match wanted.get(&(*root, rel.as_str())) {
+            None => {
+                // Contents of a removed folder go with it
+                if !plan.remove.iter().any(|removed| path.starts_with(removed)) {
+                    plan.remove.push(path.clone());
+                }
+            }
+            Some(entry) => match &entry.hash {
+                None if !path.is_dir() => {
+                    plan.remove.push(path.clone());
+                    plan.create_dirs.push(path.clone());
+                }
+                Some(hash)
+                    if path.is_dir()
+                        || entry.size != path.metadata()?.len()
+                        || &hash_file(path)? != hash =>
+                {
+                    if path.is_dir() {
+                        plan.remove.push(path.clone());
+                    }
+                    plan.write.push((path.clone(), hash.clone()));
+                }
+                _ => {}
+            },
+        }
+    }
+
+    for entry in &snapshot.entries {
+        if present.contains(&(entry.root, entry.path.as_str())) {
+            continue;
+        }
+
+        let Some(dir) = root_dir(entry.root) else {
+            continue;
+        };
+
+        let path = dir.join(&entry.path);
+        match &entry.hash {
+            None => plan.create_dirs.push(path),
+            Some(hash) => plan.write.push((path, hash.clone())),
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Whether `path` is a whole mod, which goes to the backup store instead of being deleted
+fn is_mod_folder(path: &Path, mods_dir: &Path) -> bool {
+    path.parent() == Some(mods_dir) && path.is_dir()
+}
+
+/// Applies `plan` with the objects stored in `objects`, changing nothing if one of them is missing.
+/// Mods the snapshot does not have are moved to the backup store in `backups`
+fn apply_restore(
+    plan: &RestorePlan,
+    objects: &Path,
+    mods_dir: &Path,
+    backups: &Path,
+) -> io::Result<()> {
+    if let Some((_, hash)) = plan
+        .write
+        .iter()
+        .find(|(_, hash)| !object_in(objects, hash).is_file())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("object {hash} is missing"),
+        ));
+    }
+
+    for path in &plan.remove {
+        if is_mod_folder(path, mods_dir) {
+            let folder = path.file_name().unwrap().to_string_lossy();
+            crate::backup::store_in(backups, path, &folder)?;
+        } else if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    for dir in &plan.create_dirs {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    for (path, hash) in &plan.write {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::copy(object_in(objects, hash), path)?;
+    }
+
+    Ok(())
+}
+
+pub fn restore(i: Installer, name: String, dry_run: bool, yes: bool) -> Result<(), ExitCode> {
+    let Some(snapshot) = load(&name) else {
+        log!(Error: "No snapshot named \"{}\"", name);
+        return Err(ExitCode::FAILURE);
+    };
+
+    if let Some(missing) = snapshot
+        .entries
+        .iter()
+        .filter_map(|e| e.hash.as_ref())
+        .find(|hash| !object_path(hash).exists())
+    {
+        log!(Error: "Snapshot \"{}\" is damaged, object {} is missing", name, missing);
+        return Err(ExitCode::FAILURE);
+    }
+
+    // Restoring removes every mod the snapshot does not have
+    let rim_install = i.rim_install.as_ref().unwrap();
+    if Path::new(&snapshot.game_path) != rim_install.path() {
+        log!(Error: "Snapshot \"{}\" was taken of \"{}\", not of \"{}\"", name, snapshot.game_path, rim_install.path().display());
+        return Err(ExitCode::FAILURE);
+    }
+    let config_dir = rimworld_config_dir(&i);
+    if config_dir.is_none() && snapshot.entries.iter().any(|e| e.root == Root::Config) {
+        log!(Warning: "Could not find the RimWorld Config folder, only Mods/ will be restored");
+    }
+
+    let mods_dir = rim_install.path().join("Mods");
+    let plan = plan_restore(&snapshot, &mods_dir, config_dir.as_deref()).map_err(|err| {
+        log!(Error: "Could not compare the snapshot with the current files: {}", err);
+        ExitCode::FAILURE
+    })?;
+
+    if plan == RestorePlan::default() {
+        log!(Status: "Nothing to do, the mods already match \"{}\"", name);
+        return Ok(());
+    }
+
+    if dry_run {
+        log!(Warning: "Dry run, nothing will be changed");
+        plan.remove.iter().for_each(|p| {
+            if is_mod_folder(p, &mods_dir) {
+                log!(Backup: "\"{}\"", p.display())
+            } else {
+                log!(Remove: "\"{}\"", p.display())
+            }
+        });
+        plan.create_dirs
+            .iter()
+            .for_each(|p| log!(Create: "\"{}\"", p.display()));
+        plan.write
+            .iter()
+            .for_each(|(p, _)| log!(Write: "\"{}\"", p.display()));
+        return Ok(());
+    }
+
+    log!(Status: "Restoring \"{}\" removes {} and writes {} files or folders", name, plan.remove.len(), plan.write.len() + plan.create_dirs.len());
+    if !yes {
+        printf!("Want to continue? [y/n]: ");
+        let n: String = loop {
+            let read: Result<String, _> = try_read!();
+            if let Ok(read) = read {
+                break read;
+            } else {
+                log!(Error: "Somehting wrong happened. Re-input your answer.");
+            };
+        };
+
+        if n != "yes" && n != "y" {
+            return Ok(());
+        }
+    }
+
+    match apply_restore(
+        &plan,
+        &objects_dir(),
+        &mods_dir,
+        &crate::backup::backups_dir(),
+    ) {
+        Ok(()) => {
+            log!(Restored: "Snapshot \"{}\", removed mods are in the backup store", name);
+            Ok(())
+        }
+        Err(err) => {
+            log!(Error: "Could not restore \"{}\": {}", name, err);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+pub fn delete(name: String) {
+    if !valid_name(&name) {
+        log!(Error: "Invalid snapshot name \"{}\", use letters, numbers, `-`, `_` and `.`", name);
+        return;
+    }
+
+    if std::fs::remove_file(manifest_path(&name)).is_err() {
+        log!(Error: "No snapshot named \"{}\"", name);
+        return;
+    }
+
+    // Drop the objects no other snapshot uses
+    let used: HashSet<String> = list_snapshots()
+        .into_iter()
+        .flat_map(|s| s.entries.into_iter().filter_map(|e| e.hash))
+        .collect();
+
+    let mut freed = 0;
+    if let Ok(prefixes) = objects_dir().read_dir() {
+        for prefix in prefixes.flatten() {
+            for object in prefix.path().read_dir().into_iter().flatten().flatten() {
+                if !used.contains(&*object.file_name().to_string_lossy()) {
+                    freed += object.metadata().map(|m| m.len()).unwrap_or_default();
+                    let _ = std::fs::remove_file(object.path());
+                }
+            }
+            let _ = std::fs::remove_dir(prefix.path());
+        }
+    }
+
+    log!(Removed: "Snapshot \"{}\", freed {}", name, rrm_scrap::human_size(freed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Entry, RestorePlan, Root, Snapshot, apply_restore, hash_file, load, object_in,
+        plan_restore, valid_name,
+    };
+    use std::path::Path;
+
+    /// Stores `contents` as an object and returns its entry
+    fn object(objects: &Path, path: &str, contents: &str) -> Entry {
+        let file = objects.join("tmp");
+        std::fs::create_dir_all(objects).unwrap();
+        std::fs::write(&file, contents).unwrap();
+        let hash = hash_file(&file).unwrap();
+        let object = object_in(objects, &hash);
+        std::fs::create_dir_all(object.parent().unwrap()).unwrap();
+        std::fs::rename(&file, object).unwrap();
+
+        Entry {
+            root: Root::Mods,
+            hash: Some(hash),
+            size: contents.len() as u64,
+            path: path.to_string(),
+        }
+    }
+
+    fn folder(path: &str) -> Entry {
+        Entry {
+            root: Root::Mods,
+            hash: None,
+            size: 0,
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let snapshot = Snapshot {
+            name: "before-1.5".to_string(),
+            created: 1709312640,
+            game_path: "/games/RimWorld".to_string(),
+            entries: vec![
+                Entry {
+                    root: Root::Mods,
+                    hash: None,
+                    size: 0,
+                    path: "Harmony".to_string(),
+                },
+                Entry {
+                    root: Root::Mods,
+                    hash: Some("ab".repeat(32)),
+                    size: 12,
+                    path: "Harmony/About/About.xml".to_string(),
+                },
+                Entry {
+                    root: Root::Config,
+                    hash: Some("cd".repeat(32)),
+                    size: 40,
+                    path: "ModsConfig.xml".to_string(),
+                },
+            ],
+        };
+
+        let manifest = snapshot.to_manifest();
+        assert_eq!(
+            Snapshot::from_manifest("before-1.5", &manifest),
+            Some(snapshot)
+        );
+    }
+
+    #[test]
+    fn test_valid_name() {
+        assert!(valid_name("before-1.5"));
+        assert_eq!(load("../config"), None);
+        assert!(!valid_name("../config"));
+        assert!(!valid_name("objects"));
+        assert!(!valid_name(""));
+    }
+
+    #[test]
+    fn test_restore() {
+        let dir = std::env::temp_dir().join(format!("rrm_snapshot_{}", std::process::id()));
+        let (objects, mods) = (dir.join("objects"), dir.join("Mods"));
+        let snapshot = Snapshot {
+            entries: vec![
+                folder("Harmony"),
+                object(&objects, "Harmony/About.xml", "<ModMetaData/>"),
+                folder("Harmony/Assemblies"),
+            ],
+            ..Default::default()
+        };
+
+        // Changed, added and removed files, and a file where a folder was
+        std::fs::create_dir_all(mods.join("Harmony")).unwrap();
+        std::fs::write(mods.join("Harmony/About.xml"), "changed").unwrap();
+        std::fs::write(mods.join("Harmony/Assemblies"), "").unwrap();
+        std::fs::create_dir_all(mods.join("HugsLib/About")).unwrap();
+
+        let plan = plan_restore(&snapshot, &mods, None).unwrap();
+        assert_eq!(
+            plan.remove,
+            [mods.join("Harmony/Assemblies"), mods.join("HugsLib")]
+        );
+        assert_eq!(plan.create_dirs, [mods.join("Harmony/Assemblies")]);
+        assert_eq!(plan.write.len(), 1);
+
+        let backups = dir.join("backups");
+        apply_restore(&plan, &objects, &mods, &backups).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(mods.join("Harmony/About.xml")).unwrap(),
+            "<ModMetaData/>"
+        );
+        assert!(mods.join("Harmony/Assemblies").is_dir());
+        assert!(!mods.join("HugsLib").exists());
+        assert!(
+            std::fs::read_dir(backups.join("HugsLib"))
+                .unwrap()
+                .flatten()
+                .any(|version| version.path().join("HugsLib/About").is_dir())
+        );
+        assert_eq!(
+            plan_restore(&snapshot, &mods, None).unwrap(),
+            RestorePlan::default()
+        );
+
+        // Nothing is removed when an object to write is missing
+        std::fs::create_dir_all(mods.join("HugsLib")).unwrap();
+        std::fs::remove_dir_all(&objects).unwrap();
+        std::fs::write(mods.join("Harmony/About.xml"), "changed").unwrap();
+        let plan = plan_restore(&snapshot, &mods, None).unwrap();
+        assert!(apply_restore(&plan, &objects, &mods, &backups).is_err());
+        assert!(mods.join("HugsLib").exists());
+        assert_eq!(
+            std::fs::read_to_string(mods.join("Harmony/About.xml")).unwrap(),
+            "changed"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
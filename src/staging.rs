@@ -0,0 +1,100 @@
+use crate::interrupt::{keep_on_interrupt, remove_on_interrupt};
+use crate::running::is_running;
+use crate::utils::*;
+
+/// Removes the folders of runs that were killed before they could clean up
+fn remove_stale(base: &Path) {
+    let Ok(entries) = std::fs::read_dir(base) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let pid = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix("run-"))
+            .and_then(|pid| pid.parse::<u32>().ok());
+
+        if let Some(pid) = pid
+            && pid != std::process::id()
+            && !is_running(pid)
+        {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// The folder steamcmd downloads into during one run of rrm, inside `download-dir`. It is
+/// removed when dropped or on Ctrl-C
+#[derive(Debug)]
+pub struct Staging {
+    dir: PathBuf,
+}
+
+impl Staging {
+    pub fn create(i: &Installer) -> Result<Staging, String> {
+        Staging::in_dir(&i.download_dir())
+    }
+
+    fn in_dir(base: &Path) -> Result<Staging, String> {
+        remove_stale(base);
+        let dir = base.join(format!("run-{}", std::process::id()));
+        // Left by an earlier run that had the same PID
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)
+            .map_err(|err| format!("Could not create \"{}\": {err}", dir.display()))?;
+
+        remove_on_interrupt(&dir);
+        Ok(Staging { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// steamcmd's `steamapps/workshop` folder
+    pub fn workshop_dir(&self) -> PathBuf {
+        self.dir.join("Steam").join("steamapps").join("workshop")
+    }
+
+    /// Where each downloaded mod ends up, in a folder named after its ID
+    pub fn content_dir(&self) -> PathBuf {
+        self.workshop_dir().join("content").join("294100")
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        keep_on_interrupt(&self.dir);
+
+        if let Err(err) = std::fs::remove_dir_all(&self.dir)
+            && self.dir.exists()
+        {
+            log!(Warning: "Could not remove \"{}\": {}", self.dir.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Staging;
+
+    #[test]
+    fn test_staging() {
+        let tmp = std::env::temp_dir().join(format!("rrm_staging_{}", std::process::id()));
+        let base = tmp.join("downloads");
+        let stale = base.join(format!("run-{}", i32::MAX));
+        std::fs::create_dir_all(&stale).unwrap();
+
+        let staging = Staging::in_dir(&base).unwrap();
+        assert!(!stale.exists());
+        assert!(staging.dir().is_dir());
+        assert!(staging.content_dir().starts_with(staging.dir()));
+
+        let dir = staging.dir().to_path_buf();
+        drop(staging);
+        assert!(!dir.exists());
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+use crate::printf;
+use crate::utils::*;
+use rrm_installer::steamcmd::{self, Origin, SteamCmdError};
+use rrm_scrap::Http;
+use std::process::ExitCode;
+
+/// Reads a line from the terminal without showing what is typed
+fn read_secret(prompt: &str) -> String {
+    printf!("{}: ", prompt);
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    let stty = |arg: &str| {
+        std::process::Command::new("stty")
+            .arg(arg)
+            .stdin(std::process::Stdio::inherit())
+            .status()
+    };
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    let _ = stty("-echo");
+    let mut line = String::new();
+    let _ = std::io::stdin().read_line(&mut line);
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    let _ = stty("echo");
+
+    println!();
+    line.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Asks for a Steam Guard code, which steamcmd waits for
+pub fn read_code(what: &str) -> String {
+    printf!("{}: ", what);
+    let mut line = String::new();
+    let _ = std::io::stdin().read_line(&mut line);
+    line.trim().to_string()
+}
+
+/// The password of `steam-username`, from `steam-password-command` if set or else typed in
+pub fn password(i: &Installer) -> Result<String, String> {
+    let username = i.steamcmd.steam_username.as_deref().unwrap_or_default();
+
+    let Some(command) = &i.steamcmd.steam_password_command else {
+        return Ok(read_secret(&format!("Steam password for {username}")));
+    };
+
+    #[cfg(target_os = "windows")]
+    let output = std::process::Command::new("cmd")
+        .args(["/C", command])
+        .output();
+    #[cfg(not(target_os = "windows"))]
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .output();
+
+    let output = output.map_err(|err| format!("Could not run steam-password-command: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "steam-password-command failed with {}",
+            output.status
+        ));
+    }
+
+    let password = String::from_utf8_lossy(&output.stdout);
+    let password = password.lines().next().unwrap_or_default();
+    if password.is_empty() {
+        return Err("steam-password-command printed no password".to_string());
+    }
+    Ok(password.to_string())
+}
+
+/// Downloads the steamcmd archive from `mirror`, verifies it and extracts it into `dir`
+async fn fetch(http: &Http, mirror: &str, sha256: Option<&str>, dir: &Path) -> Result<(), String> {
+    let url = steamcmd::archive_url(mirror);
+    log!(Download: "{}", url);
+
+    let archive = http.download(&url).await.map_err(|err| err.to_string())?;
+    steamcmd::verify(&archive, sha256)?;
+    steamcmd::install(&archive, dir)
+}
+
+/// Runs the steamcmd in use once, returning the version it reports
+pub fn check(i: &Installer) -> Result<String, SteamCmdError> {
+    steamcmd::probe(
+        &i.get_steamcmd_path(),
+        &rrm_installer::get_or_create_config_dir(),
+    )
+}
+
+/// Downloads the steamcmd managed by rrm and runs it once, so it updates itself before the
+/// first install
+async fn download(i: &Installer) -> Result<(), ExitCode> {
+    if i.offline {
+        log!(Error: "steamcmd can not be downloaded offline");
+        return Err(ExitCode::FAILURE);
+    }
+
+    let dir = steamcmd::managed_dir();
+    fetch(
+        &i.http(),
+        &i.steamcmd.steamcmd_mirror,
+        i.steamcmd.steamcmd_sha256.as_deref(),
+        &dir,
+    )
+    .await
+    .map_err(|err| {
+        log!(Error: "{}", err);
+        ExitCode::FAILURE
+    })?;
+
+    log!(Status: "Updating steamcmd, this can take a while");
+    match check(i) {
+        Ok(version) => {
+            log!(Done: "steamcmd {} is in \"{}\"", version, dir.display());
+            Ok(())
+        }
+        Err(err) => {
+            log!(Error: "{}", err);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Makes sure there is a steamcmd to run, downloading it the first time
+pub async fn ensure(i: &Installer) -> Result<(), ExitCode> {
+    let (path, origin) = i.steamcmd();
+    if path.is_file() {
+        return Ok(());
+    }
+
+    if origin == Origin::Config {
+        log!(Error: "steamcmd-path is \"{}\", which does not exist", path.display());
+        log!(Error: "Fix it with `rrm config set steamcmd-path <PATH>` or remove it with `rrm config unset steamcmd-path`");
+        return Err(ExitCode::FAILURE);
+    }
+
+    log!(Status: "steamcmd is not installed yet, downloading it");
+    download(i).await
+}
+
+pub async fn install(i: Installer) -> Result<(), ExitCode> {
+    if i.get_steamcmd_path().is_file() {
+        log!(Using: "\"{}\", run `rrm steamcmd update` to download it again", i.get_steamcmd_path().display());
+        return Ok(());
+    }
+
+    ensure(&i).await
+}
+
+pub async fn update(i: Installer) -> Result<(), ExitCode> {
+    let (path, origin) = i.steamcmd();
+    if origin != Origin::Managed {
+        log!(Error: "\"{}\" comes from {}, update it the way it was installed, like with your package manager", path.display(), origin);
+        return Err(ExitCode::FAILURE);
+    }
+
+    download(&i).await
+}
+
+pub fn path(i: Installer) -> Result<(), ExitCode> {
+    let (path, origin) = i.steamcmd();
+    println!("{}", path.display());
+
+    if path.is_file() {
+        Ok(())
+    } else if origin == Origin::Config {
+        log!(Warning: "It does not exist, check steamcmd-path");
+        Err(ExitCode::FAILURE)
+    } else {
+        log!(Warning: "Not installed yet, run `rrm steamcmd install`");
+        Err(ExitCode::FAILURE)
+    }
+}
+
+pub fn reset(i: Installer) -> Result<(), ExitCode> {
+    let dir = steamcmd::managed_dir();
+    if !dir.exists() {
+        log!(Warning: "There is no steamcmd installed by rrm in \"{}\"", dir.display());
+        return Ok(());
+    }
+
+    steamcmd::reset().map_err(|err| {
+        log!(Error: "Could not remove \"{}\": {}", dir.display(), err);
+        ExitCode::FAILURE
+    })?;
+    log!(Removed: "\"{}\"", dir.display());
+
+    let (path, origin) = i.steamcmd();
+    if origin != Origin::Managed {
+        log!(Using: "\"{}\" from {}", path.display(), origin);
+    } else {
+        log!(Info: "It will be downloaded again on the next install");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serves `body` to every request on a local port, returning its URL
+    async fn file_server(body: Vec<u8>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 4096];
+                let _ = socket.read(&mut buf).await;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(&body).await;
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn test_fetch() {
+        let tmp = std::env::temp_dir().join(format!("rrm_steamcmd_{}", std::process::id()));
+        let source = tmp.join("source");
+        std::fs::create_dir_all(source.join("linux32")).unwrap();
+        std::fs::write(source.join("steamcmd.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::write(source.join("linux32").join("steamcmd"), "").unwrap();
+
+        let status = std::process::Command::new("tar")
+            .args(["-czf", "../archive.tar.gz", "steamcmd.sh", "linux32"])
+            .current_dir(&source)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let archive = std::fs::read(tmp.join("archive.tar.gz")).unwrap();
+
+        let mirror = file_server(archive.clone()).await;
+        let http = Http::default();
+        let dir = tmp.join("steamcmd");
+
+        fetch(&http, &mirror, Some(&steamcmd::sha256(&archive)), &dir)
+            .await
+            .unwrap();
+        assert!(steamcmd::entry_point(&dir).is_file());
+        assert!(dir.join("linux32").join("steamcmd").is_file());
+
+        // A mismatched hash leaves the installed steamcmd as it was
+        std::fs::remove_file(dir.join("linux32").join("steamcmd")).unwrap();
+        assert!(fetch(&http, &mirror, Some("00"), &dir).await.is_err());
+        assert!(steamcmd::entry_point(&dir).is_file());
+        assert!(!dir.join("linux32").join("steamcmd").exists());
+
+        let not_an_archive = file_server(b"<html>Not Found</html>".to_vec()).await;
+        assert!(fetch(&http, &not_an_archive, None, &dir).await.is_err());
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+}
@@ -1,32 +1,52 @@
 pub use crate::log;
 use regex::Regex;
 pub use rrm_installer::Installer;
-pub use rrm_locals::{DisplayType, GameMods, GamePath, Mod};
+pub use rrm_locals::{DisplayType, GameMods, GamePath, Mod, UserDataPath};
 pub use rrm_scrap::SteamMods;
+use rrm_scrap::{ModSteamDetails, RequiredItem};
 pub use std::path::{Path, PathBuf};
 use std::process::exit;
 
-#[cfg(target_os = "macos")]
-pub const RW_DEFAULT_PATH: [&str; 2] = [
-    r"/Applications/RimWorld.app/",
-    r"~/Library/Application Support/Steam/steamapps/common/RimWorld/RimWorldMac.app/",
-];
-#[cfg(target_os = "linux")]
-pub const RW_DEFAULT_PATH: [&str; 3] = [
-    r"~/GOG Games/RimWorld",
-    r"~/.steam/steam/SteamApps/common/",
-    r"~/.local/share/Steam/steamapps/common/RimWorld",
-];
-#[cfg(target_os = "windows")]
-pub const RW_DEFAULT_PATH: [&str; 2] = [
-    r"C:\Program Files (x86)\Steam\steamapps\common\RimWorld",
-    r"C:\Program Files\Steam\steamapps\common\RimWorld",
-];
+/// Where RimWorld keeps `ModsConfig.xml` and the settings of each mod, from the user data folder
+/// of the selected instance
+pub fn rimworld_config_dir(i: &Installer) -> Option<PathBuf> {
+    i.user_data()
+        .map(|data| data.config_dir())
+        .filter(|path| path.is_dir())
+}
+
+/// What the Workshop says about an installed mod, read from its About.xml instead. Used when
+/// the answer is not cached in offline mode
+pub fn local_details(m: &Mod, id: usize) -> ModSteamDetails {
+    ModSteamDetails {
+        id,
+        title: m.name.clone(),
+        author: m.author.clone(),
+        required_items: m
+            .dependencies
+            .iter()
+            .flatten()
+            .filter_map(|dep| dep.parse().ok())
+            .map(|id: usize| RequiredItem {
+                id,
+                title: id.to_string(),
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// The version of the game installed at `game_path`, like `1.5.4104 rev435`
+pub fn game_version(game_path: &Path) -> Option<String> {
+    std::fs::read_to_string(game_path.join("Version.txt"))
+        .ok()
+        .and_then(|v| v.lines().next().map(|l| l.trim().to_string()))
+}
 
 pub const RW_NOT_FOUND: &str = "\
     Error: Unable to find RimWorld installation path.\n\
     Try specifying the path:\n\
-    \trrm set path <GAME_PATH>        <--- Like this\
+    \trrm config set game-path <GAME_PATH>        <--- Like this\
 ";
 
 pub fn dir_exists(path: &Path) -> bool {
@@ -38,10 +58,15 @@ pub const LIST_DESCRIPTION: &str = "List installed Mods in Path/To/RimWorld/Mods
 #[cfg(target_os = "windows")]
 pub const LIST_DESCRIPTION: &str = r#"List installed Mods in C:\Path\To\RimWorld\Mods"#;
 
-pub fn try_get_path(game_path: Option<&Path>, will_set: bool) -> Installer {
+pub fn try_get_path(
+    game_path: Option<&Path>,
+    will_set: bool,
+    instance: Option<&str>,
+    overrides: &[(String, String)],
+) -> Installer {
     if let Some(game_path) = game_path {
         if dir_exists(game_path) {
-            Installer::new(Some(GamePath::from(game_path))).unwrap()
+            Installer::new(Some(GamePath::from(game_path)), overrides).unwrap()
         } else {
             eprintln!(
                 "Error: \"{}\" is not a valid RimWorld installation path.",
@@ -49,27 +74,28 @@ pub fn try_get_path(game_path: Option<&Path>, will_set: bool) -> Installer {
             );
             exit(1)
         }
-    } else if let Some(installer) = Installer::new(None) {
+    } else if let Some(mut installer) = Installer::new(None, overrides) {
+        if let Err(err) = installer.select_instance(instance) {
+            eprintln!("Error: {err}");
+            exit(1);
+        }
+
         if installer.rim_install.is_some() {
             installer
-        } else if installer.rim_install.is_none() && !will_set {
-            let mut result = None;
-            RW_DEFAULT_PATH.into_iter().for_each(|path| {
-                if dir_exists(&PathBuf::from(path)) {
-                    /*eprintln!(
-                        "Warning: Found RimWorld installation path at {}",
-                        PathBuf::from(path).display()
-                    );*/
-                    let mut installer = Installer::new(None).unwrap();
-                    installer.set_path_value(path.parse().unwrap());
-                    result = Some(installer);
-                }
-            });
-
-            result.unwrap_or_else(|| {
+        } else if installer.rim_install.is_none() && !will_set && installer.instance.is_none() {
+            let Some(found) = crate::detect::detect_installs().into_iter().next() else {
                 eprintln!("{RW_NOT_FOUND}");
                 exit(1);
-            })
+            };
+
+            // Only for this run, `rrm detect --set` saves it
+            eprintln!(
+                "Warning: Using the RimWorld installation found at \"{}\", see `rrm detect --set` to save it",
+                found.path.display()
+            );
+            let mut installer = installer;
+            installer.rim_install = Some(GamePath::from(found.path.as_path()));
+            installer
         } else if installer.rim_install.is_none() && will_set {
             installer
         } else {
@@ -96,12 +122,8 @@ macro_rules! printf {
 #[macro_export]
 macro_rules! search_in_steam {
     ($args: expr, $mods: expr) => {{
-        if $args.filter.is_some() {
-            let value = if $args.filter.as_ref().unwrap().is_some() {
-                $args.filter.as_ref().unwrap().clone().unwrap()
-            } else {
-                $args.r#mod.clone()
-            };
+        if let Some(filter) = &$args.filter {
+            let value = filter.clone().unwrap_or_else(|| $args.r#mod.clone());
 
             $mods.filter_by($args.to_filter_obj(), &value)
         } else {
@@ -123,11 +145,72 @@ pub fn extract_id(m: &str, reg: &Regex) -> Option<String> {
     }
 }
 
+/// Mods whose folder name starts with `_`, like the copies `rrm duplicates` does not keep
+pub fn is_ignored(m: &Mod) -> bool {
+    Path::new(&m.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('_'))
+}
+
+/// Installed mods whose `PublishedFileId.txt` is exactly `id`
+pub fn installed_with_id(mods: &GameMods, id: usize) -> Vec<&Mod> {
+    let id = id.to_string();
+    mods.iter().filter(|m| m.steam_id == id).collect()
+}
+
+/// Gets the Steam ID of a mod given either as the ID itself or as a Workshop URL
+pub fn steam_id_from(identifier: &str) -> Option<usize> {
+    let identifier = identifier.trim();
+    if !identifier.is_empty() && identifier.chars().all(char::is_numeric) {
+        return identifier.parse().ok();
+    }
+
+    if identifier.contains("steamcommunity.com") {
+        let re = Regex::new(r"[a-zA-Z/:.]+[?0-9a-zA-Z0-9/=&]+[\?\&]{1}id=(?P<id>\d+).*").unwrap();
+        let m = identifier.replace(['\n', ' '], "");
+        return extract_id(&m, &re).and_then(|id| id.parse().ok());
+    }
+
+    None
+}
+
+pub fn more_display(output: &str, with_pager: &str) {
+    use std::io::Write;
+
+    let mut more = std::process::Command::new(with_pager)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let more_stdin = more.stdin.as_mut().unwrap();
+    more_stdin
+        .write_all(output.as_bytes())
+        .unwrap_or_else(|err| {
+            eprintln!(
+                "Something went wrong while writing contents to `more`.\n\
+            Error: {err}"
+            )
+        });
+
+    more.wait().unwrap();
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::extract_id;
+    use crate::utils::{extract_id, steam_id_from};
     use regex::Regex;
 
+    #[test]
+    fn test_steam_id_from() {
+        assert_eq!(steam_id_from("2009463077"), Some(2009463077));
+        assert_eq!(
+            steam_id_from("https://steamcommunity.com/sharedfiles/filedetails/?id=2009463077"),
+            Some(2009463077)
+        );
+        assert_eq!(steam_id_from("Harmony"), None);
+    }
+
     #[test]
     fn test_extract_id() {
         let re = Regex::new(r"[a-zA-Z/:.]+\?id=(?P<id>\d+).*").unwrap();